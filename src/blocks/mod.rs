@@ -1,5 +1,7 @@
 mod redstone;
 
+pub use redstone::{RedstoneWire, WireAlgorithm};
+
 use crate::items::{ActionResult, Item, UseOnBlockContext};
 use crate::world::TickPriority;
 use crate::world::World;
@@ -474,6 +476,18 @@ impl Block {
         }
     }
 
+    /// Returns true if using the block changes how the circuit is built, like the delay of
+    /// a repeater, instead of just operating it like a lever
+    pub fn on_use_changes_build(self) -> bool {
+        matches!(
+            self,
+            Block::RedstoneRepeater { .. }
+                | Block::RedstoneComparator { .. }
+                | Block::RedstoneWire { .. }
+                | Block::SeaPickle { .. }
+        )
+    }
+
     pub fn on_use(
         self,
         world: &mut dyn World,
@@ -730,6 +744,7 @@ impl Block {
     }
 
     fn update(self, world: &mut dyn World, pos: BlockPos) {
+        world.on_block_update(pos, self);
         match self {
             Block::RedstoneWire { wire } => {
                 wire.on_neighbor_updated(world, pos);
@@ -1016,6 +1031,44 @@ impl Block {
             _ => {}
         }
     }
+
+    /// Returns the values `set_property` accepts for a property of the block, or `None` if
+    /// the block doesn't have the property
+    pub fn property_values(self, key: &str) -> Option<&'static [&'static str]> {
+        const BOOL: &[&str] = &["true", "false"];
+        const DIRECTION: &[&str] = &["north", "south", "east", "west"];
+        const FACING: &[&str] = &["north", "south", "east", "west", "up", "down"];
+        const FACE: &[&str] = &["floor", "wall", "ceiling"];
+        const SIXTEEN: &[&str] = &[
+            "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14", "15",
+        ];
+        Some(match (self, key) {
+            (Block::RedstoneWire { .. }, "north" | "south" | "east" | "west") => {
+                &["up", "side", "none"]
+            }
+            (Block::RedstoneWire { .. }, "power") => SIXTEEN,
+            (Block::RedstoneLamp { .. }, "lit")
+            | (Block::RedstoneTorch { .. }, "lit")
+            | (Block::RedstoneWallTorch { .. }, "lit")
+            | (Block::RedstoneRepeater { .. }, "powered" | "locked")
+            | (Block::RedstoneComparator { .. }, "powered")
+            | (Block::Lever { .. }, "powered")
+            | (Block::StoneButton { .. }, "powered") => BOOL,
+            (Block::RedstoneWallTorch { .. }, "facing")
+            | (Block::RedstoneRepeater { .. }, "facing")
+            | (Block::RedstoneComparator { .. }, "facing")
+            | (Block::Lever { .. }, "facing")
+            | (Block::StoneButton { .. }, "facing")
+            | (Block::TripwireHook { .. }, "facing")
+            | (Block::WallSign { .. }, "facing") => DIRECTION,
+            (Block::Observer { .. }, "facing") => FACING,
+            (Block::RedstoneRepeater { .. }, "delay") => &["1", "2", "3", "4"],
+            (Block::RedstoneComparator { .. }, "mode") => &["compare", "subtract"],
+            (Block::Lever { .. }, "face") | (Block::StoneButton { .. }, "face") => FACE,
+            (Block::Sign { .. }, "rotation") => SIXTEEN,
+            _ => return None,
+        })
+    }
 }
 
 #[test]
@@ -1083,7 +1136,7 @@ macro_rules! blocks {
         }
 
         impl Block {
-            fn is_solid(self) -> bool {
+            pub fn is_solid(self) -> bool {
                 match self {
                     $(
                         $( Block::$name { .. } => $solid, )?
@@ -1544,6 +1597,16 @@ blocks! {
         solid: true,
         cube: true,
     },
+    StoneBricks {
+        props: {},
+        get_id: 4495,
+        from_id(_id): 4495 => {},
+        from_names(_name): {
+            "stone_bricks" => {}
+        },
+        solid: true,
+        cube: true,
+    },
     Furnace {
         props: {},
         get_id: 3374,
@@ -1,6 +1,8 @@
 mod redstone_wire;
+#[cfg(test)]
+mod tests;
 
-pub use redstone_wire::{RedstoneWire, RedstoneWireSide};
+pub use redstone_wire::{RedstoneWire, RedstoneWireSide, WireAlgorithm};
 
 use crate::blocks::{Block, BlockDirection, BlockEntity, BlockFace, BlockPos};
 use crate::world::{TickPriority, World};
@@ -1,6 +1,6 @@
 use crate::blocks::{ActionResult, Block, BlockDirection, BlockFace, BlockPos};
 use crate::world::World;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 impl Block {
     fn unwrap_wire(self) -> RedstoneWire {
@@ -67,6 +67,41 @@ impl RedstoneWireSide {
     }
 }
 
+/// The algorithm used to update redstone wire when its power changes. They all result
+/// in the same power levels, but the blocks around the wire are updated in a different
+/// order, which some circuits depend on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum WireAlgorithm {
+    /// Updates blocks in the same order as vanilla, including its redundant updates
+    Vanilla,
+    /// Redstone Wire Turbo, which removes redundant updates while keeping an order
+    /// close to vanilla
+    #[default]
+    Turbo,
+    /// Calculates the power of the whole wire network at once and then updates each
+    /// neighboring block a single time
+    AlternateCurrent,
+}
+
+impl WireAlgorithm {
+    pub fn from_name(name: &str) -> Option<WireAlgorithm> {
+        match name {
+            "vanilla" => Some(WireAlgorithm::Vanilla),
+            "turbo" => Some(WireAlgorithm::Turbo),
+            "alternate-current" => Some(WireAlgorithm::AlternateCurrent),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WireAlgorithm::Vanilla => "vanilla",
+            WireAlgorithm::Turbo => "turbo",
+            WireAlgorithm::AlternateCurrent => "alternate-current",
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug, PartialEq, Default)]
 pub struct RedstoneWire {
     pub north: RedstoneWireSide,
@@ -163,9 +198,32 @@ impl RedstoneWire {
         let new_power = RedstoneWire::calculate_power(world, pos);
 
         if self.power != new_power {
-            self.power = new_power;
-            world.set_block(pos, Block::RedstoneWire { wire: self });
-            RedstoneWireTurbo::update_surrounding_neighbors(world, pos);
+            match world.wire_algorithm() {
+                WireAlgorithm::Vanilla => {
+                    self.power = new_power;
+                    world.set_block(pos, Block::RedstoneWire { wire: self });
+                    RedstoneWire::update_neighbors_vanilla(world, pos);
+                }
+                WireAlgorithm::Turbo => {
+                    self.power = new_power;
+                    world.set_block(pos, Block::RedstoneWire { wire: self });
+                    RedstoneWireTurbo::update_surrounding_neighbors(world, pos);
+                }
+                WireAlgorithm::AlternateCurrent => AlternateCurrent::update_network(world, pos),
+            }
+        }
+    }
+
+    /// Updates the neighbors of the wire the same way vanilla does. Vanilla collects the
+    /// wire and its neighbors into a `HashSet` and updates the neighbors of each block in
+    /// the set, so the order depends on how Java hashes block positions.
+    fn update_neighbors_vanilla(world: &mut dyn World, pos: BlockPos) {
+        for set_pos in &vanilla_hash_set_order(pos) {
+            for direction in &VANILLA_UPDATE_ORDER {
+                let neighbor_pos = set_pos.offset(*direction);
+                let block = world.get_block(neighbor_pos);
+                block.update(world, neighbor_pos);
+            }
         }
     }
 
@@ -346,6 +404,214 @@ impl RedstoneWire {
     }
 }
 
+/// The order vanilla updates the neighbors of a block in
+const VANILLA_UPDATE_ORDER: [BlockFace; 6] = [
+    BlockFace::West,
+    BlockFace::East,
+    BlockFace::Bottom,
+    BlockFace::Top,
+    BlockFace::North,
+    BlockFace::South,
+];
+
+/// Returns the wire and its neighbors in the order a Java `HashSet` containing them
+/// would iterate over them. The neighbors are inserted in the order of `Direction.values()`.
+fn vanilla_hash_set_order(pos: BlockPos) -> [BlockPos; 7] {
+    let mut set = [
+        pos,
+        pos.offset(BlockFace::Bottom),
+        pos.offset(BlockFace::Top),
+        pos.offset(BlockFace::North),
+        pos.offset(BlockFace::South),
+        pos.offset(BlockFace::West),
+        pos.offset(BlockFace::East),
+    ];
+    // With 7 entries the set has 16 buckets. Entries in the same bucket keep their
+    // insertion order, which the stable sort preserves.
+    set.sort_by_key(|pos| {
+        let hash = pos
+            .y
+            .wrapping_add(pos.z.wrapping_mul(31))
+            .wrapping_mul(31)
+            .wrapping_add(pos.x);
+        (hash ^ ((hash as u32) >> 16) as i32) & 15
+    });
+    set
+}
+
+/// A wire in the network that is being updated by `AlternateCurrent`
+struct WireNode {
+    pos: BlockPos,
+    wire: RedstoneWire,
+    /// The power the wire receives from blocks that aren't wire
+    external_power: u8,
+    /// The wires that this wire gives power to
+    outputs: Vec<usize>,
+}
+
+/// An implementation of the idea behind Alternate Current by Space Walker. Instead of
+/// updating wires one at a time, the power of the whole network of connected wires is
+/// calculated at once. Each wire is changed at most once and every block next to the
+/// network is updated at most once, after all wires have their final power.
+struct AlternateCurrent {
+    nodes: Vec<WireNode>,
+    node_cache: HashMap<BlockPos, usize>,
+}
+
+impl AlternateCurrent {
+    fn update_network(world: &mut dyn World, pos: BlockPos) {
+        let mut ac = AlternateCurrent {
+            nodes: Vec::new(),
+            node_cache: HashMap::new(),
+        };
+        ac.find_network(world, pos);
+        let powers = ac.calculate_powers();
+
+        let mut changed = Vec::new();
+        for (node, &power) in ac.nodes.iter_mut().zip(powers.iter()) {
+            if node.wire.power != power {
+                node.wire.power = power;
+                world.set_block(node.pos, Block::RedstoneWire { wire: node.wire });
+                changed.push(node.pos);
+            }
+        }
+
+        let mut updated = HashSet::new();
+        for wire_pos in changed {
+            for neighbor_pos in RedstoneWireTurbo::compute_all_neighbors(wire_pos).iter() {
+                if ac.node_cache.contains_key(neighbor_pos) || !updated.insert(*neighbor_pos) {
+                    continue;
+                }
+                let block = world.get_block(*neighbor_pos);
+                block.update(world, *neighbor_pos);
+            }
+        }
+    }
+
+    fn add_node(&mut self, world: &dyn World, pos: BlockPos) -> usize {
+        if let Some(&index) = self.node_cache.get(&pos) {
+            return index;
+        }
+        let mut external_power = 0;
+        for side in &BlockFace::values() {
+            let neighbor_pos = pos.offset(*side);
+            let neighbor = world.get_block(neighbor_pos);
+            external_power =
+                external_power.max(neighbor.get_redstone_power_no_dust(world, neighbor_pos, *side));
+        }
+        let index = self.nodes.len();
+        self.nodes.push(WireNode {
+            pos,
+            wire: world.get_block(pos).unwrap_wire(),
+            external_power,
+            outputs: Vec::new(),
+        });
+        self.node_cache.insert(pos, index);
+        index
+    }
+
+    /// Finds all wires connected to the wire at `pos`
+    fn find_network(&mut self, world: &dyn World, pos: BlockPos) {
+        self.add_node(world, pos);
+        let mut next = 0;
+        while next < self.nodes.len() {
+            let node_pos = self.nodes[next].pos;
+            for source in wire_sources(world, node_pos) {
+                let source = self.add_node(world, source);
+                if !self.nodes[source].outputs.contains(&next) {
+                    self.nodes[source].outputs.push(next);
+                }
+            }
+            // Wires this wire gives power to are found by checking which of the wires
+            // that could be connected to it have it as a source.
+            for candidate in wire_sources_candidates(node_pos).iter() {
+                if !matches!(world.get_block(*candidate), Block::RedstoneWire { .. })
+                    || !wire_sources(world, *candidate).contains(&node_pos)
+                {
+                    continue;
+                }
+                let output = self.add_node(world, *candidate);
+                if !self.nodes[next].outputs.contains(&output) {
+                    self.nodes[next].outputs.push(output);
+                }
+            }
+            next += 1;
+        }
+    }
+
+    /// Calculates the power of every wire in the network. Power is spread from the most
+    /// powered wires first, so every wire only has to be visited once per power level.
+    fn calculate_powers(&self) -> Vec<u8> {
+        let mut powers: Vec<u8> = self.nodes.iter().map(|node| node.external_power).collect();
+        let mut queue: Vec<Vec<usize>> = vec![Vec::new(); 16];
+        for (index, &power) in powers.iter().enumerate() {
+            queue[power as usize].push(index);
+        }
+        for power in (1..16).rev() {
+            while let Some(index) = queue[power].pop() {
+                if powers[index] as usize != power {
+                    continue;
+                }
+                let output_power = power as u8 - 1;
+                for &output in &self.nodes[index].outputs {
+                    if powers[output] < output_power {
+                        powers[output] = output_power;
+                        queue[output_power as usize].push(output);
+                    }
+                }
+            }
+        }
+        powers
+    }
+}
+
+/// Returns every position a wire giving power to the wire at `pos` could be at
+fn wire_sources_candidates(pos: BlockPos) -> [BlockPos; 14] {
+    let BlockPos { x, y, z } = pos;
+    [
+        BlockPos::new(x, y + 1, z),
+        BlockPos::new(x, y - 1, z),
+        BlockPos::new(x, y, z - 1),
+        BlockPos::new(x, y, z + 1),
+        BlockPos::new(x + 1, y, z),
+        BlockPos::new(x - 1, y, z),
+        BlockPos::new(x, y + 1, z - 1),
+        BlockPos::new(x, y + 1, z + 1),
+        BlockPos::new(x + 1, y + 1, z),
+        BlockPos::new(x - 1, y + 1, z),
+        BlockPos::new(x, y - 1, z - 1),
+        BlockPos::new(x, y - 1, z + 1),
+        BlockPos::new(x + 1, y - 1, z),
+        BlockPos::new(x - 1, y - 1, z),
+    ]
+}
+
+/// Returns the wires that give power to the wire at `pos`. This follows the same rules
+/// as `RedstoneWire::calculate_power`.
+fn wire_sources(world: &dyn World, pos: BlockPos) -> Vec<BlockPos> {
+    let mut sources = Vec::new();
+    let mut add_wire = |pos: BlockPos| {
+        if let Block::RedstoneWire { .. } = world.get_block(pos) {
+            sources.push(pos);
+        }
+    };
+    let up_block = world.get_block(pos.offset(BlockFace::Top));
+    for side in &BlockFace::values() {
+        let neighbor_pos = pos.offset(*side);
+        add_wire(neighbor_pos);
+        if side.is_horizontal() {
+            let neighbor = world.get_block(neighbor_pos);
+            if !up_block.is_solid() && !neighbor.is_transparent() {
+                add_wire(neighbor_pos.offset(BlockFace::Top));
+            }
+            if !neighbor.is_solid() {
+                add_wire(neighbor_pos.offset(BlockFace::Bottom));
+            }
+        }
+    }
+    sources
+}
+
 #[derive(Clone, Copy)]
 struct NodeId {
     index: usize,
@@ -472,7 +738,9 @@ impl RedstoneWireTurbo {
 
         for (i, neighbor_pos) in neighbors[0..24].iter().enumerate() {
             let neighbor = if !self.node_cache.contains_key(neighbor_pos) {
-                let node_id = NodeId { index: self.nodes.len() };
+                let node_id = NodeId {
+                    index: self.nodes.len(),
+                };
                 self.node_cache.insert(*neighbor_pos, node_id);
                 self.nodes.push(UpdateNode::new(world, *neighbor_pos, pos));
                 node_id
@@ -484,7 +752,7 @@ impl RedstoneWireTurbo {
             // if let Block::RedstoneWire { .. } = node.state {
             //     if RedstoneWireTurbo::UPDATE_REDSTONE[i] {
             neighbor_nodes.push(neighbor);
-            neighbors_visited.push(node.visited);        
+            neighbors_visited.push(node.visited);
             //         continue;
             //     }
             // }
@@ -522,9 +790,9 @@ impl RedstoneWireTurbo {
 
             for node_id in &neighbor_nodes {
                 // if let Some(node_id) = node_id {
-                    let nn = &mut self.nodes[node_id.index];
-                    nn.xbias = xbias;
-                    nn.zbias = zbias;
+                let nn = &mut self.nodes[node_id.index];
+                nn.xbias = xbias;
+                nn.zbias = zbias;
                 // }
             }
         } else {
@@ -540,9 +808,9 @@ impl RedstoneWireTurbo {
 
             for node_id in &neighbor_nodes {
                 // if let Some(node_id) = node_id {
-                    let nn = &mut self.nodes[node_id.index];
-                    nn.xbias = cx;
-                    nn.zbias = cz;
+                let nn = &mut self.nodes[node_id.index];
+                nn.xbias = cx;
+                nn.zbias = cz;
                 // }
             }
         }
@@ -592,9 +860,7 @@ impl RedstoneWireTurbo {
             self.identify_neighbors(world, upd1);
         }
         // FIXME: Get rid of this nasty clone
-        let neighbors = self.nodes[upd1.index].neighbors
-            .clone()
-            .unwrap();
+        let neighbors = self.nodes[upd1.index].neighbors.clone().unwrap();
         let pos = self.nodes[upd1.index].pos;
 
         let layer1 = layer + 1;
@@ -660,10 +926,7 @@ impl RedstoneWireTurbo {
 
         let new_wire = self.calculate_current_changes(world, upd1);
         if old_wire.power != new_wire.power {
-            self.nodes[upd1.index]
-                .state
-                .wire_mut()
-                .power = new_wire.power;
+            self.nodes[upd1.index].state.wire_mut().power = new_wire.power;
 
             self.propagate_changes(world, upd1, layer);
         }
@@ -693,10 +956,7 @@ impl RedstoneWireTurbo {
         }
 
         if wire_power < 15 {
-            let neighbors = self.nodes[upd.index]
-                .neighbors
-                .as_ref()
-                .unwrap();
+            let neighbors = self.nodes[upd.index].neighbors.as_ref().unwrap();
 
             let center_up = self.nodes[neighbors[1].index].state;
 
@@ -737,3 +997,25 @@ impl RedstoneWireTurbo {
         }
     }
 }
+
+#[test]
+fn vanilla_update_order() {
+    // The order a `HashSet<BlockPos>` iterates in on Java Edition
+    let pos = BlockPos::new(0, 1, 0);
+    let order: Vec<(i32, i32, i32)> = vanilla_hash_set_order(pos)
+        .iter()
+        .map(|pos| (pos.x, pos.y, pos.z))
+        .collect();
+    assert_eq!(
+        order,
+        [
+            (0, 0, 0),
+            (0, 1, 1),
+            (1, 1, 0),
+            (0, 1, -1),
+            (0, 2, 0),
+            (-1, 1, 0),
+            (0, 1, 0)
+        ]
+    );
+}
@@ -0,0 +1,598 @@
+use super::{ComparatorMode, RedstoneWire};
+use crate::blocks::{Block, BlockEntity, BlockPos, WireAlgorithm};
+use crate::world::test_world::TestWorld;
+use crate::world::World;
+
+/// The most ticks `Circuit::settle` runs before deciding the circuit is a clock
+const MAX_SETTLE_TICKS: u32 = 1000;
+
+const WIRE_ALGORITHMS: [WireAlgorithm; 3] = [
+    WireAlgorithm::Vanilla,
+    WireAlgorithm::Turbo,
+    WireAlgorithm::AlternateCurrent,
+];
+
+/// A small circuit drawn as layers of characters. `.` is air, `#` is sandstone and `-` is
+/// redstone wire. Every other character has to be in the legend.
+struct Fixture {
+    /// The block each character stands for, written like `repeater[facing=west,delay=2]`
+    legend: &'static [(char, &'static str)],
+    /// The layers of the circuit from the bottom up. Each layer is a list of rows going
+    /// south, and each character in a row is a block going east.
+    layers: &'static [&'static [&'static str]],
+}
+
+impl Fixture {
+    fn block(&self, c: char) -> Block {
+        let name = match c {
+            '.' => "air",
+            '#' => "sandstone",
+            '-' => "redstone_wire",
+            _ => {
+                self.legend
+                    .iter()
+                    .find(|(legend_char, _)| *legend_char == c)
+                    .unwrap_or_else(|| panic!("'{}' is not in the legend", c))
+                    .1
+            }
+        };
+        let (name, properties) = match name.find('[') {
+            Some(start) => (&name[..start], &name[start + 1..name.len() - 1]),
+            None => (name, ""),
+        };
+        let mut block = Block::from_name(name).unwrap_or_else(|| panic!("unknown block {}", name));
+        for property in properties.split(',').filter(|p| !p.is_empty()) {
+            let mut split = property.splitn(2, '=');
+            block.set_property(split.next().unwrap(), split.next().unwrap_or_default());
+        }
+        block
+    }
+}
+
+/// A fixture built in a `TestWorld`
+struct Circuit {
+    world: TestWorld,
+    /// Every block of the fixture that isn't air with the character it was drawn with
+    blocks: Vec<(char, BlockPos)>,
+}
+
+impl Circuit {
+    fn new(fixture: &Fixture) -> Circuit {
+        Circuit::with_wire_algorithm(fixture, WireAlgorithm::default())
+    }
+
+    /// Sets all the blocks of the fixture like a schematic being pasted, connects the
+    /// wires, then updates every block and runs ticks until the circuit is stable.
+    fn with_wire_algorithm(fixture: &Fixture, wire_algorithm: WireAlgorithm) -> Circuit {
+        let mut circuit = Circuit {
+            world: TestWorld::new(wire_algorithm),
+            blocks: Vec::new(),
+        };
+        for (y, layer) in fixture.layers.iter().enumerate() {
+            for (z, row) in layer.iter().enumerate() {
+                for (x, c) in row.chars().enumerate() {
+                    if c != '.' {
+                        let pos = BlockPos::new(x as i32, y as i32, z as i32);
+                        circuit.world.set_block(pos, fixture.block(c));
+                        circuit.blocks.push((c, pos));
+                    }
+                }
+            }
+        }
+        for &(_, pos) in &circuit.blocks {
+            if let Block::RedstoneWire { .. } = circuit.world.get_block(pos) {
+                let wire = RedstoneWire::get_state_for_placement(&circuit.world, pos);
+                circuit.world.set_block(pos, Block::RedstoneWire { wire });
+            }
+        }
+        for &(_, pos) in &circuit.blocks {
+            let block = circuit.world.get_block(pos);
+            block.update(&mut circuit.world, pos);
+        }
+        circuit.settle();
+        circuit
+    }
+
+    /// Returns the position of the only block drawn with `c`
+    fn pos(&self, c: char) -> BlockPos {
+        let mut positions = self
+            .blocks
+            .iter()
+            .filter(|(block_char, _)| *block_char == c);
+        let pos = positions
+            .next()
+            .unwrap_or_else(|| panic!("no '{}' in fixture", c))
+            .1;
+        assert!(
+            positions.next().is_none(),
+            "more than one '{}' in fixture",
+            c
+        );
+        pos
+    }
+
+    fn block(&self, c: char) -> Block {
+        self.world.get_block(self.pos(c))
+    }
+
+    /// Uses the block like a player right clicking it
+    fn use_block(&mut self, c: char) {
+        let pos = self.pos(c);
+        self.world.get_block(pos).on_use(&mut self.world, pos, None);
+    }
+
+    fn set_block_entity(&mut self, c: char, block_entity: BlockEntity) {
+        let pos = self.pos(c);
+        self.world.set_block_entity(pos, block_entity);
+        let block = self.world.get_block(pos);
+        Block::update_surrounding_blocks(&mut self.world, pos);
+        block.update(&mut self.world, pos);
+    }
+
+    fn tick(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.world.tick();
+        }
+    }
+
+    /// Runs ticks until nothing is scheduled anymore and returns how many ran
+    fn settle(&mut self) -> u32 {
+        let mut ticks = 0;
+        while !self.world.pending_ticks().is_empty() {
+            assert!(ticks < MAX_SETTLE_TICKS, "circuit never settled");
+            self.world.tick();
+            ticks += 1;
+        }
+        ticks
+    }
+
+    /// Returns whether the component is powered, lit or turned on
+    fn is_on(&self, c: char) -> bool {
+        match self.block(c) {
+            Block::Lever { lever } => lever.powered,
+            Block::StoneButton { button } => button.powered,
+            Block::RedstoneRepeater { repeater } => repeater.powered,
+            Block::RedstoneComparator { comparator } => comparator.powered,
+            Block::RedstoneTorch { lit } => lit,
+            Block::RedstoneWallTorch { lit, .. } => lit,
+            Block::RedstoneLamp { lit } => lit,
+            block => panic!("'{}' is {:?} which can't be on", c, block),
+        }
+    }
+
+    fn comparator_output(&self, c: char) -> u8 {
+        match self.world.get_block_entity(self.pos(c)) {
+            Some(BlockEntity::Comparator { output_strength }) => *output_strength,
+            _ => 0,
+        }
+    }
+
+    /// Returns the power of every wire drawn with `c` in the order they were placed
+    fn wire_powers(&self, c: char) -> Vec<u8> {
+        self.blocks
+            .iter()
+            .filter(|(block_char, _)| *block_char == c)
+            .map(|(_, pos)| match self.world.get_block(*pos) {
+                Block::RedstoneWire { wire } => wire.power,
+                block => panic!("'{}' is {:?} and not wire", c, block),
+            })
+            .collect()
+    }
+}
+
+const DUST_LINE: Fixture = Fixture {
+    legend: &[
+        ('L', "lever[face=floor,facing=east]"),
+        ('R', "redstone_lamp"),
+    ],
+    layers: &[&["###################"], &["L-----------------#R"]],
+};
+
+#[test]
+fn dust_decays_and_powers_blocks() {
+    for &algorithm in &WIRE_ALGORITHMS {
+        let mut circuit = Circuit::with_wire_algorithm(&DUST_LINE, algorithm);
+        assert_eq!(circuit.wire_powers('-'), [0; 17], "{:?}", algorithm);
+
+        circuit.use_block('L');
+        circuit.settle();
+        let mut powers: Vec<u8> = (0..16).rev().collect();
+        powers.push(0);
+        assert_eq!(circuit.wire_powers('-'), powers, "{:?}", algorithm);
+        // The last wire has no power left to give to the block it points into
+        assert!(!circuit.is_on('R'), "{:?}", algorithm);
+
+        circuit.use_block('L');
+        circuit.settle();
+        assert_eq!(circuit.wire_powers('-'), [0; 17], "{:?}", algorithm);
+    }
+}
+
+const DUST_BOTH_ENDS: Fixture = Fixture {
+    legend: &[
+        ('L', "lever[face=floor,facing=east]"),
+        ('K', "lever[face=floor,facing=west]"),
+        ('R', "redstone_lamp"),
+    ],
+    layers: &[&["#####R######"], &["L----------K"]],
+};
+
+#[test]
+fn dust_powered_from_both_ends() {
+    for &algorithm in &WIRE_ALGORITHMS {
+        let mut circuit = Circuit::with_wire_algorithm(&DUST_BOTH_ENDS, algorithm);
+
+        circuit.use_block('L');
+        circuit.settle();
+        assert_eq!(
+            circuit.wire_powers('-'),
+            [15, 14, 13, 12, 11, 10, 9, 8, 7, 6],
+            "{:?}",
+            algorithm
+        );
+        // Wire powers the block below it
+        assert!(circuit.is_on('R'), "{:?}", algorithm);
+
+        circuit.use_block('K');
+        circuit.settle();
+        assert_eq!(
+            circuit.wire_powers('-'),
+            [15, 14, 13, 12, 11, 11, 12, 13, 14, 15],
+            "{:?}",
+            algorithm
+        );
+
+        circuit.use_block('L');
+        circuit.settle();
+        assert_eq!(
+            circuit.wire_powers('-'),
+            [6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+            "{:?}",
+            algorithm
+        );
+
+        circuit.use_block('K');
+        circuit.settle();
+        assert_eq!(circuit.wire_powers('-'), [0; 10], "{:?}", algorithm);
+        assert!(!circuit.is_on('R'), "{:?}", algorithm);
+    }
+}
+
+const DUST_BRANCH: Fixture = Fixture {
+    legend: &[('L', "lever[face=floor,facing=east]")],
+    layers: &[
+        &["#######", "#######", "#######", "#######"],
+        &["L------", "....-..", "....-..", "....-.."],
+    ],
+};
+
+const DUST_STAIRS: Fixture = Fixture {
+    legend: &[('L', "lever[face=floor,facing=east]")],
+    layers: &[&["##..."], &["L-#.."], &["..-#."], &["...-#"], &["....-"]],
+};
+
+#[test]
+fn dust_networks() {
+    for &algorithm in &WIRE_ALGORITHMS {
+        let mut branch = Circuit::with_wire_algorithm(&DUST_BRANCH, algorithm);
+        branch.use_block('L');
+        branch.settle();
+        assert_eq!(
+            branch.wire_powers('-'),
+            [15, 14, 13, 12, 11, 10, 11, 10, 9],
+            "{:?}",
+            algorithm
+        );
+        branch.use_block('L');
+        branch.settle();
+        assert_eq!(branch.wire_powers('-'), [0; 9], "{:?}", algorithm);
+
+        let mut stairs = Circuit::with_wire_algorithm(&DUST_STAIRS, algorithm);
+        stairs.use_block('L');
+        stairs.settle();
+        assert_eq!(stairs.wire_powers('-'), [15, 14, 13, 12], "{:?}", algorithm);
+        stairs.use_block('L');
+        stairs.settle();
+        assert_eq!(stairs.wire_powers('-'), [0; 4], "{:?}", algorithm);
+    }
+}
+
+const DUST_INTO_BLOCK: Fixture = Fixture {
+    legend: &[('B', "redstone_block"), ('R', "redstone_lamp")],
+    layers: &[&["####"], &["B-#R"]],
+};
+
+#[test]
+fn dust_powers_the_block_it_points_into() {
+    for &algorithm in &WIRE_ALGORITHMS {
+        let circuit = Circuit::with_wire_algorithm(&DUST_INTO_BLOCK, algorithm);
+        assert_eq!(circuit.wire_powers('-'), [15], "{:?}", algorithm);
+        assert!(circuit.is_on('R'), "{:?}", algorithm);
+    }
+}
+
+const TORCHES: Fixture = Fixture {
+    legend: &[
+        ('L', "lever[face=wall,facing=west]"),
+        ('W', "redstone_wall_torch[facing=east]"),
+        ('K', "lever[face=floor,facing=east]"),
+        ('T', "redstone_torch"),
+    ],
+    layers: &[&["###", "###"], &["L#W", "K-#"], &["...", "..T"]],
+};
+
+#[test]
+fn torches_invert() {
+    let mut circuit = Circuit::new(&TORCHES);
+    assert!(circuit.is_on('W'));
+    assert!(circuit.is_on('T'));
+
+    // Torches turn off one tick after the block they are on is powered
+    circuit.use_block('L');
+    assert!(circuit.is_on('W'));
+    circuit.tick(1);
+    assert!(!circuit.is_on('W'));
+
+    circuit.use_block('K');
+    circuit.tick(1);
+    assert!(!circuit.is_on('T'));
+
+    circuit.use_block('L');
+    circuit.use_block('K');
+    assert_eq!(circuit.settle(), 1);
+    assert!(circuit.is_on('W'));
+    assert!(circuit.is_on('T'));
+}
+
+const REPEATER: Fixture = Fixture {
+    legend: &[
+        ('L', "lever[face=floor,facing=east]"),
+        ('r', "repeater[facing=west]"),
+        ('R', "redstone_lamp"),
+    ],
+    layers: &[&["####"], &["L-rR"]],
+};
+
+#[test]
+fn repeaters_delay() {
+    for delay in 1..=4 {
+        let mut circuit = Circuit::new(&REPEATER);
+        // Using a repeater cycles through its delays
+        for _ in 1..delay {
+            circuit.use_block('r');
+        }
+        match circuit.block('r') {
+            Block::RedstoneRepeater { repeater } => assert_eq!(repeater.delay, delay),
+            block => panic!("expected repeater, found {:?}", block),
+        }
+
+        circuit.use_block('L');
+        circuit.tick(delay as u32 - 1);
+        assert!(!circuit.is_on('r'));
+        assert!(!circuit.is_on('R'));
+        circuit.tick(1);
+        assert!(circuit.is_on('r'));
+        assert!(circuit.is_on('R'));
+
+        circuit.use_block('L');
+        assert_eq!(circuit.settle(), delay as u32 + 2);
+        assert!(!circuit.is_on('r'));
+        assert!(!circuit.is_on('R'));
+    }
+}
+
+const REPEATER_LOCK: Fixture = Fixture {
+    legend: &[
+        ('L', "lever[face=floor,facing=east]"),
+        ('r', "repeater[facing=west]"),
+        ('R', "redstone_lamp"),
+        ('s', "repeater[facing=south]"),
+        ('K', "lever[face=floor,facing=east]"),
+    ],
+    layers: &[&["####", "####", "####"], &["L-rR", "..s.", "..K."]],
+};
+
+#[test]
+fn repeaters_lock() {
+    let mut circuit = Circuit::new(&REPEATER_LOCK);
+    circuit.use_block('K');
+    circuit.settle();
+    assert!(circuit.is_on('s'));
+    match circuit.block('r') {
+        Block::RedstoneRepeater { repeater } => assert!(repeater.locked),
+        block => panic!("expected repeater, found {:?}", block),
+    }
+
+    // A locked repeater keeps its state
+    circuit.use_block('L');
+    circuit.settle();
+    assert!(!circuit.is_on('r'));
+    assert!(!circuit.is_on('R'));
+
+    // Unlocking it lets the input through
+    circuit.use_block('K');
+    circuit.settle();
+    assert!(circuit.is_on('r'));
+    assert!(circuit.is_on('R'));
+
+    circuit.use_block('K');
+    circuit.settle();
+    circuit.use_block('L');
+    circuit.settle();
+    assert!(circuit.is_on('r'));
+}
+
+const COMPARATOR: Fixture = Fixture {
+    legend: &[
+        ('L', "lever[face=floor,facing=east]"),
+        ('c', "comparator[facing=west]"),
+        ('R', "redstone_lamp"),
+        ('K', "lever[face=floor,facing=east]"),
+    ],
+    layers: &[
+        &["#####", "#####", "#####", "#####"],
+        &["L-cR.", "..-..", "..-..", "..K.."],
+    ],
+};
+
+#[test]
+fn comparators_compare_and_subtract() {
+    let mut circuit = Circuit::new(&COMPARATOR);
+    circuit.use_block('L');
+    circuit.settle();
+    assert_eq!(circuit.comparator_output('c'), 15);
+    assert!(circuit.is_on('c'));
+    assert!(circuit.is_on('R'));
+
+    // The side input is 14, which is less than the rear input of 15
+    circuit.use_block('K');
+    circuit.settle();
+    assert_eq!(circuit.comparator_output('c'), 15);
+
+    circuit.use_block('c');
+    circuit.settle();
+    match circuit.block('c') {
+        Block::RedstoneComparator { comparator } => {
+            assert_eq!(comparator.mode, ComparatorMode::Subtract)
+        }
+        block => panic!("expected comparator, found {:?}", block),
+    }
+    assert_eq!(circuit.comparator_output('c'), 1);
+    assert!(circuit.is_on('c'));
+
+    circuit.use_block('K');
+    circuit.settle();
+    assert_eq!(circuit.comparator_output('c'), 15);
+
+    circuit.use_block('L');
+    circuit.settle();
+    assert_eq!(circuit.comparator_output('c'), 0);
+    assert!(!circuit.is_on('c'));
+    assert!(!circuit.is_on('R'));
+}
+
+const COMPARATOR_SIDE_STRONGER: Fixture = Fixture {
+    legend: &[
+        ('L', "lever[face=floor,facing=east]"),
+        ('c', "comparator[facing=west]"),
+        ('K', "lever[face=floor,facing=east]"),
+    ],
+    layers: &[
+        &["######", "######", "######"],
+        &["L---c.", "....-.", "....K."],
+    ],
+};
+
+#[test]
+fn comparators_turn_off_when_the_side_is_stronger() {
+    let mut circuit = Circuit::new(&COMPARATOR_SIDE_STRONGER);
+    circuit.use_block('L');
+    circuit.settle();
+    assert_eq!(circuit.comparator_output('c'), 13);
+
+    circuit.use_block('K');
+    circuit.settle();
+    assert_eq!(circuit.comparator_output('c'), 0);
+    assert!(!circuit.is_on('c'));
+
+    circuit.use_block('c');
+    circuit.settle();
+    assert_eq!(circuit.comparator_output('c'), 0);
+}
+
+const COMPARATOR_CONTAINERS: Fixture = Fixture {
+    legend: &[
+        ('X', "barrel"),
+        ('c', "comparator[facing=west]"),
+        ('Y', "barrel"),
+        ('d', "comparator[facing=west]"),
+    ],
+    layers: &[&["##", "###"], &["Xc", "Y#d"]],
+};
+
+#[test]
+fn comparators_read_containers() {
+    let mut circuit = Circuit::new(&COMPARATOR_CONTAINERS);
+    circuit.set_block_entity(
+        'X',
+        BlockEntity::Container {
+            comparator_override: 7,
+        },
+    );
+    // Containers can also be read through a solid block
+    circuit.set_block_entity(
+        'Y',
+        BlockEntity::Container {
+            comparator_override: 3,
+        },
+    );
+    circuit.settle();
+    assert_eq!(circuit.comparator_output('c'), 7);
+    assert_eq!(circuit.comparator_output('d'), 3);
+}
+
+const BUTTON: Fixture = Fixture {
+    legend: &[
+        ('b', "stone_button[face=wall,facing=west]"),
+        ('R', "redstone_lamp"),
+    ],
+    layers: &[&["###"], &["b#R"]],
+};
+
+#[test]
+fn buttons_turn_off_after_ten_ticks() {
+    let mut circuit = Circuit::new(&BUTTON);
+    circuit.use_block('b');
+    assert!(circuit.is_on('b'));
+    assert!(circuit.is_on('R'));
+
+    circuit.tick(9);
+    assert!(circuit.is_on('b'));
+    circuit.tick(1);
+    assert!(!circuit.is_on('b'));
+    // Lamps take two ticks to turn off
+    assert!(circuit.is_on('R'));
+    circuit.tick(2);
+    assert!(!circuit.is_on('R'));
+}
+
+/// A line of wire with repeaters on both sides and at its end. The order the repeaters are
+/// scheduled in depends on the wire algorithm.
+const TURBO_ORDER: Fixture = Fixture {
+    legend: &[
+        ('L', "lever[face=floor,facing=east]"),
+        ('n', "repeater[facing=north]"),
+        ('s', "repeater[facing=south]"),
+        ('w', "repeater[facing=west]"),
+    ],
+    layers: &[
+        &["#######", "#######", "#######"],
+        &[".s.s.s.", "L-----w", ".n.n.n."],
+    ],
+};
+
+/// Returns the x and z of the repeaters in the order they were scheduled to tick
+fn scheduled_repeaters(wire_algorithm: WireAlgorithm) -> Vec<(i32, i32)> {
+    let mut circuit = Circuit::with_wire_algorithm(&TURBO_ORDER, wire_algorithm);
+    circuit.use_block('L');
+    circuit
+        .world
+        .pending_ticks()
+        .iter()
+        .map(|pos| (pos.x, pos.z))
+        .collect()
+}
+
+#[test]
+fn turbo_update_order() {
+    // Vanilla updates the neighbors of each wire in the order of a Java `HashSet`
+    assert_eq!(
+        scheduled_repeaters(WireAlgorithm::Vanilla),
+        [(1, 0), (1, 2), (3, 0), (3, 2), (6, 1), (5, 0), (5, 2)]
+    );
+    // Turbo updates them in its own order, which doesn't depend on hashing
+    assert_eq!(
+        scheduled_repeaters(WireAlgorithm::Turbo),
+        [(1, 2), (1, 0), (3, 0), (3, 2), (5, 0), (6, 1), (5, 2)]
+    );
+}
@@ -43,7 +43,11 @@ macro_rules! gen_config {
                             $name: match config.$name {
                                 Some(entry) => entry,
                                 None => {
-                                    toml_patch += &format!("{} = {}\n", stringify!($name), default_config.$name);
+                                    toml_patch += &format!(
+                                        "{} = {}\n",
+                                        stringify!($name),
+                                        toml::Value::try_from(&default_config.$name).unwrap()
+                                    );
                                     default_config.$name
                                 }
                             },
@@ -64,7 +68,26 @@ gen_config! {
     motd: String = "Minecraft High Performance Redstone Server".to_string(),
     chat_format: String = "<{username}> {message}".to_string(),
     max_players: i64 = 99999,
-    bungeecord: bool = false
+    bungeecord: bool = false,
+    whitelist: bool = false,
+    rcon_enabled: bool = false,
+    rcon_bind_address: String = "127.0.0.1:25575".to_string(),
+    rcon_password: String = "".to_string(),
+    metrics_enabled: bool = false,
+    metrics_bind_address: String = "127.0.0.1:9940".to_string(),
+    plot_width: i32 = 256,
+    plot_depth: i32 = 256,
+    plot_height: i32 = 256,
+    plot_floor_layers: i32 = 8,
+    plot_floor_block: String = "sandstone".to_string(),
+    plot_road_width: i32 = 0,
+    plot_road_block: String = "stone_bricks".to_string(),
+    plot_border_block: String = "stone_bricks".to_string(),
+    plot_wall_block: String = "smooth_stone_slab".to_string(),
+    plot_claimed_wall_block: String = "quartz_slab".to_string(),
+    clipboard_quota: i64 = 32,
+    clipboard_quota_mb: i64 = 64,
+    ops: Vec<String> = Vec::new()
 }
 
 fn write_config(config: &ServerConfig) {
@@ -62,7 +62,11 @@ impl ItemStack {
         }
 
         let block_pos = context.block_pos.offset(context.block_face);
-        if self.item_type.is_block() && plot.get_block(block_pos).can_place_block_in() {
+        if self.item_type.is_block()
+            && plot.in_build_area(block_pos)
+            && plot.can_build(context.player_idx)
+            && plot.get_block(block_pos).can_place_block_in()
+        {
             let block = Block::get_state_for_placement(plot, block_pos, self.item_type, &context);
 
             match block {
@@ -155,7 +159,7 @@ macro_rules! items {
                 }
             }
 
-            fn is_block(self) -> bool {
+            pub fn is_block(self) -> bool {
                 match self {
                     $(
                         $( Item::$name { .. } => $block, )?
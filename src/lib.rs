@@ -4,8 +4,11 @@ mod blocks;
 mod chat;
 mod config;
 mod items;
+mod metrics;
+mod permissions;
 mod player;
 mod plot;
+mod rcon;
 #[macro_use]
 mod utils;
 pub mod server;
@@ -0,0 +1,410 @@
+use crate::config::CONFIG;
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often plots publish their statistics
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+/// The most tick times kept per interval. This keeps plots running at a very high rtps
+/// from using a lot of memory.
+const MAX_TICK_SAMPLES: usize = 100_000;
+/// The tick time quantiles that get exported
+const QUANTILES: [f64; 4] = [0.5, 0.9, 0.99, 1.0];
+/// The most scrape connections handled at once
+const MAX_CONNECTIONS: usize = 8;
+/// How long a scrape connection may take to send its request or receive the response
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+lazy_static! {
+    pub static ref METRICS: Metrics = Default::default();
+}
+
+/// Metrics shared by every thread of the server
+#[derive(Default)]
+pub struct Metrics {
+    pub packets_received: AtomicU64,
+    pub packets_sent: AtomicU64,
+    pub online_players: AtomicUsize,
+    pub handshaking_clients: AtomicUsize,
+    /// The number of messages the server thread found in its queue the last time it
+    /// handled messages
+    pub server_queue_depth: AtomicUsize,
+    plots: Mutex<HashMap<(i32, i32), PlotMetrics>>,
+}
+
+/// The statistics a plot publishes about itself once per second
+#[derive(Debug, Clone, Default)]
+pub struct PlotMetrics {
+    pub target_rtps: u32,
+    pub rtps: f64,
+    /// The average tick time in milliseconds
+    pub mspt: f64,
+    /// The tick times in seconds at each of the `QUANTILES`, or `None` if tick times
+    /// weren't sampled
+    pub tick_times: Option<[f64; 4]>,
+    pub pending_ticks: usize,
+    /// Ticks skipped because the plot couldn't keep up
+    pub skipped_ticks: u64,
+    pub players: usize,
+    /// The most broadcast messages the plot found in its queue at once
+    pub broadcast_queue_depth: usize,
+    /// The most private messages the plot found in its queue at once
+    pub priv_queue_depth: usize,
+}
+
+/// Collects statistics while a plot runs and publishes them to `METRICS`
+pub struct PlotStats {
+    interval_start: Instant,
+    ticks: u64,
+    skipped_ticks: u64,
+    total_tick_time: Duration,
+    tick_times: Vec<Duration>,
+    broadcast_queue_depth: usize,
+    priv_queue_depth: usize,
+    /// Whether every tick time is kept to calculate the quantiles. Sampling is only needed
+    /// for metrics and the performance boss bar.
+    pub sample_tick_times: bool,
+    /// The statistics of the last full interval
+    pub last: Option<PlotMetrics>,
+}
+
+impl Default for PlotStats {
+    fn default() -> PlotStats {
+        PlotStats {
+            interval_start: Instant::now(),
+            ticks: 0,
+            skipped_ticks: 0,
+            total_tick_time: Duration::default(),
+            tick_times: Vec::new(),
+            broadcast_queue_depth: 0,
+            priv_queue_depth: 0,
+            sample_tick_times: false,
+            last: None,
+        }
+    }
+}
+
+impl PlotStats {
+    pub fn record_tick(&mut self, time: Duration) {
+        self.ticks += 1;
+        self.total_tick_time += time;
+        if self.sample_tick_times && self.tick_times.len() < MAX_TICK_SAMPLES {
+            self.tick_times.push(time);
+        }
+    }
+
+    pub fn record_skipped_ticks(&mut self, ticks: u64) {
+        self.skipped_ticks += ticks;
+    }
+
+    pub fn record_queue_depths(&mut self, broadcast: usize, private: usize) {
+        self.broadcast_queue_depth = self.broadcast_queue_depth.max(broadcast);
+        self.priv_queue_depth = self.priv_queue_depth.max(private);
+    }
+
+    /// Starts a new interval once the current one is over and publishes the statistics
+    /// of the plot at `plot_x` and `plot_z`. Returns true if a new interval was started.
+    pub fn update(
+        &mut self,
+        plot_x: i32,
+        plot_z: i32,
+        target_rtps: u32,
+        pending_ticks: usize,
+        players: usize,
+    ) -> bool {
+        let elapsed = self.interval_start.elapsed();
+        if elapsed < PUBLISH_INTERVAL {
+            return false;
+        }
+        let tick_times = if self.sample_tick_times {
+            self.tick_times.sort_unstable();
+            let mut tick_times = [0.0; 4];
+            for (time, &q) in tick_times.iter_mut().zip(QUANTILES.iter()) {
+                *time = quantile(&self.tick_times, q).as_secs_f64();
+            }
+            Some(tick_times)
+        } else {
+            None
+        };
+        let mspt = if self.ticks == 0 {
+            0.0
+        } else {
+            self.total_tick_time.as_secs_f64() * 1000.0 / self.ticks as f64
+        };
+        let last = PlotMetrics {
+            target_rtps,
+            rtps: self.ticks as f64 / elapsed.as_secs_f64(),
+            mspt,
+            tick_times,
+            pending_ticks,
+            skipped_ticks: self.skipped_ticks,
+            players,
+            broadcast_queue_depth: self.broadcast_queue_depth,
+            priv_queue_depth: self.priv_queue_depth,
+        };
+        if CONFIG.metrics_enabled {
+            METRICS
+                .plots
+                .lock()
+                .unwrap()
+                .insert((plot_x, plot_z), last.clone());
+        }
+        *self = PlotStats {
+            sample_tick_times: self.sample_tick_times,
+            last: Some(last),
+            ..Default::default()
+        };
+        true
+    }
+}
+
+/// Removes the statistics of a plot that unloaded
+pub fn remove_plot(plot_x: i32, plot_z: i32) {
+    METRICS.plots.lock().unwrap().remove(&(plot_x, plot_z));
+}
+
+/// Returns the value at quantile `q` of the sorted samples
+fn quantile(sorted: &[Duration], q: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::default();
+    }
+    let index = ((sorted.len() - 1) as f64 * q).round() as usize;
+    sorted[index]
+}
+
+/// The name, help text and value of a metric every plot has
+type PlotGauge = (&'static str, &'static str, fn(&PlotMetrics) -> f64);
+
+fn write_metric(out: &mut String, name: &str, ty: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, ty);
+}
+
+/// Renders all metrics in the Prometheus text format
+fn render() -> String {
+    let mut out = String::new();
+    let load = |value: &AtomicUsize| value.load(Ordering::Relaxed);
+
+    write_metric(
+        &mut out,
+        "mchprs_online_players",
+        "gauge",
+        "Players online.",
+    );
+    let _ = writeln!(
+        out,
+        "mchprs_online_players {}",
+        load(&METRICS.online_players)
+    );
+    write_metric(
+        &mut out,
+        "mchprs_handshaking_clients",
+        "gauge",
+        "Clients connected that haven't joined yet.",
+    );
+    let _ = writeln!(
+        out,
+        "mchprs_handshaking_clients {}",
+        load(&METRICS.handshaking_clients)
+    );
+    write_metric(
+        &mut out,
+        "mchprs_packets_received_total",
+        "counter",
+        "Packets received from clients.",
+    );
+    let _ = writeln!(
+        out,
+        "mchprs_packets_received_total {}",
+        METRICS.packets_received.load(Ordering::Relaxed)
+    );
+    write_metric(
+        &mut out,
+        "mchprs_packets_sent_total",
+        "counter",
+        "Packets sent to clients.",
+    );
+    let _ = writeln!(
+        out,
+        "mchprs_packets_sent_total {}",
+        METRICS.packets_sent.load(Ordering::Relaxed)
+    );
+    write_metric(
+        &mut out,
+        "mchprs_server_queue_depth",
+        "gauge",
+        "Messages waiting for the server thread the last time it handled messages.",
+    );
+    let _ = writeln!(
+        out,
+        "mchprs_server_queue_depth {}",
+        load(&METRICS.server_queue_depth)
+    );
+
+    let plots = METRICS.plots.lock().unwrap().clone();
+    let mut plots: Vec<((i32, i32), PlotMetrics)> = plots.into_iter().collect();
+    plots.sort_by_key(|(pos, _)| *pos);
+
+    write_metric(&mut out, "mchprs_loaded_plots", "gauge", "Plots running.");
+    let _ = writeln!(out, "mchprs_loaded_plots {}", plots.len());
+
+    let plot_gauges: [PlotGauge; 8] = [
+        (
+            "mchprs_plot_target_rtps",
+            "The rtps the plot is set to, or 0 if it is unlimited.",
+            |p| p.target_rtps as f64,
+        ),
+        ("mchprs_plot_rtps", "The rtps the plot achieved.", |p| {
+            p.rtps
+        }),
+        (
+            "mchprs_plot_mspt",
+            "The average tick time of the plot in milliseconds.",
+            |p| p.mspt,
+        ),
+        (
+            "mchprs_plot_skipped_ticks",
+            "Ticks the plot skipped during the last second because it couldn't keep up.",
+            |p| p.skipped_ticks as f64,
+        ),
+        (
+            "mchprs_plot_pending_ticks",
+            "Block updates scheduled in the plot.",
+            |p| p.pending_ticks as f64,
+        ),
+        ("mchprs_plot_players", "Players in the plot.", |p| {
+            p.players as f64
+        }),
+        (
+            "mchprs_plot_broadcast_queue_depth",
+            "The most broadcast messages waiting for the plot at once.",
+            |p| p.broadcast_queue_depth as f64,
+        ),
+        (
+            "mchprs_plot_priv_queue_depth",
+            "The most private messages waiting for the plot at once.",
+            |p| p.priv_queue_depth as f64,
+        ),
+    ];
+    for (name, help, value) in plot_gauges.iter() {
+        write_metric(&mut out, name, "gauge", help);
+        for ((x, z), plot) in &plots {
+            let _ = writeln!(out, "{}{{plot=\"{},{}\"}} {}", name, x, z, value(plot));
+        }
+    }
+    write_metric(
+        &mut out,
+        "mchprs_plot_tick_duration_seconds",
+        "gauge",
+        "Tick times of the plot during the last second.",
+    );
+    for ((x, z), plot) in &plots {
+        let tick_times = match &plot.tick_times {
+            Some(tick_times) => tick_times,
+            None => continue,
+        };
+        for (q, time) in QUANTILES.iter().zip(tick_times.iter()) {
+            let _ = writeln!(
+                out,
+                "mchprs_plot_tick_duration_seconds{{plot=\"{},{}\",quantile=\"{}\"}} {}",
+                x, z, q, time
+            );
+        }
+    }
+    out
+}
+
+fn handle_request(mut stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut request = Vec::new();
+    let mut buf = [0; 1024];
+    // Read until the end of the headers, the body is ignored
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") && request.len() < 8192 {
+        let len = stream.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        request.extend_from_slice(&buf[..len]);
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut request_line = request.lines().next().unwrap_or_default().split(' ');
+    let (status, content_type, body) = match (request_line.next(), request_line.next()) {
+        (Some("GET"), Some("/metrics")) => (
+            "200 OK",
+            "text/plain; version=0.0.4; charset=utf-8",
+            render(),
+        ),
+        _ => ("404 Not Found", "text/plain", "Not Found\n".to_owned()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    )
+}
+
+/// Starts serving metrics over HTTP at `/metrics` if metrics are enabled
+pub fn start() {
+    if !CONFIG.metrics_enabled {
+        return;
+    }
+    let listener = match TcpListener::bind(&CONFIG.metrics_bind_address) {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!(
+                "Error binding metrics to {}: {}",
+                CONFIG.metrics_bind_address, err
+            );
+            return;
+        }
+    };
+    info!(
+        "Serving metrics on http://{}/metrics",
+        CONFIG.metrics_bind_address
+    );
+    thread::Builder::new()
+        .name("Metrics".to_owned())
+        .spawn(move || {
+            // Each connection gets its own thread so a slow client can't hold up the others
+            let connections = Arc::new(AtomicUsize::new(0));
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        warn!("Error accepting metrics connection: {}", err);
+                        continue;
+                    }
+                };
+                if connections.load(Ordering::SeqCst) >= MAX_CONNECTIONS {
+                    warn!("Too many metrics connections, disconnecting a client");
+                    continue;
+                }
+                let connections = connections.clone();
+                connections.fetch_add(1, Ordering::SeqCst);
+                thread::spawn(move || {
+                    let _ = handle_request(stream);
+                    connections.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        })
+        .unwrap();
+}
+
+#[test]
+fn tick_time_quantiles() {
+    let samples: Vec<Duration> = (1..=100).map(Duration::from_micros).collect();
+    assert_eq!(quantile(&samples, 0.5), Duration::from_micros(51));
+    assert_eq!(quantile(&samples, 0.99), Duration::from_micros(99));
+    assert_eq!(quantile(&samples, 1.0), Duration::from_micros(100));
+    assert_eq!(quantile(&[], 0.5), Duration::default());
+}
@@ -1,8 +1,9 @@
 pub mod packets;
 
+use crate::metrics::METRICS;
 use packets::{read_packet, serverbound::ServerBoundPacket, PacketEncoder};
 use std::io::Write;
-use std::net::{Shutdown, TcpListener, TcpStream};
+use std::net::{IpAddr, Shutdown, TcpListener, TcpStream};
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     mpsc, Arc,
@@ -30,6 +31,9 @@ pub struct NetworkClient {
     compressed: Arc<AtomicBool>,
     pub username: Option<String>,
     pub uuid: Option<u128>,
+    /// The address the client is connecting from. When using BungeeCord, this is the
+    /// address forwarded by the proxy.
+    pub ip: Option<IpAddr>,
 }
 
 impl NetworkClient {
@@ -45,6 +49,7 @@ impl NetworkClient {
                 // This will cause the client to disconnect
                 Err(_) => return,
             };
+            METRICS.packets_received.fetch_add(1, Ordering::Relaxed);
             if sender.send(packet).is_err() {
                 return;
             }
@@ -72,6 +77,7 @@ impl NetworkClient {
     }
 
     pub fn send_packet(&mut self, data: &PacketEncoder) {
+        METRICS.packets_sent.fetch_add(1, Ordering::Relaxed);
         if self.compressed.load(Ordering::Relaxed) {
             let _ = self.stream.write_all(&data.compressed());
         } else {
@@ -98,6 +104,7 @@ impl NetworkServer {
 
         for (index, stream) in listener.incoming().enumerate() {
             let stream = stream.unwrap();
+            let ip = stream.peer_addr().ok().map(|addr| addr.ip());
             let (packet_sender, packet_receiver) = mpsc::channel();
             let compressed = Arc::new(AtomicBool::new(false));
             let client_stream = stream.try_clone().unwrap();
@@ -116,6 +123,7 @@ impl NetworkServer {
                     compressed,
                     username: None,
                     uuid: None,
+                    ip,
                 })
                 .unwrap();
         }
@@ -217,6 +217,67 @@ impl ClientBoundPacket for C0BBlockChange {
     }
 }
 
+pub enum C0CBossBar {
+    /// Shows a new boss bar. The title is in json format and the health goes from 0 to 1.
+    Add {
+        uuid: u128,
+        title: String,
+        health: f32,
+        color: i32,
+        division: i32,
+        flags: u8,
+    },
+    Remove(u128),
+    UpdateHealth(u128, f32),
+    UpdateTitle(u128, String),
+    UpdateStyle(u128, i32, i32),
+}
+
+impl ClientBoundPacket for C0CBossBar {
+    fn encode(self) -> PacketEncoder {
+        let mut buf = Vec::new();
+        match self {
+            C0CBossBar::Add {
+                uuid,
+                title,
+                health,
+                color,
+                division,
+                flags,
+            } => {
+                buf.write_uuid(uuid);
+                buf.write_varint(0);
+                buf.write_string(32767, &title);
+                buf.write_float(health);
+                buf.write_varint(color);
+                buf.write_varint(division);
+                buf.write_unsigned_byte(flags);
+            }
+            C0CBossBar::Remove(uuid) => {
+                buf.write_uuid(uuid);
+                buf.write_varint(1);
+            }
+            C0CBossBar::UpdateHealth(uuid, health) => {
+                buf.write_uuid(uuid);
+                buf.write_varint(2);
+                buf.write_float(health);
+            }
+            C0CBossBar::UpdateTitle(uuid, title) => {
+                buf.write_uuid(uuid);
+                buf.write_varint(3);
+                buf.write_string(32767, &title);
+            }
+            C0CBossBar::UpdateStyle(uuid, color, division) => {
+                buf.write_uuid(uuid);
+                buf.write_varint(4);
+                buf.write_varint(color);
+                buf.write_varint(division);
+            }
+        }
+        PacketEncoder::new(buf, 0x0C)
+    }
+}
+
 pub struct C0EChatMessage {
     pub message: String,
     pub position: i8,
@@ -240,6 +301,9 @@ pub enum C10DeclareCommandsNodeParser {
     Float(f32, f32),
     BlockPos,
     BlockState,
+    Bool,
+    Word,
+    GreedyString,
 }
 
 impl C10DeclareCommandsNodeParser {
@@ -253,6 +317,15 @@ impl C10DeclareCommandsNodeParser {
             Vec3 => buf.write_string(32767, "minecraft:vec3"),
             BlockPos => buf.write_string(32767, "minecraft:block_pos"),
             BlockState => buf.write_string(32767, "minecraft:block_state"),
+            Bool => buf.write_string(32767, "brigadier:bool"),
+            Word => {
+                buf.write_string(32767, "brigadier:string");
+                buf.write_varint(0); // Single word
+            }
+            GreedyString => {
+                buf.write_string(32767, "brigadier:string");
+                buf.write_varint(2); // Greedy phrase
+            }
             Integer(min, max) => {
                 buf.write_string(32767, "brigadier:integer");
                 buf.write_byte(3); // Supply min and max value
@@ -107,6 +107,7 @@ fn read_decompressed<T: PacketDecoderExt>(
             0x2B => Box::new(S2BUpdateSign::decode(reader)?),
             0x2C => Box::new(S2CAnimation::decode(reader)?),
             0x2E => Box::new(S2EPlayerBlockPlacemnt::decode(reader)?),
+            0x2F => Box::new(S2FUseItem::decode(reader)?),
             _ => Box::new(SUnknown),
         },
     })
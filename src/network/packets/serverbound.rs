@@ -36,6 +36,7 @@ pub trait ServerBoundPacketHandler {
     ) {
     }
     fn handle_update_sign(&mut self, _packet: S2BUpdateSign, _player_idx: usize) {}
+    fn handle_use_item(&mut self, _packet: S2FUseItem, _player_idx: usize) {}
     fn handle_unknown(&mut self, _packet: SUnknown, _player_idx: usize) {}
 }
 
@@ -467,3 +468,19 @@ impl ServerBoundPacket for S2BUpdateSign {
         handler.handle_update_sign(*self, player_idx);
     }
 }
+
+pub struct S2FUseItem {
+    pub hand: i32,
+}
+
+impl ServerBoundPacket for S2FUseItem {
+    fn decode<T: PacketDecoderExt>(decoder: &mut T) -> DecodeResult<Self> {
+        Ok(S2FUseItem {
+            hand: decoder.read_varint()?,
+        })
+    }
+
+    fn handle(self: Box<Self>, handler: &mut dyn ServerBoundPacketHandler, player_idx: usize) {
+        handler.handle_use_item(*self, player_idx);
+    }
+}
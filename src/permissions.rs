@@ -0,0 +1,168 @@
+use crate::plot::database;
+use lazy_static::lazy_static;
+use log::error;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// The group every player is a member of
+pub const DEFAULT_GROUP: &str = "default";
+
+/// The permissions of a single player.
+///
+/// Permission nodes are dot separated names like `mchprs.command.stop`. A node ending in
+/// `*` grants or denies every node that starts with the part before it, so
+/// `mchprs.worldedit.*` covers all worldedit commands and `*` covers everything.
+/// Players can be given nodes directly or through groups. The most specific matching node
+/// decides whether a player has a permission, and nodes given to the player directly win
+/// over nodes from their groups. Operators have every permission.
+#[derive(Debug, Default)]
+pub struct Permissions {
+    op: bool,
+    player: HashMap<String, bool>,
+    groups: HashMap<String, bool>,
+}
+
+impl Permissions {
+    /// Creates the permissions from the nodes given to the player directly and the nodes
+    /// of all their groups. If groups disagree on a node, the group granting it wins.
+    pub fn new(player: Vec<(String, bool)>, groups: Vec<(String, bool)>) -> Permissions {
+        let mut group_nodes = HashMap::new();
+        for (node, value) in groups {
+            *group_nodes.entry(node).or_insert(false) |= value;
+        }
+        Permissions {
+            op: false,
+            player: player.into_iter().collect(),
+            groups: group_nodes,
+        }
+    }
+
+    /// The permissions of an operator
+    pub fn op() -> Permissions {
+        Permissions {
+            op: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn has(&self, node: &str) -> bool {
+        if self.op {
+            return true;
+        }
+        for candidate in node_candidates(node) {
+            if let Some(&value) = self.player.get(&candidate) {
+                return value;
+            }
+            if let Some(&value) = self.groups.get(&candidate) {
+                return value;
+            }
+        }
+        false
+    }
+}
+
+/// Returns the node and the wildcards that match it, from most to least specific.
+/// For `a.b.c` these are `a.b.c`, `a.b.*`, `a.*` and `*`.
+fn node_candidates(node: &str) -> Vec<String> {
+    let mut candidates = vec![node.to_string()];
+    let parts: Vec<&str> = node.split('.').collect();
+    for len in (0..parts.len()).rev() {
+        let mut wildcard = parts[..len].join(".");
+        if len > 0 {
+            wildcard.push('.');
+        }
+        wildcard.push('*');
+        if wildcard != node {
+            candidates.push(wildcard);
+        }
+    }
+    candidates
+}
+
+/// Returns true if the node only contains lowercase letters, digits, `-` and `_` separated
+/// by dots, with `*` only allowed as the last part.
+pub fn is_valid_node(node: &str) -> bool {
+    let parts: Vec<&str> = node.split('.').collect();
+    parts.iter().enumerate().all(|(i, part)| {
+        (*part == "*" && i == parts.len() - 1)
+            || (!part.is_empty()
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_'))
+    })
+}
+
+/// Loads the permissions of a player from the database
+pub fn get_permissions(uuid: &str) -> database::Result<Permissions> {
+    if database::is_op(uuid)? {
+        return Ok(Permissions::op());
+    }
+    Ok(Permissions::new(
+        database::get_user_permissions(uuid)?,
+        database::get_user_group_permissions(uuid)?,
+    ))
+}
+
+lazy_static! {
+    static ref CACHE: Mutex<HashMap<u128, Permissions>> = Mutex::new(HashMap::new());
+}
+
+/// Clears the cached permissions. This has to be called whenever permissions, groups or
+/// operators change, because a group change can affect every player.
+pub fn invalidate_cache() {
+    CACHE.lock().unwrap().clear();
+}
+
+/// Returns true if the player has the permission. Permissions are loaded from the database
+/// once and cached until `invalidate_cache` is called. Database errors are logged and deny
+/// the permission.
+pub fn has_permission(uuid: u128, node: &str) -> bool {
+    // The cache stays locked while loading so an invalidation can't be lost in between
+    let mut cache = CACHE.lock().unwrap();
+    if let Some(permissions) = cache.get(&uuid) {
+        return permissions.has(node);
+    }
+    match get_permissions(&format!("{:032x}", uuid)) {
+        Ok(permissions) => {
+            let has = permissions.has(node);
+            cache.insert(uuid, permissions);
+            has
+        }
+        Err(err) => {
+            error!("Error loading permissions: {}", err);
+            false
+        }
+    }
+}
+
+#[test]
+fn permission_wildcards() {
+    let node = |node: &str, value| (node.to_string(), value);
+    let permissions = Permissions::new(
+        vec![node("mchprs.worldedit.load", true)],
+        vec![
+            node("mchprs.command.*", true),
+            node("mchprs.command.stop", false),
+            node("mchprs.worldedit.*", false),
+            node("mchprs.worldedit.set", false),
+            node("mchprs.worldedit.set", true),
+        ],
+    );
+    assert!(permissions.has("mchprs.command.rtps"));
+    assert!(permissions.has("mchprs.command.rtps.high"));
+    assert!(!permissions.has("mchprs.command.stop"));
+    assert!(permissions.has("mchprs.worldedit.load"));
+    assert!(permissions.has("mchprs.worldedit.set"));
+    assert!(!permissions.has("mchprs.worldedit.copy"));
+    assert!(!permissions.has("other.node"));
+
+    let admin = Permissions::new(Vec::new(), vec![node("*", true)]);
+    assert!(admin.has("mchprs.command.stop"));
+    assert!(Permissions::op().has("mchprs.command.stop"));
+
+    assert!(is_valid_node("mchprs.worldedit.*"));
+    assert!(is_valid_node("*"));
+    assert!(!is_valid_node("mchprs.*.load"));
+    assert!(!is_valid_node("mchprs..load"));
+    assert!(!is_valid_node("Mchprs.stop"));
+}
@@ -3,11 +3,13 @@ use crate::chat::ChatComponent;
 use crate::items::{Item, ItemStack};
 use crate::network::packets::clientbound::*;
 use crate::network::NetworkClient;
-use crate::plot::worldedit::{WorldEditClipboard, WorldEditUndo};
+use crate::plot::geometry::PLOT_GEOMETRY;
+use crate::plot::worldedit::{WorldEditClipboard, WorldEditTool, WorldEditUndo};
 use byteorder::{BigEndian, ReadBytesExt};
 use log::warn;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::collections::HashMap;
 use std::fmt;
 use std::fs::{self, OpenOptions};
 use std::io::{Cursor, Write};
@@ -52,6 +54,41 @@ pub struct PlayerData {
     fly_speed: f32,
     walk_speed: f32,
     gamemode: Gamemode,
+    worldedit_tools: HashMap<u32, WorldEditTool>,
+}
+
+/// The player data that was saved before worldedit tools could be bound. It is still
+/// loaded so players don't lose their inventories.
+#[derive(Debug, Deserialize)]
+struct LegacyPlayerData {
+    on_ground: bool,
+    flying: bool,
+    motion: Vec<f64>,
+    position: Vec<f64>,
+    rotation: Vec<f32>,
+    inventory: Vec<InventoryEntry>,
+    selected_item_slot: i32,
+    fly_speed: f32,
+    walk_speed: f32,
+    gamemode: Gamemode,
+}
+
+impl From<LegacyPlayerData> for PlayerData {
+    fn from(data: LegacyPlayerData) -> PlayerData {
+        PlayerData {
+            on_ground: data.on_ground,
+            flying: data.flying,
+            motion: data.motion,
+            position: data.position,
+            rotation: data.rotation,
+            inventory: data.inventory,
+            selected_item_slot: data.selected_item_slot,
+            fly_speed: data.fly_speed,
+            walk_speed: data.walk_speed,
+            gamemode: data.gamemode,
+            worldedit_tools: HashMap::new(),
+        }
+    }
 }
 
 bitflags! {
@@ -108,8 +145,15 @@ pub struct Player {
     /// The saved sections used for worldedit //undo
     /// Each entry stores the plot coords and the clipboard
     pub worldedit_undo: Vec<WorldEditUndo>,
+    /// The worldedit tools bound to each hotbar slot
+    pub worldedit_tools: HashMap<u32, WorldEditTool>,
+    /// Set when a tool was used on a block. The client can send a use item packet right
+    /// after clicking a block, which shouldn't use the tool a second time.
+    pub worldedit_tool_used: bool,
     /// Commands are stored so they can be handled after packets
     pub command_queue: Vec<String>,
+    /// Whether the rtps and mspt of the plot are shown in a boss bar
+    pub show_performance: bool,
 }
 
 impl fmt::Debug for Player {
@@ -144,7 +188,9 @@ impl Player {
     /// It will be created.
     pub fn load_player(uuid: u128, username: String, client: NetworkClient) -> Player {
         if let Ok(data) = fs::read(format!("./world/players/{:032x}", uuid)) {
-            let player_data: PlayerData = match bincode::deserialize(&data) {
+            let player_data = bincode::deserialize::<PlayerData>(&data)
+                .or_else(|_| bincode::deserialize::<LegacyPlayerData>(&data).map(PlayerData::from));
+            let player_data = match player_data {
                 Ok(data) => data,
                 Err(_) => {
                     warn!("There was an error loading the player data for {}, player data will be reset.", username);
@@ -194,7 +240,10 @@ impl Player {
                 second_position: None,
                 worldedit_clipboard: None,
                 worldedit_undo: Vec::new(),
+                worldedit_tools: player_data.worldedit_tools,
+                worldedit_tool_used: false,
                 command_queue: Vec::new(),
+                show_performance: false,
             }
         } else {
             Player::create_player(uuid, username, client)
@@ -205,16 +254,17 @@ impl Player {
     fn create_player(uuid: u128, username: String, client: NetworkClient) -> Player {
         let mut inventory: Vec<Option<ItemStack>> = vec![];
         inventory.resize_with(46, || None);
+        let (x, z) = PLOT_GEOMETRY.center(0, 0);
         Player {
             uuid,
             username,
             skin_parts: Default::default(),
             selected_slot: 0,
-            x: 128f64,
+            x,
             y: 128f64,
-            z: 128f64,
-            last_chunk_x: 8,
-            last_chunk_z: 8,
+            z,
+            last_chunk_x: x as i32 >> 4,
+            last_chunk_z: z as i32 >> 4,
             yaw: 0f32,
             pitch: 0f32,
             entity_id: client.id,
@@ -233,7 +283,10 @@ impl Player {
             second_position: None,
             worldedit_clipboard: None,
             worldedit_undo: Vec::new(),
+            worldedit_tools: HashMap::new(),
+            worldedit_tool_used: false,
             command_queue: Vec::new(),
+            show_performance: false,
         }
     }
 
@@ -243,6 +296,7 @@ impl Player {
         let mut file = OpenOptions::new()
             .write(true)
             .create(true)
+            .truncate(true)
             .open(format!("./world/players/{:032x}", self.uuid))
             .unwrap();
         let mut inventory: Vec<InventoryEntry> = Vec::new();
@@ -273,6 +327,7 @@ impl Player {
             rotation: vec![self.pitch, self.yaw],
             selected_item_slot: self.selected_slot as i32,
             walk_speed: self.walk_speed,
+            worldedit_tools: self.worldedit_tools.clone(),
         })
         .unwrap();
         file.write_all(&data).unwrap();
@@ -457,3 +512,29 @@ impl Player {
         self.client.send_packet(&change_game_state);
     }
 }
+
+#[test]
+fn legacy_player_data() {
+    let data = PlayerData {
+        on_ground: true,
+        flying: false,
+        motion: vec![0.0; 3],
+        position: vec![1.0, 2.0, 3.0],
+        rotation: vec![0.0; 2],
+        inventory: Vec::new(),
+        selected_item_slot: 4,
+        fly_speed: 1.0,
+        walk_speed: 1.0,
+        gamemode: Gamemode::Creative,
+        worldedit_tools: HashMap::new(),
+    };
+    let mut bytes = bincode::serialize(&data).unwrap();
+    // Data saved before tools existed ends before the length of the tool map
+    bytes.truncate(bytes.len() - 8);
+    assert!(bincode::deserialize::<PlayerData>(&bytes).is_err());
+    let legacy: PlayerData = bincode::deserialize::<LegacyPlayerData>(&bytes)
+        .unwrap()
+        .into();
+    assert_eq!(legacy.position, data.position);
+    assert_eq!(legacy.selected_item_slot, 4);
+}
@@ -0,0 +1,213 @@
+use super::worldedit::WorldEditClipboard;
+use crate::blocks::{BlockEntity, BlockPos};
+use crate::config::CONFIG;
+use crate::world::storage::{PalettedBitBuffer, PalettedBitBufferData};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// The folder named clipboards are saved in, with a folder for each player
+const CLIPBOARDS_DIR: &str = "./world/clipboards";
+const MAX_NAME_LENGTH: usize = 32;
+
+/// A clipboard as it is saved to disk
+#[derive(Serialize, Deserialize)]
+struct ClipboardData {
+    offset_x: i32,
+    offset_y: i32,
+    offset_z: i32,
+    size_x: u32,
+    size_y: u32,
+    size_z: u32,
+    data: PalettedBitBufferData,
+    block_entities: HashMap<BlockPos, BlockEntity>,
+}
+
+#[derive(Debug)]
+pub enum ClipboardError {
+    InvalidName(String),
+    NotFound(String),
+    AlreadyExists(String),
+    TooManyClipboards,
+    QuotaExceeded,
+    Corrupted(String),
+    Io(io::Error),
+}
+
+impl fmt::Display for ClipboardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClipboardError::InvalidName(name) => write!(
+                f,
+                "invalid clipboard name: {}. Names can only use letters, numbers, - and _ and be at most {} characters long.",
+                name, MAX_NAME_LENGTH
+            ),
+            ClipboardError::NotFound(name) => write!(f, "there is no clipboard named {}", name),
+            ClipboardError::AlreadyExists(name) => {
+                write!(f, "there is already a clipboard named {}", name)
+            }
+            ClipboardError::TooManyClipboards => write!(
+                f,
+                "there can be at most {} saved clipboards",
+                CONFIG.clipboard_quota
+            ),
+            ClipboardError::QuotaExceeded => write!(
+                f,
+                "saved clipboards can take up at most {} MB",
+                CONFIG.clipboard_quota_mb
+            ),
+            ClipboardError::Corrupted(name) => write!(f, "the clipboard {} is corrupted", name),
+            ClipboardError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<io::Error> for ClipboardError {
+    fn from(err: io::Error) -> ClipboardError {
+        ClipboardError::Io(err)
+    }
+}
+
+pub type ClipboardResult<T> = Result<T, ClipboardError>;
+
+/// Names are used as file names, so they can't contain anything that could reach outside
+/// of the player's folder
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_NAME_LENGTH
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn player_dir(owner: u128) -> PathBuf {
+    PathBuf::from(CLIPBOARDS_DIR).join(format!("{:032x}", owner))
+}
+
+fn clipboard_path(owner: u128, name: &str) -> ClipboardResult<PathBuf> {
+    if !is_valid_name(name) {
+        return Err(ClipboardError::InvalidName(name.to_owned()));
+    }
+    Ok(player_dir(owner).join(format!("{}.clip", name)))
+}
+
+/// Returns the names and sizes in bytes of the clipboards a player has saved, sorted by name
+pub fn list(owner: u128) -> ClipboardResult<Vec<(String, u64)>> {
+    let entries = match fs::read_dir(player_dir(owner)) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut clipboards = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("clip") {
+            continue;
+        }
+        if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+            clipboards.push((name.to_owned(), entry.metadata()?.len()));
+        }
+    }
+    clipboards.sort();
+    Ok(clipboards)
+}
+
+/// Saves the clipboard under the name. If there already is a clipboard with the name, it is
+/// replaced if `replace` is true and the save fails otherwise. Fails if the player would go
+/// over their quota.
+pub fn save(
+    owner: u128,
+    name: &str,
+    clipboard: &WorldEditClipboard,
+    replace: bool,
+) -> ClipboardResult<()> {
+    let path = clipboard_path(owner, name)?;
+    if !replace && path.exists() {
+        return Err(ClipboardError::AlreadyExists(name.to_owned()));
+    }
+    let data = ClipboardData {
+        offset_x: clipboard.offset_x,
+        offset_y: clipboard.offset_y,
+        offset_z: clipboard.offset_z,
+        size_x: clipboard.size_x,
+        size_y: clipboard.size_y,
+        size_z: clipboard.size_z,
+        data: clipboard.data.save(),
+        block_entities: clipboard.block_entities.clone(),
+    };
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&bincode::serialize(&data).unwrap())?;
+    let bytes = encoder.finish()?;
+
+    // The clipboard being replaced doesn't count towards the quota
+    let others: Vec<_> = list(owner)?
+        .into_iter()
+        .filter(|(other, _)| other != name)
+        .collect();
+    if others.len() as i64 >= CONFIG.clipboard_quota {
+        return Err(ClipboardError::TooManyClipboards);
+    }
+    let used: u64 = others.iter().map(|(_, size)| size).sum();
+    if (used + bytes.len() as u64) as i64 > CONFIG.clipboard_quota_mb * 1024 * 1024 {
+        return Err(ClipboardError::QuotaExceeded);
+    }
+
+    fs::create_dir_all(player_dir(owner))?;
+    let mut file = File::create(path)?;
+    file.write_all(&bytes)?;
+    Ok(())
+}
+
+pub fn load(owner: u128, name: &str) -> ClipboardResult<WorldEditClipboard> {
+    let path = clipboard_path(owner, name)?;
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Err(ClipboardError::NotFound(name.to_owned()))
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let corrupted = || ClipboardError::Corrupted(name.to_owned());
+    let data: ClipboardData =
+        bincode::deserialize_from(GzDecoder::new(file)).map_err(|_| corrupted())?;
+    let volume = data.size_x as usize * data.size_y as usize * data.size_z as usize;
+    let buffer = PalettedBitBuffer::load_data(data.data).ok_or_else(corrupted)?;
+    if buffer.entries() != volume {
+        return Err(corrupted());
+    }
+    Ok(WorldEditClipboard {
+        offset_x: data.offset_x,
+        offset_y: data.offset_y,
+        offset_z: data.offset_z,
+        size_x: data.size_x,
+        size_y: data.size_y,
+        size_z: data.size_z,
+        data: buffer,
+        block_entities: data.block_entities,
+    })
+}
+
+/// Deletes a saved clipboard. Returns false if there was no clipboard with the name.
+pub fn delete(owner: u128, name: &str) -> ClipboardResult<bool> {
+    match fs::remove_file(clipboard_path(owner, name)?) {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[test]
+fn clipboard_names() {
+    assert!(is_valid_name("alu_v2-final"));
+    assert!(!is_valid_name(""));
+    assert!(!is_valid_name("../../config"));
+    assert!(!is_valid_name("a/b"));
+    assert!(!is_valid_name(&"a".repeat(MAX_NAME_LENGTH + 1)));
+}
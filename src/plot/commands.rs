@@ -1,57 +1,1004 @@
-use super::{database, worldedit, Plot};
+use super::geometry::{PlotRegion, PLOT_GEOMETRY};
+use super::profiler::{Profiler, DEFAULT_PROFILE_DURATION};
+use super::{database, worldedit, Plot, TpsMode, Warp, MAX_RTPS};
+use crate::blocks::WireAlgorithm;
 use crate::network::packets::clientbound::{
     C10DeclareCommands, C10DeclareCommandsNode as Node, C10DeclareCommandsNodeParser as Parser,
     ClientBoundPacket,
 };
 use crate::network::packets::PacketEncoder;
-use crate::player::Gamemode;
-use crate::server::Message;
+use crate::permissions;
+use crate::player::{Gamemode, Player};
+use crate::server::{ban_message, Message};
 use crate::world::World;
-use log::info;
+use log::{error, info};
 
 use bitflags::_core::i32::MAX;
+use chrono::{Local, TimeZone};
+use std::net::IpAddr;
+use std::sync::mpsc::Sender;
 use std::time::{Duration, Instant};
 
+/// How many ticks `/rwarp` runs at most if no limit is given
+const DEFAULT_WARP_TICKS: u32 = 10_000;
+
+/// Players need the `mchprs.command.rtps.high` permission to set the rtps higher than this
+const HIGH_RTPS: u32 = 1000;
+
+/// Returns the permission node needed to run a command
+fn command_permission(command: &str) -> Option<&'static str> {
+    Some(match command {
+        "//load" => "mchprs.worldedit.load",
+        "/rtps" => "mchprs.command.rtps",
+        "/radv" | "/radvance" => "mchprs.command.radvance",
+        "/rwarp" => "mchprs.command.rwarp",
+        "/rprofile" => "mchprs.command.rprofile",
+        "/teleport" | "/tp" => "mchprs.command.teleport",
+        "/stop" => "mchprs.command.stop",
+        "/plot" | "/p" => "mchprs.command.plot",
+        "/speed" => "mchprs.command.speed",
+        "/gmsp" | "/gmc" | "/gamemode" => "mchprs.command.gamemode",
+        "/permission" | "/perm" => "mchprs.command.permission",
+        "/whitelist" => "mchprs.command.whitelist",
+        "/ban" | "/ban-ip" | "/banlist" => "mchprs.command.ban",
+        "/pardon" | "/pardon-ip" => "mchprs.command.pardon",
+        "/op" | "/deop" => "mchprs.command.op",
+        _ => return None,
+    })
+}
+
+/// The longest a ban can last, in seconds
+const MAX_BAN_DURATION: i64 = 100 * 365 * 24 * 60 * 60;
+
+/// Parses a duration like `30m`, `12h` or `7d` into seconds. Durations too long to fit
+/// are saturated so they can be rejected as too long.
+fn parse_duration(duration: &str) -> Option<i64> {
+    let unit = match duration.chars().last()? {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    let amount = duration[..duration.len() - 1].parse::<i64>().ok()?;
+    if amount > 0 {
+        Some(amount.saturating_mul(unit))
+    } else {
+        None
+    }
+}
+
+/// Formats a unix timestamp from the plot database for chat
+fn format_timestamp(timestamp: Option<i64>, missing: &str) -> String {
+    match timestamp.and_then(|timestamp| Local.timestamp_opt(timestamp, 0).single()) {
+        Some(time) => time.format("%Y-%m-%d %H:%M").to_string(),
+        None => missing.to_string(),
+    }
+}
+
 impl Plot {
     /// Handles a command that starts with `/plot` or `/p`
-    fn handle_plot_command(&mut self, player: usize, command: &str, _args: Vec<&str>) {
-        let plot_x = self.players[player].x as i32 >> 8;
-        let plot_z = self.players[player].z as i32 >> 8;
+    fn handle_plot_command(
+        &mut self,
+        player: usize,
+        command: &str,
+        args: Vec<&str>,
+    ) -> database::Result<()> {
+        let (plot_x, plot_z) = PLOT_GEOMETRY.plot_at(
+            self.players[player].x.floor() as i32,
+            self.players[player].z.floor() as i32,
+        );
         match command {
             "claim" | "c" => {
-                if database::is_claimed(plot_x, plot_z).unwrap() {
+                if database::is_claimed(plot_x, plot_z)? {
                     self.players[player].send_system_message("Plot is already claimed!");
                 } else {
-                    self.claim_plot(plot_x, plot_z, player);
+                    self.claim_plot(plot_x, plot_z, player)?;
                 }
             }
             "info" | "i" => {
-                if let Some(owner) = database::get_plot_owner(plot_x, plot_z) {
+                if let Some(owner) = database::get_plot_owner(plot_x, plot_z)? {
+                    let last_seen = database::get_user_last_seen(owner.clone())?;
+                    let name = database::get_cached_username(owner.clone())?.unwrap_or(owner);
                     self.players[player].send_system_message(&format!(
-                        "Plot owner is: {}",
-                        database::get_cached_username(owner.clone()).unwrap_or(owner)
+                        "Plot owner is: {} (last seen {})",
+                        name,
+                        format_timestamp(last_seen, "never")
                     ));
                 } else {
                     self.players[player].send_system_message("Plot is not owned by anyone.");
+                    return Ok(());
+                }
+                let timestamps = database::get_plot_timestamps(plot_x, plot_z)?.unwrap_or_default();
+                let settings = &self.settings;
+                let mut lines = Vec::new();
+                if !self.region.is_single() {
+                    lines.push(format!("Merged plots: {}", self.region));
+                }
+                if let Some(name) = &settings.name {
+                    lines.push(format!("Name: {}", name));
+                }
+                if let Some(description) = &settings.description {
+                    lines.push(format!("Description: {}", description));
+                }
+                if let Some(greeting) = &settings.greeting {
+                    lines.push(format!("Greeting: {}", greeting));
+                }
+                lines.push(format!(
+                    "Default rtps: {}",
+                    settings
+                        .default_rtps
+                        .map_or_else(|| "none".to_string(), |tps| tps.to_string())
+                ));
+                lines.push(format!("Keep loaded: {}", settings.keep_loaded));
+                lines.push(format!("Auto pause: {}", settings.auto_pause));
+                lines.push(format!("Public build: {}", settings.public_build));
+                lines.push(format!(
+                    "Wire algorithm: {}",
+                    settings.wire_algorithm.name()
+                ));
+                lines.push(format!(
+                    "Created: {}",
+                    format_timestamp(timestamps.created_at, "unknown")
+                ));
+                lines.push(format!(
+                    "Last modified: {}",
+                    format_timestamp(timestamps.last_modified, "never")
+                ));
+                lines.push(format!(
+                    "Last visited: {}",
+                    format_timestamp(timestamps.last_visited, "never")
+                ));
+                for line in lines {
+                    self.players[player].send_system_message(&line);
                 }
             }
+            // Settings are stored on the first plot of merged plots
+            "set" => self.handle_plot_set_command(player, self.x, self.z, args)?,
+            "merge" => self.handle_plot_merge_command(player, args)?,
+            "unmerge" => self.handle_plot_unmerge_command(player, args)?,
             "auto" | "a" => {
                 let mut start = (0, 0);
                 for _ in 0..MAX {
-                    if database::is_claimed(start.0, start.1).unwrap() {
+                    if database::is_claimed(start.0, start.1)? {
                         start = Plot::get_next_plot(start.0, start.1);
                     } else {
-                        self.claim_plot(start.0, start.1, player);
+                        self.claim_plot(start.0, start.1, player)?;
                         break;
                     }
                 }
             }
             "middle" => {
-                let center = Plot::get_center(plot_x, plot_z);
+                let center = PLOT_GEOMETRY.region_center(&self.region);
                 self.players[player].teleport(center.0, 64.0, center.1);
             }
+            "home" | "h" => {
+                let uuid = format!("{:032x}", self.players[player].uuid);
+                let plots = database::get_owned_plots(&uuid)?;
+                if plots.is_empty() {
+                    self.players[player].send_error_message("You don't own any plots!");
+                    return Ok(());
+                }
+                if let Some(plot) = self.pick_plot(player, &plots, args.first()) {
+                    self.teleport_to_plot(player, plot.0, plot.1)?;
+                }
+            }
+            "visit" | "v" => {
+                if args.is_empty() {
+                    self.players[player].send_error_message("/p visit <player> [n]");
+                    return Ok(());
+                }
+                let uuid = if let Some(uuid) = database::get_cached_uuid(args[0])? {
+                    uuid
+                } else {
+                    self.players[player].send_error_message("Player not found!");
+                    return Ok(());
+                };
+                let plots = database::get_owned_plots(&uuid)?;
+                if plots.is_empty() {
+                    self.players[player]
+                        .send_error_message(&format!("{} doesn't own any plots!", args[0]));
+                    return Ok(());
+                }
+                if let Some(plot) = self.pick_plot(player, &plots, args.get(1)) {
+                    self.teleport_to_plot(player, plot.0, plot.1)?;
+                }
+            }
+            "list" | "l" => {
+                let (uuid, name) = if let Some(name) = args.first() {
+                    if let Some(uuid) = database::get_cached_uuid(name)? {
+                        let name = database::get_cached_username(uuid.clone())?
+                            .unwrap_or_else(|| name.to_string());
+                        (uuid, name)
+                    } else {
+                        self.players[player].send_error_message("Player not found!");
+                        return Ok(());
+                    }
+                } else {
+                    (
+                        format!("{:032x}", self.players[player].uuid),
+                        self.players[player].username.clone(),
+                    )
+                };
+                let plots = database::get_owned_plots(&uuid)?;
+                if plots.is_empty() {
+                    self.players[player]
+                        .send_system_message(&format!("{} doesn't own any plots.", name));
+                    return Ok(());
+                }
+                self.players[player].send_system_message(&format!("Plots owned by {}:", name));
+                for (i, (x, z)) in plots.into_iter().enumerate() {
+                    self.players[player].send_system_message(&format!("{}: {},{}", i + 1, x, z));
+                }
+            }
+            "sethome" => {
+                let uuid = format!("{:032x}", self.players[player].uuid);
+                if database::get_plot_owner(plot_x, plot_z)? != Some(uuid) {
+                    self.players[player]
+                        .send_error_message("You can only set the home of plots you own!");
+                    return Ok(());
+                }
+                let (x, y, z) = (
+                    self.players[player].x,
+                    self.players[player].y,
+                    self.players[player].z,
+                );
+                database::set_plot_home(plot_x, plot_z, x, y, z)?;
+                self.players[player].send_system_message(&format!(
+                    "The home of plot {},{} has been set.",
+                    plot_x, plot_z
+                ));
+            }
             _ => self.players[player].send_error_message("Invalid argument for /plot"),
         }
+        Ok(())
+    }
+
+    /// Handles `/rprofile <start [seconds]|stop|report|heatmap>`
+    fn handle_profile_command(&mut self, player: usize, args: &[&str]) {
+        match args {
+            ["start", rest @ ..] => {
+                let duration = match rest.first().map(|secs| secs.parse::<u64>()) {
+                    None => DEFAULT_PROFILE_DURATION,
+                    Some(Ok(secs)) if secs > 0 => Duration::from_secs(secs),
+                    Some(_) => {
+                        self.players[player].send_error_message("Unable to parse seconds!");
+                        return;
+                    }
+                };
+                if matches!(&self.profiler, Some(profiler) if profiler.is_running()) {
+                    self.players[player].send_error_message("The profiler is already running!");
+                    return;
+                }
+                self.hide_heatmaps();
+                self.profiler = Some(Profiler::new(duration));
+                self.players[player].send_system_message(&format!(
+                    "Profiling block updates for {} seconds.",
+                    duration.as_secs()
+                ));
+            }
+            ["stop"] => match &mut self.profiler {
+                Some(profiler) if profiler.is_running() => {
+                    profiler.stop();
+                    let report = profiler.report();
+                    for line in &report {
+                        self.players[player].send_system_message(line);
+                    }
+                }
+                _ => self.players[player].send_error_message("The profiler isn't running!"),
+            },
+            ["report"] => match &self.profiler {
+                Some(profiler) => {
+                    if profiler.is_running() {
+                        self.players[player].send_system_message(&format!(
+                            "The profiler is still running for {} seconds.",
+                            profiler.time_left().as_secs()
+                        ));
+                    }
+                    for line in &profiler.report() {
+                        self.players[player].send_system_message(line);
+                    }
+                }
+                None => self.players[player].send_error_message("Nothing has been profiled yet!"),
+            },
+            ["heatmap"] => {
+                let uuid = self.players[player].uuid;
+                match &self.profiler {
+                    Some(profiler) if profiler.heatmap_viewers.contains(&uuid) => {
+                        self.hide_heatmap(player);
+                        self.players[player].send_system_message("The heatmap is now hidden.");
+                    }
+                    Some(_) => {
+                        self.show_heatmap(player);
+                        self.players[player].send_system_message(
+                            "Showing the heatmap. Blocks go from blue to red as they get more active. Run this again to hide it.",
+                        );
+                    }
+                    None => {
+                        self.players[player].send_error_message("Nothing has been profiled yet!")
+                    }
+                }
+            }
+            _ => self.players[player]
+                .send_error_message("/rprofile <start [seconds]|stop|report|heatmap>"),
+        }
+    }
+
+    /// Handles `/p set <flag> <value>`, which changes the settings of the plot the player is in
+    fn handle_plot_set_command(
+        &mut self,
+        player: usize,
+        plot_x: i32,
+        plot_z: i32,
+        args: Vec<&str>,
+    ) -> database::Result<()> {
+        let uuid = format!("{:032x}", self.players[player].uuid);
+        if database::get_plot_owner(plot_x, plot_z)? != Some(uuid) {
+            self.players[player]
+                .send_error_message("You can only change the settings of plots you own!");
+            return Ok(());
+        }
+        if args.is_empty() {
+            self.players[player].send_error_message(
+                "/p set <keep-loaded|rtps|auto-pause|public-build|wire-algorithm|name|description|greeting> <value>",
+            );
+            return Ok(());
+        }
+        let flag = args[0];
+        let value = args[1..].join(" ");
+        let parse_bool = |value: &str| match value {
+            "true" | "on" | "yes" => Some(true),
+            "false" | "off" | "no" => Some(false),
+            _ => None,
+        };
+        let text = if value.is_empty() {
+            None
+        } else {
+            Some(value.clone())
+        };
+        match flag {
+            "keep-loaded" | "auto-pause" | "public-build" => {
+                let enabled = if let Some(enabled) = parse_bool(&value) {
+                    enabled
+                } else {
+                    self.players[player].send_error_message("The value must be true or false!");
+                    return Ok(());
+                };
+                match flag {
+                    "keep-loaded" => self.settings.keep_loaded = enabled,
+                    "auto-pause" => self.settings.auto_pause = enabled,
+                    _ => self.settings.public_build = enabled,
+                }
+            }
+            "rtps" => {
+                if value == "none" || value.is_empty() {
+                    self.settings.default_rtps = None;
+                } else if let Ok(tps) = value.parse::<u32>() {
+                    if tps > MAX_RTPS {
+                        self.players[player]
+                            .send_error_message("The rtps cannot go higher than 35000!");
+                        return Ok(());
+                    }
+                    if tps > HIGH_RTPS && !self.check_permission(player, "mchprs.command.rtps.high")
+                    {
+                        return Ok(());
+                    }
+                    self.settings.default_rtps = Some(tps);
+                    self.set_tps(tps);
+                } else {
+                    self.players[player].send_error_message("Unable to parse rtps!");
+                    return Ok(());
+                }
+            }
+            "wire-algorithm" => {
+                if let Some(algorithm) = WireAlgorithm::from_name(&value) {
+                    self.settings.wire_algorithm = algorithm;
+                } else {
+                    self.players[player].send_error_message(
+                        "The value must be vanilla, turbo or alternate-current!",
+                    );
+                    return Ok(());
+                }
+            }
+            "name" => self.settings.name = text,
+            "description" => self.settings.description = text,
+            "greeting" => self.settings.greeting = text,
+            _ => {
+                self.players[player].send_error_message(&format!("Unknown plot setting: {}", flag));
+                return Ok(());
+            }
+        }
+        database::set_plot_settings(plot_x, plot_z, &self.settings)?;
+        self.players[player].send_system_message(&format!("Plot setting {} was updated.", flag));
+        Ok(())
+    }
+
+    /// Handles `/p merge <direction>`, which merges the plot the player is in with the
+    /// plots next to it in that direction. All of the plots have to be owned by the player.
+    fn handle_plot_merge_command(
+        &mut self,
+        player: usize,
+        args: Vec<&str>,
+    ) -> database::Result<()> {
+        let region = self.region;
+        let side = match args.first().map(|dir| dir.to_lowercase()).as_deref() {
+            Some("north") | Some("n") => PlotRegion {
+                min_z: region.min_z - 1,
+                max_z: region.min_z - 1,
+                ..region
+            },
+            Some("south") | Some("s") => PlotRegion {
+                min_z: region.max_z + 1,
+                max_z: region.max_z + 1,
+                ..region
+            },
+            Some("east") | Some("e") => PlotRegion {
+                min_x: region.max_x + 1,
+                max_x: region.max_x + 1,
+                ..region
+            },
+            Some("west") | Some("w") => PlotRegion {
+                min_x: region.min_x - 1,
+                max_x: region.min_x - 1,
+                ..region
+            },
+            _ => {
+                self.players[player].send_error_message("/p merge <north|east|south|west>");
+                return Ok(());
+            }
+        };
+        // Grow the region until it fully contains every merged region it overlaps
+        let mut merged = region.union(&side);
+        loop {
+            let mut grown = merged;
+            for (x, z) in merged.plots() {
+                if let Some(other) = database::get_merge_region(x, z)? {
+                    grown = grown.union(&other);
+                }
+            }
+            if grown == merged {
+                break;
+            }
+            merged = grown;
+        }
+        let uuid = format!("{:032x}", self.players[player].uuid);
+        for (x, z) in merged.plots() {
+            if database::get_plot_owner(x, z)?.as_ref() != Some(&uuid) {
+                self.players[player].send_error_message(&format!(
+                    "You must own all of the plots from {} to merge them!",
+                    merged
+                ));
+                return Ok(());
+            }
+        }
+        database::merge_plots(merged)?;
+        self.players[player].send_system_message(&format!("Merged plots {}.", merged));
+        self.message_sender
+            .send(Message::MergePlots(merged))
+            .unwrap();
+        Ok(())
+    }
+
+    /// Handles `/p unmerge`, which splits the merged plots the player is in back up. If
+    /// anything was built on the roads between the plots, it has to be confirmed with
+    /// `/p unmerge confirm`.
+    fn handle_plot_unmerge_command(
+        &mut self,
+        player: usize,
+        args: Vec<&str>,
+    ) -> database::Result<()> {
+        if self.region.is_single() {
+            self.players[player].send_error_message("This plot isn't merged!");
+            return Ok(());
+        }
+        let uuid = format!("{:032x}", self.players[player].uuid);
+        for (x, z) in self.region.plots() {
+            if database::get_plot_owner(x, z)?.as_ref() != Some(&uuid) {
+                self.players[player].send_error_message(&format!(
+                    "You must own all of the plots from {} to unmerge them!",
+                    self.region
+                ));
+                return Ok(());
+            }
+        }
+        if args.first() != Some(&"confirm") {
+            let builds = self.merged_road_builds();
+            if builds > 0 {
+                self.players[player].send_error_message(&format!(
+                    "{} blocks are built on the roads between the plots. Unmerging puts the roads back over them. Run /p unmerge confirm to unmerge anyway.",
+                    builds
+                ));
+                return Ok(());
+            }
+        }
+        database::unmerge_plots(self.region)?;
+        self.restore_merged_roads();
+        self.players[player].send_system_message(&format!("Unmerged plots {}.", self.region));
+        self.message_sender
+            .send(Message::UnmergePlots(self.region))
+            .unwrap();
+        Ok(())
+    }
+
+    /// Handles `/permission`, which manages permission groups and the permissions of players
+    fn handle_permission_command(
+        &mut self,
+        player: usize,
+        args: Vec<&str>,
+    ) -> database::Result<()> {
+        const USAGE: &str = "/perm <groups|group <group> ...|user <player> ...>";
+        let parse_value = |value: Option<&&str>| match value.copied() {
+            Some("true") | Some("allow") => Some(true),
+            Some("false") | Some("deny") => Some(false),
+            _ => None,
+        };
+        match args.as_slice() {
+            ["groups"] => {
+                let groups = database::get_groups()?;
+                self.players[player].send_system_message(&format!("Groups: {}", groups.join(", ")));
+            }
+            ["group", group, action, rest @ ..] => match (*action, rest.first()) {
+                ("info", None) => {
+                    if let Some(nodes) = database::get_group_permissions(group)? {
+                        self.players[player]
+                            .send_system_message(&format!("Permissions of group {}:", group));
+                        for (node, value) in nodes {
+                            self.players[player]
+                                .send_system_message(&format!("{}: {}", node, value));
+                        }
+                    } else {
+                        self.players[player].send_error_message("That group doesn't exist!");
+                    }
+                }
+                ("create", None) => {
+                    if !permissions::is_valid_node(group) || group.contains(&['.', '*'][..]) {
+                        self.players[player].send_error_message("Invalid group name!");
+                    } else if database::create_group(group)? {
+                        self.players[player]
+                            .send_system_message(&format!("Created group {}.", group));
+                    } else {
+                        self.players[player].send_error_message("That group already exists!");
+                    }
+                }
+                ("delete", None) => {
+                    if *group == permissions::DEFAULT_GROUP {
+                        self.players[player]
+                            .send_error_message("The default group cannot be deleted!");
+                    } else if database::delete_group(group)? {
+                        self.players[player]
+                            .send_system_message(&format!("Deleted group {}.", group));
+                    } else {
+                        self.players[player].send_error_message("That group doesn't exist!");
+                    }
+                }
+                ("set", Some(node)) => {
+                    let value = if let Some(value) = parse_value(rest.get(1)) {
+                        value
+                    } else {
+                        self.players[player]
+                            .send_error_message("/perm group <group> set <node> <true|false>");
+                        return Ok(());
+                    };
+                    if !permissions::is_valid_node(node) {
+                        self.players[player].send_error_message("Invalid permission node!");
+                    } else if database::set_group_permission(group, node, value)? {
+                        self.players[player].send_system_message(&format!(
+                            "Set {} to {} for group {}.",
+                            node, value, group
+                        ));
+                    } else {
+                        self.players[player].send_error_message("That group doesn't exist!");
+                    }
+                }
+                ("unset", Some(node)) => {
+                    if database::unset_group_permission(group, node)? {
+                        self.players[player].send_system_message(&format!(
+                            "Removed {} from group {}.",
+                            node, group
+                        ));
+                    } else {
+                        self.players[player]
+                            .send_error_message("That group doesn't have that permission!");
+                    }
+                }
+                _ => self.players[player].send_error_message(
+                    "/perm group <group> <info|create|delete|set|unset> [node] [value]",
+                ),
+            },
+            ["user", name, action, rest @ ..] => {
+                let uuid = if let Some(uuid) = database::get_cached_uuid(name)? {
+                    uuid
+                } else {
+                    self.players[player].send_error_message("Player not found!");
+                    return Ok(());
+                };
+                match (*action, rest.first()) {
+                    ("info", None) => {
+                        let groups = database::get_user_groups(&uuid)?;
+                        self.players[player].send_system_message(&format!(
+                            "Groups of {}: {}",
+                            name,
+                            groups.join(", ")
+                        ));
+                        for (node, value) in database::get_user_permissions(&uuid)? {
+                            self.players[player]
+                                .send_system_message(&format!("{}: {}", node, value));
+                        }
+                    }
+                    ("check", Some(node)) => {
+                        let has_permission = permissions::get_permissions(&uuid)?.has(node);
+                        self.players[player].send_system_message(&format!(
+                            "{} {} {}.",
+                            name,
+                            if has_permission { "has" } else { "does not have" },
+                            node
+                        ));
+                    }
+                    ("set", Some(node)) => {
+                        let value = if let Some(value) = parse_value(rest.get(1)) {
+                            value
+                        } else {
+                            self.players[player]
+                                .send_error_message("/perm user <player> set <node> <true|false>");
+                            return Ok(());
+                        };
+                        if !permissions::is_valid_node(node) {
+                            self.players[player].send_error_message("Invalid permission node!");
+                            return Ok(());
+                        }
+                        database::set_user_permission(&uuid, node, value)?;
+                        self.players[player].send_system_message(&format!(
+                            "Set {} to {} for {}.",
+                            node, value, name
+                        ));
+                    }
+                    ("unset", Some(node)) => {
+                        if database::unset_user_permission(&uuid, node)? {
+                            self.players[player]
+                                .send_system_message(&format!("Removed {} from {}.", node, name));
+                        } else {
+                            self.players[player]
+                                .send_error_message("That player doesn't have that permission!");
+                        }
+                    }
+                    ("addgroup", Some(group)) => {
+                        if database::add_user_group(&uuid, group)? {
+                            self.players[player]
+                                .send_system_message(&format!("Added {} to {}.", name, group));
+                        } else {
+                            self.players[player].send_error_message("That group doesn't exist!");
+                        }
+                    }
+                    ("removegroup", Some(group)) => {
+                        if database::remove_user_group(&uuid, group)? {
+                            self.players[player]
+                                .send_system_message(&format!("Removed {} from {}.", name, group));
+                        } else {
+                            self.players[player]
+                                .send_error_message("That player isn't in that group!");
+                        }
+                    }
+                    _ => self.players[player].send_error_message(
+                        "/perm user <player> <info|check|set|unset|addgroup|removegroup> [node|group] [value]",
+                    ),
+                }
+            }
+            _ => self.players[player].send_error_message(USAGE),
+        }
+        Ok(())
+    }
+
+    /// Handles the commands that control who can join the server: `/whitelist`, `/ban`,
+    /// `/ban-ip`, `/banlist`, `/pardon`, `/pardon-ip`, `/op` and `/deop`
+    fn handle_access_command(
+        &mut self,
+        player: usize,
+        command: &str,
+        args: Vec<&str>,
+    ) -> database::Result<()> {
+        // Players that haven't joined yet are looked up by their offline uuid
+        let lookup_uuid = |name: &str| -> database::Result<String> {
+            Ok(database::get_cached_uuid(name)?
+                .unwrap_or_else(|| format!("{:032x}", Player::generate_offline_uuid(name))))
+        };
+        let banned_by = self.players[player].username.clone();
+        match (command, args.as_slice()) {
+            ("/whitelist", ["add", name]) => {
+                if database::add_to_whitelist(&lookup_uuid(name)?, name)? {
+                    self.players[player]
+                        .send_system_message(&format!("Added {} to the whitelist.", name));
+                } else {
+                    self.players[player].send_error_message("That player is already whitelisted!");
+                }
+            }
+            ("/whitelist", ["remove", name]) => {
+                if database::remove_from_whitelist(&lookup_uuid(name)?)? {
+                    self.players[player]
+                        .send_system_message(&format!("Removed {} from the whitelist.", name));
+                } else {
+                    self.players[player].send_error_message("That player isn't whitelisted!");
+                }
+            }
+            ("/whitelist", ["list"]) => {
+                let names = database::get_whitelist()?;
+                self.players[player].send_system_message(&format!(
+                    "Whitelisted players ({}): {}",
+                    names.len(),
+                    names.join(", ")
+                ));
+            }
+            ("/whitelist", _) => {
+                self.players[player].send_error_message("/whitelist <add|remove|list> [player]")
+            }
+            ("/ban", [name, rest @ ..]) | ("/ban-ip", [name, rest @ ..]) => {
+                let duration = rest.first().and_then(|arg| parse_duration(arg));
+                if matches!(duration, Some(duration) if duration > MAX_BAN_DURATION) {
+                    self.players[player].send_error_message("Bans can last at most 100 years!");
+                    return Ok(());
+                }
+                let reason_args = if duration.is_some() { &rest[1..] } else { rest };
+                let reason = if reason_args.is_empty() {
+                    None
+                } else {
+                    Some(reason_args.join(" "))
+                };
+                if command == "/ban" {
+                    let uuid = lookup_uuid(name)?;
+                    database::ban_player(&uuid, name, reason.as_deref(), &banned_by, duration)?;
+                    if let Some(ban) = database::get_player_ban(&uuid)? {
+                        let message = ban_message("You have been banned from this server.", &ban);
+                        let uuid = u128::from_str_radix(&uuid, 16).unwrap_or_default();
+                        let _ = self.message_sender.send(Message::KickPlayer(uuid, message));
+                    }
+                    self.players[player].send_system_message(&format!("Banned {}.", name));
+                } else {
+                    let ip: IpAddr = if let Ok(ip) = name.parse() {
+                        ip
+                    } else if let Some(ip) = database::get_user_ip(&lookup_uuid(name)?)? {
+                        ip.parse().unwrap()
+                    } else {
+                        self.players[player].send_error_message("Unknown player or IP address!");
+                        return Ok(());
+                    };
+                    database::ban_ip(&ip.to_string(), reason.as_deref(), &banned_by, duration)?;
+                    if let Some(ban) = database::get_ip_ban(&ip.to_string())? {
+                        let message =
+                            ban_message("Your IP address has been banned from this server.", &ban);
+                        let _ = self.message_sender.send(Message::KickIp(ip, message));
+                    }
+                    self.players[player].send_system_message(&format!("Banned IP {}.", ip));
+                }
+            }
+            ("/ban", _) => {
+                self.players[player].send_error_message("/ban <player> [duration] [reason]")
+            }
+            ("/ban-ip", _) => {
+                self.players[player].send_error_message("/ban-ip <player|ip> [duration] [reason]")
+            }
+            ("/banlist", []) => {
+                let bans = database::get_bans()?;
+                if bans.is_empty() {
+                    self.players[player].send_system_message("There are no bans.");
+                }
+                for ban in bans {
+                    self.players[player].send_system_message(&format!(
+                        "{} was banned by {}{}{}",
+                        ban.target,
+                        ban.banned_by,
+                        ban.reason
+                            .map_or_else(String::new, |reason| format!(": {}", reason)),
+                        ban.expires_at
+                            .map_or_else(String::new, |expires_at| format!(
+                                " (until {})",
+                                format_timestamp(Some(expires_at), "never")
+                            ))
+                    ));
+                }
+            }
+            ("/banlist", _) => self.players[player].send_error_message("/banlist"),
+            ("/pardon", [name]) => {
+                if database::pardon_player(&lookup_uuid(name)?)? {
+                    self.players[player].send_system_message(&format!("Unbanned {}.", name));
+                } else {
+                    self.players[player].send_error_message("That player isn't banned!");
+                }
+            }
+            ("/pardon", _) => self.players[player].send_error_message("/pardon <player>"),
+            ("/pardon-ip", [ip]) => {
+                if database::pardon_ip(ip)? {
+                    self.players[player].send_system_message(&format!("Unbanned IP {}.", ip));
+                } else {
+                    self.players[player].send_error_message("That IP address isn't banned!");
+                }
+            }
+            ("/pardon-ip", _) => self.players[player].send_error_message("/pardon-ip <ip>"),
+            ("/op", []) => {
+                let names = database::get_ops()?;
+                self.players[player]
+                    .send_system_message(&format!("Operators: {}", names.join(", ")));
+            }
+            ("/op", [name]) => {
+                if database::add_op(&lookup_uuid(name)?, name)? {
+                    self.players[player]
+                        .send_system_message(&format!("Made {} an operator.", name));
+                } else {
+                    self.players[player].send_error_message("That player is already an operator!");
+                }
+            }
+            ("/deop", [name]) => {
+                if database::remove_op(&lookup_uuid(name)?)? {
+                    self.players[player]
+                        .send_system_message(&format!("{} is no longer an operator.", name));
+                } else {
+                    self.players[player].send_error_message("That player isn't an operator!");
+                }
+            }
+            _ => self.players[player].send_error_message(&format!("{} <player>", command)),
+        }
+        Ok(())
+    }
+
+    /// Picks the plot selected by the optional 1-based index argument of `/p home`
+    /// and `/p visit`. Sends an error to the player and returns `None` if the index is invalid.
+    fn pick_plot(
+        &mut self,
+        player: usize,
+        plots: &[(i32, i32)],
+        index_arg: Option<&&str>,
+    ) -> Option<(i32, i32)> {
+        let index = match index_arg.map(|arg| arg.parse::<usize>()) {
+            None => 1,
+            Some(Ok(index)) => index,
+            Some(Err(_)) => {
+                self.players[player].send_error_message("Unable to parse plot number!");
+                return None;
+            }
+        };
+        if index == 0 || index > plots.len() {
+            self.players[player].send_error_message(&format!(
+                "Plot number must be between 1 and {}!",
+                plots.len()
+            ));
+            return None;
+        }
+        Some(plots[index - 1])
+    }
+
+    /// Teleports the player to the home of a plot, or the center if it has no home set.
+    /// If the plot is not this plot, the player will be moved to it by the server thread.
+    fn teleport_to_plot(
+        &mut self,
+        player: usize,
+        plot_x: i32,
+        plot_z: i32,
+    ) -> database::Result<()> {
+        let (x, y, z) = database::get_plot_home(plot_x, plot_z)?.unwrap_or_else(|| {
+            let center = Plot::get_center(plot_x, plot_z);
+            (center.0, 64.0, center.1)
+        });
+        self.players[player].teleport(x, y, z);
+        Ok(())
+    }
+
+    /// Sends a summary of how the plot performed during the last second
+    fn send_rtps_stats(&mut self, player: usize) {
+        let stats = match self.stats.last.clone() {
+            Some(stats) => stats,
+            None => {
+                self.players[player]
+                    .send_error_message("The plot hasn't been running long enough yet.");
+                return;
+            }
+        };
+        let ms = |secs: f64| secs * 1000.0;
+        let mut lines = vec![
+            format!(
+                "Target rtps: {}, achieved rtps: {:.1}",
+                stats.target_rtps, stats.rtps
+            ),
+            match stats.tick_times {
+                Some(tick_times) => format!(
+                    "Tick time: {:.3} ms average, {:.3} ms median, {:.3} ms 99th percentile, {:.3} ms max",
+                    stats.mspt,
+                    ms(tick_times[0]),
+                    ms(tick_times[2]),
+                    ms(tick_times[3])
+                ),
+                None => format!("Tick time: {:.3} ms average", stats.mspt),
+            },
+            format!("Pending ticks: {}", stats.pending_ticks),
+        ];
+        if stats.skipped_ticks > 0 {
+            lines.push(format!(
+                "Skipped {} ticks because the plot couldn't keep up.",
+                stats.skipped_ticks
+            ));
+        }
+        if self.tps_mode == TpsMode::Unlimited {
+            lines[0] = format!("Target rtps: unlimited, achieved rtps: {:.1}", stats.rtps);
+        } else if stats.target_rtps > 0 && stats.rtps < stats.target_rtps as f64 * 0.95 {
+            lines.push("The plot is not keeping up with its target rtps.".to_owned());
+        }
+        for line in lines {
+            self.players[player].send_system_message(&line);
+        }
+    }
+
+    /// Handles a server command sent to this plot from the console or RCON. The output is
+    /// sent to `reply`.
+    pub(super) fn handle_console_command(&mut self, line: &str, reply: Sender<String>) {
+        let send = |message: String| {
+            let _ = reply.send(message);
+        };
+        let args: Vec<&str> = line.split_whitespace().collect();
+        match args.as_slice() {
+            ["rtps"] => send(format!(
+                "Plot {} is running at {} rtps ({:?})",
+                self.region, self.tps, self.tps_mode
+            )),
+            ["rtps", "unlimited"] => {
+                self.set_tps_mode(TpsMode::Unlimited);
+                send(format!(
+                    "Plot {} will now tick as fast as it can",
+                    self.region
+                ));
+            }
+            ["rtps", "adaptive"] => {
+                self.set_tps_mode(TpsMode::Adaptive { max: MAX_RTPS });
+                send(format!(
+                    "The rtps of plot {} will now adapt to the circuit",
+                    self.region
+                ));
+            }
+            ["rtps", tps] => match tps.parse::<u32>() {
+                Ok(tps) if tps <= MAX_RTPS => {
+                    self.set_tps(tps);
+                    send(format!("Set the rtps of plot {} to {}", self.region, tps));
+                }
+                Ok(_) => send("The rtps cannot go higher than 35000!".to_owned()),
+                Err(_) => send("Unable to parse rtps!".to_owned()),
+            },
+            ["radv", ticks] | ["radvance", ticks] => match ticks.parse::<u32>() {
+                Ok(ticks) => {
+                    let start_time = Instant::now();
+                    for _ in 0..ticks {
+                        self.tick();
+                    }
+                    send(format!(
+                        "Plot {} has been advanced by {} ticks ({:?})",
+                        self.region,
+                        ticks,
+                        start_time.elapsed()
+                    ));
+                }
+                Err(_) => send("Unable to parse ticks!".to_owned()),
+            },
+            ["save"] => {
+                self.save();
+                send(format!("Saved plot {}", self.region));
+            }
+            ["info"] => {
+                let owner = match database::get_plot_owner(self.x, self.z) {
+                    Ok(Some(owner)) => database::get_cached_username(owner)
+                        .ok()
+                        .flatten()
+                        .unwrap_or_else(|| "Unknown".to_owned()),
+                    Ok(None) => "Nobody".to_owned(),
+                    Err(err) => {
+                        error!("Error getting the owner of plot {}: {}", self.region, err);
+                        "Unknown".to_owned()
+                    }
+                };
+                let players: Vec<&str> = self.players.iter().map(|p| p.username.as_str()).collect();
+                send(format!("Plot {} owned by {}", self.region, owner));
+                send(format!("  rtps: {}", self.tps));
+                send(format!("  Players: {}", players.join(", ")));
+            }
+            _ => send(
+                "Unknown plot command. Plots support rtps, radvance, save and info.".to_owned(),
+            ),
+        }
     }
 
     // Returns true if packets should stop being handled
@@ -67,6 +1014,12 @@ impl Plot {
             command,
             args.join(" ")
         );
+        if let Some(node) = command_permission(command) {
+            if !self.check_permission(player, node) {
+                return false;
+            }
+        }
+
         // Handle worldedit commands
         if command.starts_with("//")
             && worldedit::execute_command(self, player, command.trim_start_matches("//"), &mut args)
@@ -85,28 +1038,67 @@ impl Plot {
             }
             "/rtps" => {
                 if args.is_empty() {
-                    self.players[player]
-                        .send_system_message(&format!("The rtps is currently set to {}", self.tps));
+                    let message = match self.tps_mode {
+                        TpsMode::Fixed => format!("The rtps is currently set to {}", self.tps),
+                        TpsMode::Unlimited => "The rtps is currently unlimited".to_owned(),
+                        TpsMode::Adaptive { .. } => {
+                            format!("The rtps is adaptive and currently {}", self.tps)
+                        }
+                    };
+                    self.players[player].send_system_message(&message);
                     return false;
                 }
+                match args[0] {
+                    "stats" => {
+                        self.send_rtps_stats(player);
+                        return false;
+                    }
+                    "display" => {
+                        self.toggle_performance_bar(player);
+                        return false;
+                    }
+                    "unlimited" => {
+                        if self.check_permission(player, "mchprs.command.rtps.high") {
+                            self.set_tps_mode(TpsMode::Unlimited);
+                            self.players[player]
+                                .send_system_message("The plot will now tick as fast as it can.");
+                        }
+                        return false;
+                    }
+                    "adaptive" => {
+                        // Players who can't set a high rtps can't get one from adaptive either
+                        let max = if permissions::has_permission(
+                            self.players[player].uuid,
+                            "mchprs.command.rtps.high",
+                        ) {
+                            MAX_RTPS
+                        } else {
+                            HIGH_RTPS
+                        };
+                        self.set_tps_mode(TpsMode::Adaptive { max });
+                        self.players[player].send_system_message(&format!(
+                            "The rtps will now adapt to the circuit, up to {}.",
+                            max
+                        ));
+                        return false;
+                    }
+                    _ => {}
+                }
                 let tps = if let Ok(tps) = args[0].parse::<u32>() {
                     tps
                 } else {
                     self.players[player].send_error_message("Unable to parse rtps!");
                     return false;
                 };
-                if tps > 35000 {
+                if tps > MAX_RTPS {
                     self.players[player]
                         .send_error_message("The rtps cannot go higher than 35000!");
                     return false;
                 }
-                if tps > 10 {
-                    self.sleep_time = Duration::from_micros(1_000_000 / tps as u64);
-                } else {
-                    self.sleep_time = Duration::from_millis(2);
+                if tps > HIGH_RTPS && !self.check_permission(player, "mchprs.command.rtps.high") {
+                    return false;
                 }
-                self.lag_time = Duration::from_millis(0);
-                self.tps = tps;
+                self.set_tps(tps);
                 self.players[player].send_system_message("The rtps was successfully set.");
             }
             "/radv" | "/radvance" => {
@@ -131,6 +1123,32 @@ impl Plot {
                     start_time.elapsed()
                 ));
             }
+            "/rwarp" => {
+                let mut max_ticks = DEFAULT_WARP_TICKS;
+                let mut final_state_only = false;
+                for arg in &args {
+                    if *arg == "-s" {
+                        final_state_only = true;
+                    } else if let Ok(ticks) = arg.parse::<u32>() {
+                        max_ticks = ticks;
+                    } else {
+                        self.players[player].send_error_message("Unable to parse ticks!");
+                        return false;
+                    }
+                }
+                if self.warp.is_some() {
+                    self.players[player].send_error_message("A warp is already running!");
+                    return false;
+                }
+                self.warp = Some(Warp {
+                    player: self.players[player].uuid,
+                    ticks: 0,
+                    max_ticks,
+                    final_state_only,
+                    start_time: Instant::now(),
+                });
+            }
+            "/rprofile" => self.handle_profile_command(player, &args),
             "/teleport" | "/tp" => {
                 if args.len() == 3 {
                     let x;
@@ -175,7 +1193,30 @@ impl Plot {
                     return false;
                 }
                 let command = args.remove(0);
-                self.handle_plot_command(player, command, args);
+                if let Err(err) = self.handle_plot_command(player, command, args) {
+                    error!("Database error while handling /plot {}: {}", command, err);
+                    self.players[player]
+                        .send_error_message("There was an error accessing the plot database.");
+                }
+            }
+            "/permission" | "/perm" => {
+                if let Err(err) = self.handle_permission_command(player, args) {
+                    error!("Database error while handling {}: {}", command, err);
+                    self.players[player]
+                        .send_error_message("There was an error accessing the plot database.");
+                }
+                permissions::invalidate_cache();
+            }
+            "/whitelist" | "/ban" | "/ban-ip" | "/banlist" | "/pardon" | "/pardon-ip" | "/op"
+            | "/deop" => {
+                if let Err(err) = self.handle_access_command(player, command, args) {
+                    error!("Database error while handling {}: {}", command, err);
+                    self.players[player]
+                        .send_error_message("There was an error accessing the plot database.");
+                }
+                if command == "/op" || command == "/deop" {
+                    permissions::invalidate_cache();
+                }
             }
             "/speed" => {
                 if args.len() != 1 {
@@ -242,329 +1283,1297 @@ lazy_static! {
             // 0: Root Node
             Node {
                 flags: CommandFlags::ROOT.bits() as i8,
-                children: vec![1, 4, 5, 6, 11, 12, 14, 16, 18, 19, 20, 21, 22, 23, 24, 26, 29, 31, 32, 34, 36],
+                children: vec![1, 4, 5, 6, 11, 12, 14, 16, 18, 19, 20, 21, 22, 23, 24, 26, 29, 31, 32, 34, 36, 65, 70, 71, 73, 74, 75, 76, 77, 78, 79, 84, 87, 93, 97, 100, 103, 106, 108, 110, 111, 113, 115, 116, 118, 122, 126, 129, 132, 136, 141, 152],
                 redirect_node: None,
                 name: None,
                 parser: None,
             },
-            // 1: /teleport
+            // 1: /teleport
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![2, 3],
+                redirect_node: None,
+                name: Some("teleport"),
+                parser: None,
+            },
+            // 2: /teleport [x, y, z]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("x, y, z"),
+                parser: Some(Parser::Vec3),
+            },
+            // 3: /teleport [player]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("player"),
+                parser: Some(Parser::Entity(3)), // Only allow one player
+            },
+            // 4: /tp
+            Node {
+                flags: (CommandFlags::REDIRECT | CommandFlags::LITERAL).bits() as i8,
+                children: vec![],
+                redirect_node: Some(1),
+                name: Some("tp"),
+                parser: None,
+            },
+            // 5: /stop
+            Node {
+                flags: (CommandFlags::EXECUTABLE | CommandFlags::LITERAL).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("stop"),
+                parser: None,
+            },
+            // 6: /plot
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![7, 8, 9, 10, 38, 39, 40, 41, 43, 46, 48, 49, 59, 64],
+                redirect_node: None,
+                name: Some("plot"),
+                parser: None,
+            },
+            // 7: /plot info
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("info"),
+                parser: None,
+            },
+            // 8: /plot i
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                children: vec![],
+                redirect_node: Some(7),
+                name: Some("i"),
+                parser: None,
+            },
+            // 9: /plot claim
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("claim"),
+                parser: None,
+            },
+            // 10: /plot c
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                children: vec![],
+                redirect_node: Some(9),
+                name: Some("c"),
+                parser: None,
+            },
+            // 11: /p
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                children: vec![],
+                redirect_node: Some(6),
+                name: Some("p"),
+                parser: None,
+            },
+            // 12: /rtps
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![13, 80, 81, 82, 83],
+                redirect_node: None,
+                name: Some("rtps"),
+                parser: None,
+            },
+            // 13: /rtps [rtps]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("rtps"),
+                parser: Some(Parser::Integer(0, 35000)),
+            },
+            // 14: //pos1
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![15],
+                redirect_node: None,
+                name: Some("/pos1"),
+                parser: None,
+            },
+            // 15: //pos1 [pos]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("pos"),
+                parser: Some(Parser::BlockPos),
+            },
+            // 16: //pos2
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![17],
+                redirect_node: None,
+                name: Some("/pos2"),
+                parser: None,
+            },
+            // 17: //pos2 [pos]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("pos"),
+                parser: Some(Parser::BlockPos),
+            },
+            // 18: /1
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                children: vec![],
+                redirect_node: Some(14),
+                name: Some("/1"),
+                parser: None,
+            },
+            // 19: /2
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                children: vec![],
+                redirect_node: Some(16),
+                name: Some("/2"),
+                parser: None,
+            },
+            // 20: //copy
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![21],
+                redirect_node: None,
+                name: Some("/copy"),
+                parser: None,
+            },
+            // 21: //c
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                children: vec![],
+                redirect_node: Some(20),
+                name: Some("/c"),
+                parser: None,
+            },
+            // 22: //paste
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![23],
+                redirect_node: None,
+                name: Some("/paste"),
+                parser: None,
+            },
+            // 23: //p
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                children: vec![],
+                redirect_node: Some(20),
+                name: Some("/p"),
+                parser: None,
+            },
+            // 24: //set
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![25],
+                redirect_node: None,
+                name: Some("/set"),
+                parser: None,
+            },
+            // 25: //set [block]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("block"),
+                parser: Some(Parser::BlockState),
+            },
+            // 26: //replace
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![27],
+                redirect_node: None,
+                name: Some("/replace"),
+                parser: None,
+            },
+            // 27: //replace [oldblock]
+            Node {
+                flags: (CommandFlags::ARGUMENT).bits() as i8,
+                children: vec![28],
+                redirect_node: None,
+                name: Some("oldblock"),
+                parser: Some(Parser::BlockState),
+            },
+            // 28: //replace [oldblock] [newblock]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("newblock"),
+                parser: Some(Parser::BlockState),
+            },
+            // 29: /radvance
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![30],
+                redirect_node: None,
+                name: Some("radvance"),
+                parser: None,
+            },
+            // 30: /radvance [rticks]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("rticks"),
+                parser: Some(Parser::Integer(0, 35000)),
+            },
+            // 31: /radv
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                children: vec![],
+                redirect_node: Some(29),
+                name: Some("radv"),
+                parser: None,
+            },
+            // 32: /speed
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![33],
+                redirect_node: None,
+                name: Some("speed"),
+                parser: None,
+            },
+            // 33: /speed [speed]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("speed"),
+                parser: Some(Parser::Float(0.0, 10.0)),
+            },
+            // 34: //stack
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![35],
+                redirect_node: None,
+                name: Some("/stack"),
+                parser: None,
+            },
+            // 35: //stack [amount]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("amount"),
+                parser: Some(Parser::Integer(0, 256)),
+            },
+            // 36: //undo
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("/undo"),
+                parser: None,
+            },
+            // 37: //sel
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("/sel"),
+                parser: None,
+            },
+            // 38: /p auto
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("auto"),
+                parser: None,
+            },
+            // 39: /p a
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                children: vec![],
+                redirect_node: Some(9),
+                name: Some("a"),
+                parser: None,
+            },
+            // 40: /p auto
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("middle"),
+                parser: None,
+            },
+            // 41: /p home
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![42],
+                redirect_node: None,
+                name: Some("home"),
+                parser: None,
+            },
+            // 42: /p home [n]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("n"),
+                parser: Some(Parser::Integer(1, i32::MAX)),
+            },
+            // 43: /p visit
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![44],
+                redirect_node: None,
+                name: Some("visit"),
+                parser: None,
+            },
+            // 44: /p visit <player>
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![45],
+                redirect_node: None,
+                name: Some("player"),
+                parser: Some(Parser::Entity(3)), // Only allow one player
+            },
+            // 45: /p visit <player> [n]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("n"),
+                parser: Some(Parser::Integer(1, i32::MAX)),
+            },
+            // 46: /p list
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![47],
+                redirect_node: None,
+                name: Some("list"),
+                parser: None,
+            },
+            // 47: /p list [player]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("player"),
+                parser: Some(Parser::Entity(3)), // Only allow one player
+            },
+            // 48: /p sethome
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("sethome"),
+                parser: None,
+            },
+            // 49: /p set
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![50, 51, 52, 53, 54, 55, 56],
+                redirect_node: None,
+                name: Some("set"),
+                parser: None,
+            },
+            // 50: /p set keep-loaded
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![57],
+                redirect_node: None,
+                name: Some("keep-loaded"),
+                parser: None,
+            },
+            // 51: /p set auto-pause
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![57],
+                redirect_node: None,
+                name: Some("auto-pause"),
+                parser: None,
+            },
+            // 52: /p set public-build
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![57],
+                redirect_node: None,
+                name: Some("public-build"),
+                parser: None,
+            },
+            // 53: /p set rtps
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![13],
+                redirect_node: None,
+                name: Some("rtps"),
+                parser: None,
+            },
+            // 54: /p set name
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![58],
+                redirect_node: None,
+                name: Some("name"),
+                parser: None,
+            },
+            // 55: /p set description
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![58],
+                redirect_node: None,
+                name: Some("description"),
+                parser: None,
+            },
+            // 56: /p set greeting
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![58],
+                redirect_node: None,
+                name: Some("greeting"),
+                parser: None,
+            },
+            // 57: /p set <flag> [value]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("value"),
+                parser: Some(Parser::Bool),
+            },
+            // 58: /p set <text setting> [value]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("value"),
+                parser: Some(Parser::GreedyString),
+            },
+            // 59: /p merge
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![60, 61, 62, 63],
+                redirect_node: None,
+                name: Some("merge"),
+                parser: None,
+            },
+            // 60: /p merge north
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("north"),
+                parser: None,
+            },
+            // 61: /p merge east
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("east"),
+                parser: None,
+            },
+            // 62: /p merge south
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("south"),
+                parser: None,
+            },
+            // 63: /p merge west
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("west"),
+                parser: None,
+            },
+            // 64: /p unmerge
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![161],
+                redirect_node: None,
+                name: Some("unmerge"),
+                parser: None,
+            },
+            // 65: /permission
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![66, 67, 68],
+                redirect_node: None,
+                name: Some("permission"),
+                parser: None,
+            },
+            // 66: /permission groups
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("groups"),
+                parser: None,
+            },
+            // 67: /permission group
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![69],
+                redirect_node: None,
+                name: Some("group"),
+                parser: None,
+            },
+            // 68: /permission user
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![69],
+                redirect_node: None,
+                name: Some("user"),
+                parser: None,
+            },
+            // 69: /permission <group|user> [arguments]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("arguments"),
+                parser: Some(Parser::GreedyString),
+            },
+            // 70: /perm
+            Node {
+                flags: (CommandFlags::REDIRECT | CommandFlags::LITERAL).bits() as i8,
+                children: vec![],
+                redirect_node: Some(65),
+                name: Some("perm"),
+                parser: None,
+            },
+            // 71: /whitelist
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![72],
+                redirect_node: None,
+                name: Some("whitelist"),
+                parser: None,
+            },
+            // 72: /<whitelist|ban|ban-ip|pardon|pardon-ip|op|deop> [arguments]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("arguments"),
+                parser: Some(Parser::GreedyString),
+            },
+            // 73: /ban
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![72],
+                redirect_node: None,
+                name: Some("ban"),
+                parser: None,
+            },
+            // 74: /ban-ip
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![72],
+                redirect_node: None,
+                name: Some("ban-ip"),
+                parser: None,
+            },
+            // 75: /banlist
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("banlist"),
+                parser: None,
+            },
+            // 76: /pardon
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![72],
+                redirect_node: None,
+                name: Some("pardon"),
+                parser: None,
+            },
+            // 77: /pardon-ip
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![72],
+                redirect_node: None,
+                name: Some("pardon-ip"),
+                parser: None,
+            },
+            // 78: /op
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![72],
+                redirect_node: None,
+                name: Some("op"),
+                parser: None,
+            },
+            // 79: /deop
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![72],
+                redirect_node: None,
+                name: Some("deop"),
+                parser: None,
+            },
+            // 80: /rtps stats
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("stats"),
+                parser: None,
+            },
+            // 81: /rtps display
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("display"),
+                parser: None,
+            },
+            // 82: /rtps unlimited
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("unlimited"),
+                parser: None,
+            },
+            // 83: /rtps adaptive
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("adaptive"),
+                parser: None,
+            },
+            // 84: /rwarp
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![85, 86],
+                redirect_node: None,
+                name: Some("rwarp"),
+                parser: None,
+            },
+            // 85: /rwarp [max_ticks]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![86],
+                redirect_node: None,
+                name: Some("max_ticks"),
+                parser: Some(Parser::Integer(0, i32::MAX)),
+            },
+            // 86: /rwarp [max_ticks] -s
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("-s"),
+                parser: None,
+            },
+            // 87: /rprofile
+            Node {
+                flags: CommandFlags::LITERAL.bits() as i8,
+                children: vec![88, 90, 91, 92],
+                redirect_node: None,
+                name: Some("rprofile"),
+                parser: None,
+            },
+            // 88: /rprofile start
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![89],
+                redirect_node: None,
+                name: Some("start"),
+                parser: None,
+            },
+            // 89: /rprofile start [seconds]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("seconds"),
+                parser: Some(Parser::Integer(1, i32::MAX)),
+            },
+            // 90: /rprofile stop
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("stop"),
+                parser: None,
+            },
+            // 91: /rprofile report
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("report"),
+                parser: None,
+            },
+            // 92: /rprofile heatmap
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("heatmap"),
+                parser: None,
+            },
+            // 93: //rstack
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![94],
+                redirect_node: None,
+                name: Some("/rstack"),
+                parser: None,
+            },
+            // 94: //rstack [count]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![95],
+                redirect_node: None,
+                name: Some("count"),
+                parser: Some(Parser::Integer(0, i32::MAX)),
+            },
+            // 95: //rstack [count] [spacing]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![96],
+                redirect_node: None,
+                name: Some("spacing"),
+                parser: Some(Parser::Integer(0, i32::MAX)),
+            },
+            // 96: //rstack [count] [spacing] [direction]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("direction"),
+                parser: Some(Parser::GreedyString),
+            },
+            // 97: //expand
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![98],
+                redirect_node: None,
+                name: Some("/expand"),
+                parser: None,
+            },
+            // 98: //expand [amount]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![99],
+                redirect_node: None,
+                name: Some("amount"),
+                parser: Some(Parser::Integer(0, i32::MAX)),
+            },
+            // 99: //expand [amount] [direction]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("direction"),
+                parser: Some(Parser::GreedyString),
+            },
+            // 100: //contract
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![101],
+                redirect_node: None,
+                name: Some("/contract"),
+                parser: None,
+            },
+            // 101: //contract [amount]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![102],
+                redirect_node: None,
+                name: Some("amount"),
+                parser: Some(Parser::Integer(0, i32::MAX)),
+            },
+            // 102: //contract [amount] [direction]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("direction"),
+                parser: Some(Parser::GreedyString),
+            },
+            // 103: //shift
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![104],
+                redirect_node: None,
+                name: Some("/shift"),
+                parser: None,
+            },
+            // 104: //shift [amount]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![105],
+                redirect_node: None,
+                name: Some("amount"),
+                parser: Some(Parser::Integer(0, i32::MAX)),
+            },
+            // 105: //shift [amount] [direction]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("direction"),
+                parser: Some(Parser::GreedyString),
+            },
+            // 106: //outset
+            Node {
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![107],
+                redirect_node: None,
+                name: Some("/outset"),
+                parser: None,
+            },
+            // 107: //outset [amount]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![],
+                redirect_node: None,
+                name: Some("amount"),
+                parser: Some(Parser::Integer(0, i32::MAX)),
+            },
+            // 108: //inset
             Node {
-                flags: CommandFlags::LITERAL.bits() as i8,
-                children: vec![2, 3],
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![109],
                 redirect_node: None,
-                name: Some("teleport"),
+                name: Some("/inset"),
                 parser: None,
             },
-            // 2: /teleport [x, y, z]
+            // 109: //inset [amount]
             Node {
                 flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
                 redirect_node: None,
-                name: Some("x, y, z"),
-                parser: Some(Parser::Vec3),
+                name: Some("amount"),
+                parser: Some(Parser::Integer(0, i32::MAX)),
             },
-            // 3: /teleport [player]
+            // 110: //size
             Node {
-                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
                 redirect_node: None,
-                name: Some("player"),
-                parser: Some(Parser::Entity(3)), // Only allow one player
+                name: Some("/size"),
+                parser: None,
             },
-            // 4: /tp
+            // 111: //walls
             Node {
-                flags: (CommandFlags::REDIRECT | CommandFlags::LITERAL).bits() as i8,
-                children: vec![],
-                redirect_node: Some(1),
-                name: Some("tp"),
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![112],
+                redirect_node: None,
+                name: Some("/walls"),
                 parser: None,
             },
-            // 5: /stop
+            // 112: //walls [pattern]
             Node {
-                flags: (CommandFlags::EXECUTABLE | CommandFlags::LITERAL).bits() as i8,
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
                 redirect_node: None,
-                name: Some("stop"),
-                parser: None,
+                name: Some("pattern"),
+                parser: Some(Parser::BlockState),
             },
-            // 6: /plot
+            // 113: //faces
             Node {
                 flags: (CommandFlags::LITERAL).bits() as i8,
-                children: vec![7, 8, 9, 10, 38, 39, 40],
+                children: vec![114],
                 redirect_node: None,
-                name: Some("plot"),
+                name: Some("/faces"),
                 parser: None,
             },
-            // 7: /plot info
+            // 114: //faces [pattern]
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
                 redirect_node: None,
-                name: Some("info"),
-                parser: None,
+                name: Some("pattern"),
+                parser: Some(Parser::BlockState),
             },
-            // 8: /plot i
+            // 115: //outline
             Node {
                 flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
                 children: vec![],
-                redirect_node: Some(7),
-                name: Some("i"),
+                redirect_node: Some(113),
+                name: Some("/outline"),
                 parser: None,
             },
-            // 9: /plot claim
+            // 116: //line
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
-                children: vec![],
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![117],
                 redirect_node: None,
-                name: Some("claim"),
+                name: Some("/line"),
                 parser: None,
             },
-            // 10: /plot c
+            // 117: //line [pattern]
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
-                redirect_node: Some(9),
-                name: Some("c"),
+                redirect_node: None,
+                name: Some("pattern"),
+                parser: Some(Parser::BlockState),
+            },
+            // 118: //cyl
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![119],
+                redirect_node: None,
+                name: Some("/cyl"),
                 parser: None,
             },
-            // 11: /p
+            // 119: //cyl [pattern]
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                flags: (CommandFlags::ARGUMENT).bits() as i8,
+                children: vec![120],
+                redirect_node: None,
+                name: Some("pattern"),
+                parser: Some(Parser::BlockState),
+            },
+            // 120: //cyl [pattern] [radius]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![121],
+                redirect_node: None,
+                name: Some("radius"),
+                parser: Some(Parser::Integer(0, i32::MAX)),
+            },
+            // 121: //cyl [pattern] [radius] [height]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
-                redirect_node: Some(6),
-                name: Some("p"),
-                parser: None,
+                redirect_node: None,
+                name: Some("height"),
+                parser: Some(Parser::Integer(1, i32::MAX)),
             },
-            // 12: /rtps
+            // 122: //hcyl
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
-                children: vec![13],
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![123],
                 redirect_node: None,
-                name: Some("rtps"),
+                name: Some("/hcyl"),
                 parser: None,
             },
-            // 13: /rtps [rtps]
+            // 123: //hcyl [pattern]
+            Node {
+                flags: (CommandFlags::ARGUMENT).bits() as i8,
+                children: vec![124],
+                redirect_node: None,
+                name: Some("pattern"),
+                parser: Some(Parser::BlockState),
+            },
+            // 124: //hcyl [pattern] [radius]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![125],
+                redirect_node: None,
+                name: Some("radius"),
+                parser: Some(Parser::Integer(0, i32::MAX)),
+            },
+            // 125: //hcyl [pattern] [radius] [height]
             Node {
                 flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
                 redirect_node: None,
-                name: Some("rtps"),
-                parser: Some(Parser::Integer(0, 35000)),
+                name: Some("height"),
+                parser: Some(Parser::Integer(1, i32::MAX)),
             },
-            // 14: //pos1
+            // 126: //sphere
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
-                children: vec![15],
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![127],
                 redirect_node: None,
-                name: Some("/pos1"),
+                name: Some("/sphere"),
                 parser: None,
             },
-            // 15: //pos1 [pos]
+            // 127: //sphere [pattern]
+            Node {
+                flags: (CommandFlags::ARGUMENT).bits() as i8,
+                children: vec![128],
+                redirect_node: None,
+                name: Some("pattern"),
+                parser: Some(Parser::BlockState),
+            },
+            // 128: //sphere [pattern] [radius]
             Node {
                 flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
                 redirect_node: None,
-                name: Some("pos"),
-                parser: Some(Parser::BlockPos),
+                name: Some("radius"),
+                parser: Some(Parser::Integer(0, i32::MAX)),
             },
-            // 16: //pos2
+            // 129: //hsphere
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
-                children: vec![17],
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![130],
                 redirect_node: None,
-                name: Some("/pos2"),
+                name: Some("/hsphere"),
                 parser: None,
             },
-            // 17: //pos2 [pos]
+            // 130: //hsphere [pattern]
+            Node {
+                flags: (CommandFlags::ARGUMENT).bits() as i8,
+                children: vec![131],
+                redirect_node: None,
+                name: Some("pattern"),
+                parser: Some(Parser::BlockState),
+            },
+            // 131: //hsphere [pattern] [radius]
             Node {
                 flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
                 redirect_node: None,
-                name: Some("pos"),
-                parser: Some(Parser::BlockPos),
+                name: Some("radius"),
+                parser: Some(Parser::Integer(0, i32::MAX)),
             },
-            // 18: /1
+            // 132: //brush
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
-                children: vec![],
-                redirect_node: Some(14),
-                name: Some("/1"),
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![133],
+                redirect_node: None,
+                name: Some("/brush"),
                 parser: None,
             },
-            // 19: /2
+            // 133: //brush sphere
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![134],
+                redirect_node: None,
+                name: Some("sphere"),
+                parser: None,
+            },
+            // 134: //brush sphere [pattern]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![135],
+                redirect_node: None,
+                name: Some("pattern"),
+                parser: Some(Parser::BlockState),
+            },
+            // 135: //brush sphere [pattern] [radius]
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
-                redirect_node: Some(16),
-                name: Some("/2"),
+                redirect_node: None,
+                name: Some("radius"),
+                parser: Some(Parser::Integer(0, i32::MAX)),
+            },
+            // 136: //tool
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![137, 139, 140],
+                redirect_node: None,
+                name: Some("/tool"),
                 parser: None,
             },
-            // 20: //copy
+            // 137: //tool replacer
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
-                children: vec![21],
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![138],
                 redirect_node: None,
-                name: Some("/copy"),
+                name: Some("replacer"),
                 parser: None,
             },
-            // 21: //c
+            // 138: //tool replacer [pattern]
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
-                redirect_node: Some(20),
-                name: Some("/c"),
-                parser: None,
+                redirect_node: None,
+                name: Some("pattern"),
+                parser: Some(Parser::BlockState),
             },
-            // 22: //paste
+            // 139: //tool info
             Node {
                 flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
-                children: vec![23],
+                children: vec![],
                 redirect_node: None,
-                name: Some("/paste"),
+                name: Some("info"),
                 parser: None,
             },
-            // 23: //p
+            // 140: //tool none
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
-                redirect_node: Some(20),
-                name: Some("/p"),
+                redirect_node: None,
+                name: Some("none"),
                 parser: None,
             },
-            // 24: //set
+            // 141: //clipboard
             Node {
                 flags: (CommandFlags::LITERAL).bits() as i8,
-                children: vec![25],
+                children: vec![142, 144, 146, 149, 150],
                 redirect_node: None,
-                name: Some("/set"),
+                name: Some("/clipboard"),
                 parser: None,
             },
-            // 25: //set [block]
+            // 142: //clipboard save
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![143],
+                redirect_node: None,
+                name: Some("save"),
+                parser: None,
+            },
+            // 143: //clipboard save <name>
             Node {
                 flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
                 redirect_node: None,
-                name: Some("block"),
-                parser: Some(Parser::BlockState),
+                name: Some("name"),
+                parser: Some(Parser::Word),
             },
-            // 26: //replace
+            // 144: //clipboard load
             Node {
                 flags: (CommandFlags::LITERAL).bits() as i8,
-                children: vec![27],
+                children: vec![145],
                 redirect_node: None,
-                name: Some("/replace"),
+                name: Some("load"),
                 parser: None,
             },
-            // 27: //replace [oldblock]
-            Node {
-                flags: (CommandFlags::ARGUMENT).bits() as i8,
-                children: vec![28],
-                redirect_node: None,
-                name: Some("oldblock"),
-                parser: Some(Parser::BlockState),
-            },
-            // 28: //replace [oldblock] [newblock]
+            // 145: //clipboard load <name>
             Node {
                 flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
                 redirect_node: None,
-                name: Some("newblock"),
-                parser: Some(Parser::BlockState),
+                name: Some("name"),
+                parser: Some(Parser::Word),
             },
-            // 29: /radvance
+            // 146: //clipboard share
             Node {
                 flags: (CommandFlags::LITERAL).bits() as i8,
-                children: vec![30],
+                children: vec![147],
                 redirect_node: None,
-                name: Some("radvance"),
+                name: Some("share"),
                 parser: None,
             },
-            // 30: /radvance [rticks]
+            // 147: //clipboard share <player>
+            Node {
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
+                children: vec![148],
+                redirect_node: None,
+                name: Some("player"),
+                parser: Some(Parser::Entity(3)),
+            },
+            // 148: //clipboard share <player> [name]
             Node {
                 flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
                 redirect_node: None,
-                name: Some("rticks"),
-                parser: Some(Parser::Integer(0, 35000)),
+                name: Some("name"),
+                parser: Some(Parser::Word),
             },
-            // 31: /radv
+            // 149: //clipboard list
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
-                redirect_node: Some(29),
-                name: Some("radv"),
+                redirect_node: None,
+                name: Some("list"),
                 parser: None,
             },
-            // 32: /speed
+            // 150: //clipboard delete
             Node {
                 flags: (CommandFlags::LITERAL).bits() as i8,
-                children: vec![33],
+                children: vec![151],
                 redirect_node: None,
-                name: Some("speed"),
+                name: Some("delete"),
                 parser: None,
             },
-            // 33: /speed [speed]
+            // 151: //clipboard delete <name>
             Node {
                 flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
                 redirect_node: None,
-                name: Some("speed"),
-                parser: Some(Parser::Float(0.0, 10.0)),
+                name: Some("name"),
+                parser: Some(Parser::Word),
             },
-            // 34: //stack
+            // 152: //schem
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
-                children: vec![35],
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![153, 155, 157, 159],
                 redirect_node: None,
-                name: Some("/stack"),
+                name: Some("/schem"),
                 parser: None,
             },
-            // 35: //stack [amount]
+            // 153: //schem load
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![154],
+                redirect_node: None,
+                name: Some("load"),
+                parser: None,
+            },
+            // 154: //schem load <name>
             Node {
                 flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
                 redirect_node: None,
-                name: Some("amount"),
-                parser: Some(Parser::Integer(0, 256)),
+                name: Some("name"),
+                parser: Some(Parser::GreedyString),
             },
-            // 36: //undo
+            // 155: //schem list
             Node {
                 flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
-                children: vec![],
+                children: vec![156],
                 redirect_node: None,
-                name: Some("/undo"),
+                name: Some("list"),
                 parser: None,
             },
-            // 37: //sel
+            // 156: //schem list [page]
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
                 redirect_node: None,
-                name: Some("/sel"),
+                name: Some("page"),
+                parser: Some(Parser::Integer(1, i32::MAX)),
+            },
+            // 157: //schem info
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![158],
+                redirect_node: None,
+                name: Some("info"),
                 parser: None,
             },
-            // 38: /p auto
+            // 158: //schem info <name>
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
                 redirect_node: None,
-                name: Some("auto"),
+                name: Some("name"),
+                parser: Some(Parser::GreedyString),
+            },
+            // 159: //schem delete
+            Node {
+                flags: (CommandFlags::LITERAL).bits() as i8,
+                children: vec![160],
+                redirect_node: None,
+                name: Some("delete"),
                 parser: None,
             },
-            // 39: /p a
+            // 160: //schem delete <name>
             Node {
-                flags: (CommandFlags::LITERAL | CommandFlags::REDIRECT).bits() as i8,
+                flags: (CommandFlags::ARGUMENT | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
-                redirect_node: Some(9),
-                name: Some("a"),
-                parser: None,
+                redirect_node: None,
+                name: Some("name"),
+                parser: Some(Parser::GreedyString),
             },
-            // 40: /p auto
+            // 161: /p unmerge confirm
             Node {
                 flags: (CommandFlags::LITERAL | CommandFlags::EXECUTABLE).bits() as i8,
                 children: vec![],
                 redirect_node: None,
-                name: Some("middle"),
+                name: Some("confirm"),
                 parser: None,
             },
         ],
@@ -1,16 +1,211 @@
-use rusqlite::{params, Connection, NO_PARAMS};
+use super::geometry::PlotRegion;
+use super::PlotSettings;
+use crate::blocks::WireAlgorithm;
+use crate::permissions::DEFAULT_GROUP;
+use rusqlite::{params, Connection, OptionalExtension, Row, NO_PARAMS};
 use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub type Result<T> = rusqlite::Result<T>;
 
 lazy_static! {
     static ref CONN: Mutex<Connection> =
         Mutex::new(Connection::open("./world/plots.db").expect("Error opening plot database!"));
 }
 
+/// The migrations that bring the database schema up to date. The schema version stored
+/// in the database is the number of migrations that have been applied, so migrations
+/// must never be changed or reordered once released. New ones go at the end.
+const MIGRATIONS: &[&str] = &[
+    // 1: The tables that existed before the schema was versioned
+    "CREATE TABLE IF NOT EXISTS user(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        uuid BLOB(16) UNIQUE NOT NULL,
+        name VARCHAR(16) NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS plot(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        plot_x INTEGER NOT NULL,
+        plot_z INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS userplot(
+        user_id INTEGER NOT NULL,
+        plot_id INTEGER NOT NULL,
+        is_owner BOOLEAN NOT NULL DEFAULT FALSE,
+        FOREIGN KEY(user_id) REFERENCES user(id),
+        FOREIGN KEY(plot_id) REFERENCES plot(id)
+    );
+    CREATE TABLE IF NOT EXISTS plothome(
+        plot_id INTEGER PRIMARY KEY,
+        x REAL NOT NULL,
+        y REAL NOT NULL,
+        z REAL NOT NULL,
+        FOREIGN KEY(plot_id) REFERENCES plot(id)
+    );
+    CREATE TABLE IF NOT EXISTS plotsettings(
+        plot_id INTEGER PRIMARY KEY,
+        keep_loaded BOOLEAN NOT NULL DEFAULT FALSE,
+        default_rtps INTEGER,
+        auto_pause BOOLEAN NOT NULL DEFAULT TRUE,
+        public_build BOOLEAN NOT NULL DEFAULT TRUE,
+        name TEXT,
+        description TEXT,
+        greeting TEXT,
+        FOREIGN KEY(plot_id) REFERENCES plot(id)
+    );
+    CREATE TABLE IF NOT EXISTS plotmerge(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        min_x INTEGER NOT NULL,
+        min_z INTEGER NOT NULL,
+        max_x INTEGER NOT NULL,
+        max_z INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS plot_size(
+        width INTEGER NOT NULL,
+        depth INTEGER NOT NULL
+    );",
+    // 2: Timestamps. Plots claimed before this migration have no creation date.
+    "CREATE TABLE plotmeta(
+        plot_id INTEGER PRIMARY KEY,
+        created_at INTEGER,
+        last_modified INTEGER,
+        last_visited INTEGER,
+        FOREIGN KEY(plot_id) REFERENCES plot(id)
+    );
+    INSERT INTO plotmeta(plot_id) SELECT id FROM plot;
+    CREATE TABLE userseen(
+        user_id INTEGER PRIMARY KEY,
+        last_seen INTEGER NOT NULL,
+        FOREIGN KEY(user_id) REFERENCES user(id)
+    );",
+    // 3: Permissions. Every player is in the default group without being added to it.
+    "CREATE TABLE permgroup(
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT UNIQUE NOT NULL
+    );
+    CREATE TABLE groupperm(
+        group_id INTEGER NOT NULL,
+        node TEXT NOT NULL,
+        value BOOLEAN NOT NULL,
+        PRIMARY KEY(group_id, node),
+        FOREIGN KEY(group_id) REFERENCES permgroup(id)
+    );
+    CREATE TABLE usergroup(
+        user_id INTEGER NOT NULL,
+        group_id INTEGER NOT NULL,
+        PRIMARY KEY(user_id, group_id),
+        FOREIGN KEY(user_id) REFERENCES user(id),
+        FOREIGN KEY(group_id) REFERENCES permgroup(id)
+    );
+    CREATE TABLE userperm(
+        user_id INTEGER NOT NULL,
+        node TEXT NOT NULL,
+        value BOOLEAN NOT NULL,
+        PRIMARY KEY(user_id, node),
+        FOREIGN KEY(user_id) REFERENCES user(id)
+    );
+    INSERT INTO permgroup(name) VALUES('default'), ('admin');
+    INSERT INTO groupperm(group_id, node, value) VALUES
+        (1, 'mchprs.command.*', TRUE),
+        (1, 'mchprs.worldedit.*', TRUE),
+        (1, 'mchprs.command.stop', FALSE),
+        (1, 'mchprs.command.gamemode', FALSE),
+        (1, 'mchprs.command.permission', FALSE),
+        (1, 'mchprs.command.rtps.high', FALSE),
+        (1, 'mchprs.worldedit.load', FALSE),
+        (2, '*', TRUE);",
+    // 4: Access control
+    "CREATE TABLE whitelist(
+        uuid TEXT PRIMARY KEY,
+        name TEXT NOT NULL
+    );
+    CREATE TABLE op(
+        uuid TEXT PRIMARY KEY,
+        name TEXT NOT NULL
+    );
+    CREATE TABLE playerban(
+        uuid TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        reason TEXT,
+        banned_by TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        expires_at INTEGER
+    );
+    CREATE TABLE ipban(
+        ip TEXT PRIMARY KEY,
+        reason TEXT,
+        banned_by TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        expires_at INTEGER
+    );
+    ALTER TABLE userseen ADD COLUMN last_ip TEXT;
+    INSERT INTO groupperm(group_id, node, value)
+        SELECT permgroup.id, nodes.node, FALSE
+        FROM permgroup, (
+            SELECT 'mchprs.command.whitelist' AS node
+            UNION ALL SELECT 'mchprs.command.ban'
+            UNION ALL SELECT 'mchprs.command.pardon'
+            UNION ALL SELECT 'mchprs.command.op'
+        ) AS nodes
+        WHERE permgroup.name = 'default';",
+    // 5: Redstone wire algorithm. Plots without one use the default.
+    "ALTER TABLE plotsettings ADD COLUMN wire_algorithm TEXT;",
+    // 6: Schematic management. Like //load, it is only for admins by default.
+    "INSERT INTO groupperm(group_id, node, value) VALUES (1, 'mchprs.worldedit.schem', FALSE);",
+    // 7: Deleting schematics has its own node, so players who can browse them can't delete them
+    "INSERT INTO groupperm(group_id, node, value)
+        VALUES (1, 'mchprs.worldedit.schem.delete', FALSE);",
+    // 8: Whether the wall of a claimed plot has been changed to the claimed wall yet. Plots
+    // claimed before this migration already have it, since it used to be built on every load.
+    "ALTER TABLE plotmeta ADD COLUMN wall_built BOOLEAN NOT NULL DEFAULT FALSE;
+    UPDATE plotmeta SET wall_built = TRUE;",
+];
+
+/// The times a plot was created, last modified and last visited, in seconds since the
+/// unix epoch.
+#[derive(Debug, Clone, Default)]
+pub struct PlotTimestamps {
+    pub created_at: Option<i64>,
+    pub last_modified: Option<i64>,
+    pub last_visited: Option<i64>,
+}
+
+/// A player or IP ban
+#[derive(Debug, Clone)]
+pub struct Ban {
+    /// The name of the banned player or the banned IP address
+    pub target: String,
+    pub reason: Option<String>,
+    pub banned_by: String,
+    pub created_at: i64,
+    /// The ban is permanent if there is no expiry time
+    pub expires_at: Option<i64>,
+}
+
+impl Ban {
+    fn from_row(row: &Row) -> Result<Ban> {
+        Ok(Ban {
+            target: row.get(0)?,
+            reason: row.get(1)?,
+            banned_by: row.get(2)?,
+            created_at: row.get(3)?,
+            expires_at: row.get(4)?,
+        })
+    }
+}
+
 fn lock<'a>() -> MutexGuard<'a, Connection> {
     CONN.lock().unwrap()
 }
 
-pub fn get_plot_owner(plot_x: i32, plot_z: i32) -> Option<String> {
+/// The current time in seconds since the unix epoch
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.as_secs() as i64)
+}
+
+pub fn get_plot_owner(plot_x: i32, plot_z: i32) -> Result<Option<String>> {
     lock()
         .query_row(
             "SELECT
@@ -28,10 +223,10 @@ pub fn get_plot_owner(plot_x: i32, plot_z: i32) -> Option<String> {
             params![plot_x, plot_z],
             |row| row.get::<_, String>(0),
         )
-        .ok()
+        .optional()
 }
 
-pub fn get_cached_username(uuid: String) -> Option<String> {
+pub fn get_cached_username(uuid: String) -> Result<Option<String>> {
     lock()
         .query_row(
             "SELECT
@@ -43,82 +238,860 @@ pub fn get_cached_username(uuid: String) -> Option<String> {
             params![uuid],
             |row| row.get::<_, String>(0),
         )
-        .ok()
+        .optional()
 }
 
-pub fn is_claimed(plot_x: i32, plot_z: i32) -> Option<bool> {
-    lock()
-        .query_row(
-            "SELECT EXISTS(SELECT * FROM plot WHERE plot_x = ?1 AND plot_z = ?2)",
-            params![plot_x, plot_z],
-            |row| row.get::<_, bool>(0),
-        )
-        .ok()
+pub fn is_claimed(plot_x: i32, plot_z: i32) -> Result<bool> {
+    lock().query_row(
+        "SELECT EXISTS(SELECT * FROM plot WHERE plot_x = ?1 AND plot_z = ?2)",
+        params![plot_x, plot_z],
+        |row| row.get::<_, bool>(0),
+    )
 }
 
-pub fn claim_plot(plot_x: i32, plot_z: i32, uuid: String) {
-    let conn = lock();
-    conn.execute(
+pub fn claim_plot(plot_x: i32, plot_z: i32, uuid: String) -> Result<()> {
+    let mut conn = lock();
+    let tx = conn.transaction()?;
+    tx.execute(
         "INSERT INTO plot(plot_x, plot_z) VALUES(?1, ?2)",
         params![plot_x, plot_z],
-    )
-    .unwrap();
+    )?;
+    let plot_id = tx.last_insert_rowid();
 
-    conn.execute(
+    tx.execute(
         "INSERT INTO userplot(user_id, plot_id, is_owner)
                 VALUES(
                     (SELECT id FROM user WHERE user.uuid = ?1),
-                    LAST_INSERT_ROWID(),
+                    ?2,
                     TRUE
                 )",
-        params![uuid],
-    )
-    .unwrap();
+        params![uuid, plot_id],
+    )?;
+
+    tx.execute(
+        "INSERT INTO plotmeta(plot_id, created_at) VALUES(?1, ?2)",
+        params![plot_id, now()],
+    )?;
+    tx.commit()
 }
 
-pub fn ensure_user(uuid: String, name: &str) {
+/// Returns the plots owned by the user in the order they were claimed
+pub fn get_owned_plots(uuid: &str) -> Result<Vec<(i32, i32)>> {
+    let conn = lock();
+    let mut stmt = conn.prepare(
+        "SELECT
+            plot_x, plot_z
+        FROM
+            plot
+        JOIN
+            userplot ON userplot.plot_id = plot.id
+        JOIN
+            user ON user.id = userplot.user_id
+        WHERE
+            uuid=?1
+            AND is_owner=TRUE
+        ORDER BY
+            plot.id",
+    )?;
+    let plots = stmt.query_map(params![uuid], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    plots.collect()
+}
+
+/// Looks up the uuid of a user that has joined the server before by their name
+pub fn get_cached_uuid(name: &str) -> Result<Option<String>> {
     lock()
-        .execute(
-            "INSERT INTO user(uuid, name)
-                VALUES (?1, ?2)
-                ON CONFLICT (uuid) DO UPDATE SET name = ?3",
-            params![uuid, name, name],
+        .query_row(
+            "SELECT
+                uuid
+            FROM
+                user
+            WHERE
+                name=?1 COLLATE NOCASE",
+            params![name],
+            |row| row.get::<_, String>(0),
         )
-        .unwrap();
+        .optional()
+}
+
+pub fn get_plot_home(plot_x: i32, plot_z: i32) -> Result<Option<(f64, f64, f64)>> {
+    lock()
+        .query_row(
+            "SELECT
+                x, y, z
+            FROM
+                plothome
+            JOIN
+                plot ON plot.id = plothome.plot_id
+            WHERE
+                plot_x=?1
+                AND plot_z=?2",
+            params![plot_x, plot_z],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
 }
 
-pub fn init() {
+pub fn set_plot_home(plot_x: i32, plot_z: i32, x: f64, y: f64, z: f64) -> Result<()> {
+    lock().execute(
+        "INSERT INTO plothome(plot_id, x, y, z)
+            VALUES(
+                (SELECT id FROM plot WHERE plot_x = ?1 AND plot_z = ?2),
+                ?3, ?4, ?5
+            )
+            ON CONFLICT (plot_id) DO UPDATE SET x = ?3, y = ?4, z = ?5",
+        params![plot_x, plot_z, x, y, z],
+    )?;
+    Ok(())
+}
+
+pub fn get_plot_settings(plot_x: i32, plot_z: i32) -> Result<Option<PlotSettings>> {
+    lock()
+        .query_row(
+            "SELECT
+                keep_loaded, default_rtps, auto_pause, public_build, name, description, greeting,
+                wire_algorithm
+            FROM
+                plotsettings
+            JOIN
+                plot ON plot.id = plotsettings.plot_id
+            WHERE
+                plot_x=?1
+                AND plot_z=?2",
+            params![plot_x, plot_z],
+            |row| {
+                let wire_algorithm: Option<String> = row.get(7)?;
+                Ok(PlotSettings {
+                    keep_loaded: row.get(0)?,
+                    default_rtps: row.get(1)?,
+                    auto_pause: row.get(2)?,
+                    public_build: row.get(3)?,
+                    name: row.get(4)?,
+                    description: row.get(5)?,
+                    greeting: row.get(6)?,
+                    wire_algorithm: wire_algorithm
+                        .and_then(|name| WireAlgorithm::from_name(&name))
+                        .unwrap_or_default(),
+                })
+            },
+        )
+        .optional()
+}
+
+pub fn set_plot_settings(plot_x: i32, plot_z: i32, settings: &PlotSettings) -> Result<()> {
+    lock().execute(
+        "INSERT INTO plotsettings(
+            plot_id, keep_loaded, default_rtps, auto_pause, public_build, name, description, greeting,
+            wire_algorithm
+        )
+            VALUES(
+                (SELECT id FROM plot WHERE plot_x = ?1 AND plot_z = ?2),
+                ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10
+            )
+            ON CONFLICT (plot_id) DO UPDATE SET
+                keep_loaded = ?3,
+                default_rtps = ?4,
+                auto_pause = ?5,
+                public_build = ?6,
+                name = ?7,
+                description = ?8,
+                greeting = ?9,
+                wire_algorithm = ?10",
+        params![
+            plot_x,
+            plot_z,
+            settings.keep_loaded,
+            settings.default_rtps,
+            settings.auto_pause,
+            settings.public_build,
+            settings.name,
+            settings.description,
+            settings.greeting,
+            settings.wire_algorithm.name()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Returns all plots that have keep-loaded enabled
+pub fn get_keep_loaded_plots() -> Result<Vec<(i32, i32)>> {
     let conn = lock();
+    let mut stmt = conn.prepare(
+        "SELECT
+            plot_x, plot_z
+        FROM
+            plot
+        JOIN
+            plotsettings ON plotsettings.plot_id = plot.id
+        WHERE
+            keep_loaded=TRUE",
+    )?;
+    let plots = stmt.query_map(NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?)))?;
+    plots.collect()
+}
 
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS user(
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            uuid BLOB(16) UNIQUE NOT NULL,
-            name VARCHAR(16) NOT NULL
+/// Returns the region of merged plots that contains the plot, if it has been merged
+pub fn get_merge_region(plot_x: i32, plot_z: i32) -> Result<Option<PlotRegion>> {
+    lock()
+        .query_row(
+            "SELECT
+                min_x, min_z, max_x, max_z
+            FROM
+                plotmerge
+            WHERE
+                ?1 BETWEEN min_x AND max_x
+                AND ?2 BETWEEN min_z AND max_z",
+            params![plot_x, plot_z],
+            |row| {
+                Ok(PlotRegion {
+                    min_x: row.get(0)?,
+                    min_z: row.get(1)?,
+                    max_x: row.get(2)?,
+                    max_z: row.get(3)?,
+                })
+            },
+        )
+        .optional()
+}
+
+/// Returns the region the plot belongs to, which is just the plot itself if it isn't merged
+pub fn get_plot_region(plot_x: i32, plot_z: i32) -> Result<PlotRegion> {
+    Ok(get_merge_region(plot_x, plot_z)?.unwrap_or_else(|| PlotRegion::single(plot_x, plot_z)))
+}
+
+/// Records the plots in the region as merged, replacing any smaller merges inside of it
+pub fn merge_plots(region: PlotRegion) -> Result<()> {
+    let mut conn = lock();
+    let tx = conn.transaction()?;
+    tx.execute(
+        "DELETE FROM plotmerge
+            WHERE min_x >= ?1 AND min_z >= ?2 AND max_x <= ?3 AND max_z <= ?4",
+        params![region.min_x, region.min_z, region.max_x, region.max_z],
+    )?;
+    tx.execute(
+        "INSERT INTO plotmerge(min_x, min_z, max_x, max_z) VALUES(?1, ?2, ?3, ?4)",
+        params![region.min_x, region.min_z, region.max_x, region.max_z],
+    )?;
+    tx.commit()
+}
+
+pub fn unmerge_plots(region: PlotRegion) -> Result<()> {
+    lock().execute(
+        "DELETE FROM plotmerge WHERE min_x = ?1 AND min_z = ?2 AND max_x = ?3 AND max_z = ?4",
+        params![region.min_x, region.min_z, region.max_x, region.max_z],
+    )?;
+    Ok(())
+}
+
+pub fn get_plot_timestamps(plot_x: i32, plot_z: i32) -> Result<Option<PlotTimestamps>> {
+    lock()
+        .query_row(
+            "SELECT
+                created_at, last_modified, last_visited
+            FROM
+                plotmeta
+            JOIN
+                plot ON plot.id = plotmeta.plot_id
+            WHERE
+                plot_x=?1
+                AND plot_z=?2",
+            params![plot_x, plot_z],
+            |row| {
+                Ok(PlotTimestamps {
+                    created_at: row.get(0)?,
+                    last_modified: row.get(1)?,
+                    last_visited: row.get(2)?,
+                })
+            },
+        )
+        .optional()
+}
+
+/// Sets the last modified time of the plots in the region to now.
+/// Plots that haven't been claimed are ignored.
+pub fn touch_plots_modified(region: PlotRegion) -> Result<()> {
+    lock().execute(
+        "UPDATE plotmeta SET last_modified = ?5
+            WHERE plot_id IN (
+                SELECT id FROM plot
+                WHERE plot_x BETWEEN ?1 AND ?3 AND plot_z BETWEEN ?2 AND ?4
+            )",
+        params![
+            region.min_x,
+            region.min_z,
+            region.max_x,
+            region.max_z,
+            now()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Returns true if a plot in the region was claimed, but its wall hasn't been changed to
+/// the claimed wall yet. This happens when a plot is claimed while it isn't loaded.
+pub fn is_wall_outdated(region: PlotRegion) -> Result<bool> {
+    lock().query_row(
+        "SELECT EXISTS(
+            SELECT * FROM plot JOIN plotmeta ON plotmeta.plot_id = plot.id
+            WHERE plot_x BETWEEN ?1 AND ?3 AND plot_z BETWEEN ?2 AND ?4
+                AND NOT plotmeta.wall_built
         )",
-        NO_PARAMS,
+        params![region.min_x, region.min_z, region.max_x, region.max_z],
+        |row| row.get::<_, bool>(0),
     )
-    .unwrap();
+}
 
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS plot(
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            plot_x INTEGER NOT NULL,
-            plot_z INTEGER NOT NULL
-        )",
-        NO_PARAMS,
+/// Records that the claimed plots in the region have the claimed wall
+pub fn set_walls_built(region: PlotRegion) -> Result<()> {
+    lock().execute(
+        "UPDATE plotmeta SET wall_built = TRUE
+            WHERE plot_id IN (
+                SELECT id FROM plot
+                WHERE plot_x BETWEEN ?1 AND ?3 AND plot_z BETWEEN ?2 AND ?4
+            )",
+        params![region.min_x, region.min_z, region.max_x, region.max_z],
+    )?;
+    Ok(())
+}
+
+/// Sets the last visited time of the plots in the region to now.
+/// Plots that haven't been claimed are ignored.
+pub fn touch_plots_visited(region: PlotRegion) -> Result<()> {
+    lock().execute(
+        "UPDATE plotmeta SET last_visited = ?5
+            WHERE plot_id IN (
+                SELECT id FROM plot
+                WHERE plot_x BETWEEN ?1 AND ?3 AND plot_z BETWEEN ?2 AND ?4
+            )",
+        params![
+            region.min_x,
+            region.min_z,
+            region.max_x,
+            region.max_z,
+            now()
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn ensure_user(uuid: String, name: &str) -> Result<()> {
+    lock().execute(
+        "INSERT INTO user(uuid, name)
+            VALUES (?1, ?2)
+            ON CONFLICT (uuid) DO UPDATE SET name = ?3",
+        params![uuid, name, name],
+    )?;
+    Ok(())
+}
+
+/// Sets the time the user was last seen on the server to now
+pub fn touch_user_seen(uuid: String) -> Result<()> {
+    lock().execute(
+        "INSERT INTO userseen(user_id, last_seen)
+            VALUES((SELECT id FROM user WHERE uuid = ?1), ?2)
+            ON CONFLICT (user_id) DO UPDATE SET last_seen = ?2",
+        params![uuid, now()],
+    )?;
+    Ok(())
+}
+
+pub fn get_user_last_seen(uuid: String) -> Result<Option<i64>> {
+    lock()
+        .query_row(
+            "SELECT
+                last_seen
+            FROM
+                userseen
+            JOIN
+                user ON user.id = userseen.user_id
+            WHERE
+                uuid=?1",
+            params![uuid],
+            |row| row.get::<_, i64>(0),
+        )
+        .optional()
+}
+
+/// Returns the permission nodes given to the user directly
+pub fn get_user_permissions(uuid: &str) -> Result<Vec<(String, bool)>> {
+    let conn = lock();
+    let mut stmt = conn.prepare(
+        "SELECT
+            node, value
+        FROM
+            userperm
+        JOIN
+            user ON user.id = userperm.user_id
+        WHERE
+            uuid=?1",
+    )?;
+    let nodes = stmt.query_map(params![uuid], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    nodes.collect()
+}
+
+/// Returns the permission nodes of every group the user is in, including the default group
+pub fn get_user_group_permissions(uuid: &str) -> Result<Vec<(String, bool)>> {
+    let conn = lock();
+    let mut stmt = conn.prepare(
+        "SELECT
+            node, value
+        FROM
+            groupperm
+        JOIN
+            permgroup ON permgroup.id = groupperm.group_id
+        WHERE
+            permgroup.name=?2
+            OR permgroup.id IN (
+                SELECT group_id FROM usergroup
+                JOIN user ON user.id = usergroup.user_id
+                WHERE uuid=?1
+            )",
+    )?;
+    let nodes = stmt.query_map(params![uuid, DEFAULT_GROUP], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+    nodes.collect()
+}
+
+/// Returns the names of the groups the user has been added to
+pub fn get_user_groups(uuid: &str) -> Result<Vec<String>> {
+    let conn = lock();
+    let mut stmt = conn.prepare(
+        "SELECT
+            permgroup.name
+        FROM
+            usergroup
+        JOIN
+            permgroup ON permgroup.id = usergroup.group_id
+        JOIN
+            user ON user.id = usergroup.user_id
+        WHERE
+            uuid=?1
+        ORDER BY
+            permgroup.name",
+    )?;
+    let groups = stmt.query_map(params![uuid], |row| row.get(0))?;
+    groups.collect()
+}
+
+pub fn set_user_permission(uuid: &str, node: &str, value: bool) -> Result<()> {
+    lock().execute(
+        "INSERT INTO userperm(user_id, node, value)
+            VALUES((SELECT id FROM user WHERE uuid = ?1), ?2, ?3)
+            ON CONFLICT (user_id, node) DO UPDATE SET value = ?3",
+        params![uuid, node, value],
+    )?;
+    Ok(())
+}
+
+/// Removes a permission node from the user. Returns false if the user didn't have it.
+pub fn unset_user_permission(uuid: &str, node: &str) -> Result<bool> {
+    let removed = lock().execute(
+        "DELETE FROM userperm
+            WHERE user_id = (SELECT id FROM user WHERE uuid = ?1) AND node = ?2",
+        params![uuid, node],
+    )?;
+    Ok(removed > 0)
+}
+
+/// Adds the user to a group. Returns false if the group doesn't exist.
+pub fn add_user_group(uuid: &str, group: &str) -> Result<bool> {
+    let added = lock().execute(
+        "INSERT OR IGNORE INTO usergroup(user_id, group_id)
+            SELECT user.id, permgroup.id FROM user, permgroup
+            WHERE user.uuid = ?1 AND permgroup.name = ?2",
+        params![uuid, group],
+    )?;
+    Ok(added > 0 || group_exists(group)?)
+}
+
+/// Removes the user from a group. Returns false if the user wasn't in it.
+pub fn remove_user_group(uuid: &str, group: &str) -> Result<bool> {
+    let removed = lock().execute(
+        "DELETE FROM usergroup
+            WHERE user_id = (SELECT id FROM user WHERE uuid = ?1)
+            AND group_id = (SELECT id FROM permgroup WHERE name = ?2)",
+        params![uuid, group],
+    )?;
+    Ok(removed > 0)
+}
+
+pub fn group_exists(group: &str) -> Result<bool> {
+    lock().query_row(
+        "SELECT EXISTS(SELECT * FROM permgroup WHERE name = ?1)",
+        params![group],
+        |row| row.get(0),
+    )
+}
+
+pub fn get_groups() -> Result<Vec<String>> {
+    let conn = lock();
+    let mut stmt = conn.prepare("SELECT name FROM permgroup ORDER BY name")?;
+    let groups = stmt.query_map(NO_PARAMS, |row| row.get(0))?;
+    groups.collect()
+}
+
+/// Creates a permission group. Returns false if it already exists.
+pub fn create_group(group: &str) -> Result<bool> {
+    let created = lock().execute(
+        "INSERT OR IGNORE INTO permgroup(name) VALUES(?1)",
+        params![group],
+    )?;
+    Ok(created > 0)
+}
+
+/// Deletes a permission group along with its nodes and members. Returns false if it
+/// doesn't exist.
+pub fn delete_group(group: &str) -> Result<bool> {
+    let mut conn = lock();
+    let tx = conn.transaction()?;
+    let group_id = tx
+        .query_row(
+            "SELECT id FROM permgroup WHERE name = ?1",
+            params![group],
+            |row| row.get::<_, i64>(0),
+        )
+        .optional()?;
+    let group_id = match group_id {
+        Some(group_id) => group_id,
+        None => return Ok(false),
+    };
+    tx.execute(
+        "DELETE FROM groupperm WHERE group_id = ?1",
+        params![group_id],
+    )?;
+    tx.execute(
+        "DELETE FROM usergroup WHERE group_id = ?1",
+        params![group_id],
+    )?;
+    tx.execute("DELETE FROM permgroup WHERE id = ?1", params![group_id])?;
+    tx.commit()?;
+    Ok(true)
+}
+
+/// Returns the permission nodes of a group, or `None` if the group doesn't exist
+pub fn get_group_permissions(group: &str) -> Result<Option<Vec<(String, bool)>>> {
+    if !group_exists(group)? {
+        return Ok(None);
+    }
+    let conn = lock();
+    let mut stmt = conn.prepare(
+        "SELECT
+            node, value
+        FROM
+            groupperm
+        JOIN
+            permgroup ON permgroup.id = groupperm.group_id
+        WHERE
+            permgroup.name=?1
+        ORDER BY
+            node",
+    )?;
+    let nodes = stmt.query_map(params![group], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    nodes.collect::<Result<_>>().map(Some)
+}
+
+/// Sets a permission node of a group. Returns false if the group doesn't exist.
+pub fn set_group_permission(group: &str, node: &str, value: bool) -> Result<bool> {
+    let changed = lock().execute(
+        "INSERT INTO groupperm(group_id, node, value)
+            SELECT id, ?2, ?3 FROM permgroup WHERE name = ?1
+            ON CONFLICT (group_id, node) DO UPDATE SET value = ?3",
+        params![group, node, value],
+    )?;
+    Ok(changed > 0)
+}
+
+/// Removes a permission node from a group. Returns false if the group didn't have it.
+pub fn unset_group_permission(group: &str, node: &str) -> Result<bool> {
+    let removed = lock().execute(
+        "DELETE FROM groupperm
+            WHERE group_id = (SELECT id FROM permgroup WHERE name = ?1) AND node = ?2",
+        params![group, node],
+    )?;
+    Ok(removed > 0)
+}
+
+pub fn is_whitelisted(uuid: &str) -> Result<bool> {
+    lock().query_row(
+        "SELECT EXISTS(SELECT * FROM whitelist WHERE uuid = ?1)",
+        params![uuid],
+        |row| row.get(0),
     )
-    .unwrap();
+}
+
+/// Adds the player to the whitelist. Returns false if they were already on it.
+pub fn add_to_whitelist(uuid: &str, name: &str) -> Result<bool> {
+    let added = lock().execute(
+        "INSERT OR IGNORE INTO whitelist(uuid, name) VALUES(?1, ?2)",
+        params![uuid, name],
+    )?;
+    Ok(added > 0)
+}
+
+/// Removes the player from the whitelist. Returns false if they weren't on it.
+pub fn remove_from_whitelist(uuid: &str) -> Result<bool> {
+    let removed = lock().execute("DELETE FROM whitelist WHERE uuid = ?1", params![uuid])?;
+    Ok(removed > 0)
+}
+
+/// Returns the names of the whitelisted players
+pub fn get_whitelist() -> Result<Vec<String>> {
+    let conn = lock();
+    let mut stmt = conn.prepare("SELECT name FROM whitelist ORDER BY name")?;
+    let names = stmt.query_map(NO_PARAMS, |row| row.get(0))?;
+    names.collect()
+}
+
+pub fn is_op(uuid: &str) -> Result<bool> {
+    lock().query_row(
+        "SELECT EXISTS(SELECT * FROM op WHERE uuid = ?1)",
+        params![uuid],
+        |row| row.get(0),
+    )
+}
+
+/// Makes the player an operator. Returns false if they already were one.
+pub fn add_op(uuid: &str, name: &str) -> Result<bool> {
+    let added = lock().execute(
+        "INSERT OR IGNORE INTO op(uuid, name) VALUES(?1, ?2)",
+        params![uuid, name],
+    )?;
+    Ok(added > 0)
+}
+
+/// Takes away the player's operator status. Returns false if they weren't an operator.
+pub fn remove_op(uuid: &str) -> Result<bool> {
+    let removed = lock().execute("DELETE FROM op WHERE uuid = ?1", params![uuid])?;
+    Ok(removed > 0)
+}
+
+/// Returns the names of the operators
+pub fn get_ops() -> Result<Vec<String>> {
+    let conn = lock();
+    let mut stmt = conn.prepare("SELECT name FROM op ORDER BY name")?;
+    let names = stmt.query_map(NO_PARAMS, |row| row.get(0))?;
+    names.collect()
+}
+
+/// Returns when a ban that starts now and lasts `duration` seconds expires. Durations that
+/// overflow the timestamp are rejected.
+fn ban_expiry(now: i64, duration: Option<i64>) -> Result<Option<i64>> {
+    match duration {
+        Some(duration) => match now.checked_add(duration) {
+            Some(expires_at) => Ok(Some(expires_at)),
+            None => Err(rusqlite::Error::IntegralValueOutOfRange(5, duration)),
+        },
+        None => Ok(None),
+    }
+}
+
+/// Bans a player, replacing any existing ban. The ban lasts for `duration` seconds, or
+/// forever if there is no duration.
+pub fn ban_player(
+    uuid: &str,
+    name: &str,
+    reason: Option<&str>,
+    banned_by: &str,
+    duration: Option<i64>,
+) -> Result<()> {
+    let now = now();
+    let expires_at = ban_expiry(now, duration)?;
+    lock().execute(
+        "INSERT OR REPLACE INTO playerban(uuid, name, reason, banned_by, created_at, expires_at)
+            VALUES(?1, ?2, ?3, ?4, ?5, ?6)",
+        params![uuid, name, reason, banned_by, now, expires_at],
+    )?;
+    Ok(())
+}
 
+/// Returns the player's ban if they are banned and it hasn't expired yet
+pub fn get_player_ban(uuid: &str) -> Result<Option<Ban>> {
+    lock()
+        .query_row(
+            "SELECT
+                name, reason, banned_by, created_at, expires_at
+            FROM
+                playerban
+            WHERE
+                uuid=?1
+                AND (expires_at IS NULL OR expires_at > ?2)",
+            params![uuid, now()],
+            Ban::from_row,
+        )
+        .optional()
+}
+
+/// Lifts a player's ban. Returns false if they weren't banned.
+pub fn pardon_player(uuid: &str) -> Result<bool> {
+    let removed = lock().execute("DELETE FROM playerban WHERE uuid = ?1", params![uuid])?;
+    Ok(removed > 0)
+}
+
+/// Bans an IP address, replacing any existing ban. The ban lasts for `duration` seconds,
+/// or forever if there is no duration.
+pub fn ban_ip(
+    ip: &str,
+    reason: Option<&str>,
+    banned_by: &str,
+    duration: Option<i64>,
+) -> Result<()> {
+    let now = now();
+    let expires_at = ban_expiry(now, duration)?;
+    lock().execute(
+        "INSERT OR REPLACE INTO ipban(ip, reason, banned_by, created_at, expires_at)
+            VALUES(?1, ?2, ?3, ?4, ?5)",
+        params![ip, reason, banned_by, now, expires_at],
+    )?;
+    Ok(())
+}
+
+/// Returns the ban of an IP address if it is banned and the ban hasn't expired yet
+pub fn get_ip_ban(ip: &str) -> Result<Option<Ban>> {
+    lock()
+        .query_row(
+            "SELECT
+                ip, reason, banned_by, created_at, expires_at
+            FROM
+                ipban
+            WHERE
+                ip=?1
+                AND (expires_at IS NULL OR expires_at > ?2)",
+            params![ip, now()],
+            Ban::from_row,
+        )
+        .optional()
+}
+
+/// Lifts the ban of an IP address. Returns false if it wasn't banned.
+pub fn pardon_ip(ip: &str) -> Result<bool> {
+    let removed = lock().execute("DELETE FROM ipban WHERE ip = ?1", params![ip])?;
+    Ok(removed > 0)
+}
+
+/// Returns all player bans and IP bans that haven't expired
+pub fn get_bans() -> Result<Vec<Ban>> {
+    let conn = lock();
+    let mut stmt = conn.prepare(
+        "SELECT name, reason, banned_by, created_at, expires_at FROM playerban
+            WHERE expires_at IS NULL OR expires_at > ?1
+        UNION ALL
+        SELECT ip, reason, banned_by, created_at, expires_at FROM ipban
+            WHERE expires_at IS NULL OR expires_at > ?1
+        ORDER BY created_at",
+    )?;
+    let bans = stmt.query_map(params![now()], Ban::from_row)?;
+    bans.collect()
+}
+
+/// Records the IP address the user last joined from
+pub fn set_user_ip(uuid: &str, ip: &str) -> Result<()> {
+    lock().execute(
+        "UPDATE userseen SET last_ip = ?2
+            WHERE user_id = (SELECT id FROM user WHERE uuid = ?1)",
+        params![uuid, ip],
+    )?;
+    Ok(())
+}
+
+pub fn get_user_ip(uuid: &str) -> Result<Option<String>> {
+    lock()
+        .query_row(
+            "SELECT
+                last_ip
+            FROM
+                userseen
+            JOIN
+                user ON user.id = userseen.user_id
+            WHERE
+                uuid=?1",
+            params![uuid],
+            |row| row.get::<_, Option<String>>(0),
+        )
+        .optional()
+        .map(Option::flatten)
+}
+
+/// Returns the plot width and depth that the world was created with
+pub fn get_plot_size() -> Result<Option<(i32, i32)>> {
+    lock()
+        .query_row("SELECT width, depth FROM plot_size", NO_PARAMS, |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?))
+        })
+        .optional()
+}
+
+pub fn set_plot_size(width: i32, depth: i32) -> Result<()> {
+    let mut conn = lock();
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM plot_size", NO_PARAMS)?;
+    tx.execute(
+        "INSERT INTO plot_size(width, depth) VALUES(?1, ?2)",
+        params![width, depth],
+    )?;
+    tx.commit()
+}
+
+/// Returns the number of migrations that have been applied to the database
+fn schema_version(conn: &Connection) -> Result<usize> {
     conn.execute(
-        "CREATE TABLE IF NOT EXISTS userplot(
-            user_id INTEGER NOT NULL,
-            plot_id INTEGER NOT NULL,
-            is_owner BOOLEAN NOT NULL DEFAULT FALSE,
-            FOREIGN KEY(user_id) REFERENCES user(id),
-            FOREIGN KEY(plot_id) REFERENCES plot(id)
-        )",
+        "CREATE TABLE IF NOT EXISTS schema_version(version INTEGER NOT NULL)",
         NO_PARAMS,
-    )
-    .unwrap();
+    )?;
+    let version = conn
+        .query_row("SELECT version FROM schema_version", NO_PARAMS, |row| {
+            row.get::<_, i64>(0)
+        })
+        .optional()?;
+    Ok(version.unwrap_or(0) as usize)
+}
+
+/// Applies the migrations that haven't been applied to the database yet. Each migration
+/// runs in its own transaction together with the version bump.
+fn migrate(conn: &mut Connection) -> Result<()> {
+    let version = schema_version(conn)?;
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.execute("DELETE FROM schema_version", NO_PARAMS)?;
+        tx.execute(
+            "INSERT INTO schema_version(version) VALUES(?1)",
+            params![(i + 1) as i64],
+        )?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+pub fn init() -> Result<()> {
+    migrate(&mut lock())
+}
+
+#[test]
+fn database_migrations() {
+    let mut conn = Connection::open_in_memory().unwrap();
+    migrate(&mut conn).unwrap();
+    assert_eq!(schema_version(&conn).unwrap(), MIGRATIONS.len());
+    // Running the migrations again must not do anything
+    migrate(&mut conn).unwrap();
+    assert_eq!(schema_version(&conn).unwrap(), MIGRATIONS.len());
+    conn.execute("INSERT INTO plot(plot_x, plot_z) VALUES(1, 2)", NO_PARAMS)
+        .unwrap();
+    conn.execute("INSERT INTO plotmeta(plot_id) VALUES(1)", NO_PARAMS)
+        .unwrap();
+    // Newly claimed plots still need their wall built
+    let wall_built: bool = conn
+        .query_row(
+            "SELECT wall_built FROM plotmeta WHERE plot_id = 1",
+            NO_PARAMS,
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert!(!wall_built);
+}
+
+#[test]
+fn ban_expiry_overflow() {
+    assert_eq!(ban_expiry(100, Some(60)).unwrap(), Some(160));
+    assert_eq!(ban_expiry(100, None).unwrap(), None);
+    assert!(ban_expiry(100, Some(i64::MAX)).is_err());
 }
@@ -0,0 +1,514 @@
+use crate::blocks::Block;
+use crate::config::{ServerConfig, CONFIG};
+use std::fmt;
+
+lazy_static! {
+    /// The plot layout used by the whole server, derived from `Config.toml`.
+    pub static ref PLOT_GEOMETRY: PlotGeometry = PlotGeometry::from_config(&CONFIG);
+}
+
+/// A rectangle of plots that are simulated together by one plot thread. Plots that
+/// haven't been merged are a region of a single plot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlotRegion {
+    pub min_x: i32,
+    pub min_z: i32,
+    pub max_x: i32,
+    pub max_z: i32,
+}
+
+impl PlotRegion {
+    pub fn single(plot_x: i32, plot_z: i32) -> PlotRegion {
+        PlotRegion {
+            min_x: plot_x,
+            min_z: plot_z,
+            max_x: plot_x,
+            max_z: plot_z,
+        }
+    }
+
+    /// The smallest region containing both regions
+    pub fn union(&self, other: &PlotRegion) -> PlotRegion {
+        PlotRegion {
+            min_x: self.min_x.min(other.min_x),
+            min_z: self.min_z.min(other.min_z),
+            max_x: self.max_x.max(other.max_x),
+            max_z: self.max_z.max(other.max_z),
+        }
+    }
+
+    pub fn contains(&self, plot_x: i32, plot_z: i32) -> bool {
+        plot_x >= self.min_x && plot_x <= self.max_x && plot_z >= self.min_z && plot_z <= self.max_z
+    }
+
+    pub fn contains_region(&self, other: &PlotRegion) -> bool {
+        self.contains(other.min_x, other.min_z) && self.contains(other.max_x, other.max_z)
+    }
+
+    pub fn intersects(&self, other: &PlotRegion) -> bool {
+        self.min_x <= other.max_x
+            && other.min_x <= self.max_x
+            && self.min_z <= other.max_z
+            && other.min_z <= self.max_z
+    }
+
+    pub fn is_single(&self) -> bool {
+        self.min_x == self.max_x && self.min_z == self.max_z
+    }
+
+    /// The number of plots in the region along the x axis
+    pub fn size_x(&self) -> i32 {
+        self.max_x - self.min_x + 1
+    }
+
+    /// The number of plots in the region along the z axis
+    pub fn size_z(&self) -> i32 {
+        self.max_z - self.min_z + 1
+    }
+
+    /// Returns the coordinates of every plot in the region. The position of a plot in
+    /// this list is also the position of its chunks in the region's chunk storage.
+    pub fn plots(&self) -> Vec<(i32, i32)> {
+        let mut plots = Vec::new();
+        for plot_x in self.min_x..=self.max_x {
+            for plot_z in self.min_z..=self.max_z {
+                plots.push((plot_x, plot_z));
+            }
+        }
+        plots
+    }
+
+    fn plot_index(&self, plot_x: i32, plot_z: i32) -> usize {
+        ((plot_x - self.min_x) * self.size_z() + (plot_z - self.min_z)) as usize
+    }
+}
+
+impl fmt::Display for PlotRegion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_single() {
+            write!(f, "{},{}", self.min_x, self.min_z)
+        } else {
+            write!(
+                f,
+                "{},{} to {},{}",
+                self.min_x, self.min_z, self.max_x, self.max_z
+            )
+        }
+    }
+}
+
+/// Describes the size and shape of every plot in the world. All conversions between
+/// block, chunk, and plot coordinates should go through this type.
+///
+/// Each plot owns a `width` by `depth` cell of the world. The outer edge of the cell is
+/// taken up by half of the road separating it from its neighbors, followed by a one block
+/// wide border. Players can only build inside of the border. When plots are merged, the
+/// roads and borders between them become part of the build area.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlotGeometry {
+    /// The size of a plot along the x axis in blocks. This is always a multiple of 16.
+    pub width: i32,
+    /// The size of a plot along the z axis in blocks. This is always a multiple of 16.
+    pub depth: i32,
+    /// The highest y coordinate (exclusive) that blocks can be placed at.
+    pub height: i32,
+    /// The number of floor layers generated in a new plot.
+    pub floor_layers: i32,
+    /// The block state id of the floor generated in a new plot.
+    pub floor_block: u32,
+    /// The total width of the road between two neighboring plots.
+    pub road_width: i32,
+    /// The block state id used for the road surface.
+    pub road_block: u32,
+    /// The block state id of the border below the plot wall.
+    pub border_block: u32,
+    /// The block state id of the wall on top of the border of unclaimed plots.
+    pub wall_block: u32,
+    /// The block state id of the wall on top of the border of claimed plots.
+    pub claimed_wall_block: u32,
+}
+
+impl PlotGeometry {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.width <= 0 || self.width % 16 != 0 {
+            return Err(format!(
+                "plot_width must be a positive multiple of 16, got {}",
+                self.width
+            ));
+        }
+        if self.depth <= 0 || self.depth % 16 != 0 {
+            return Err(format!(
+                "plot_depth must be a positive multiple of 16, got {}",
+                self.depth
+            ));
+        }
+        if self.height <= 0 || self.height > 256 {
+            return Err(format!(
+                "plot_height must be between 1 and 256, got {}",
+                self.height
+            ));
+        }
+        if self.floor_layers < 0 || self.floor_layers >= self.height {
+            return Err(format!(
+                "plot_floor_layers must be between 0 and plot_height, got {}",
+                self.floor_layers
+            ));
+        }
+        // The road and both borders have to leave at least one buildable block
+        if self.road_width < 0
+            || self.road_width + 2 >= self.width
+            || self.road_width + 2 >= self.depth
+        {
+            return Err(format!(
+                "plot_road_width must leave room for the plot, got {}",
+                self.road_width
+            ));
+        }
+        Ok(())
+    }
+
+    fn from_config(config: &ServerConfig) -> PlotGeometry {
+        let block = |key: &str, name: &str| {
+            PlotGeometry::parse_block(name)
+                .unwrap_or_else(|| panic!("Unknown block for {}: {}", key, name))
+        };
+        let geometry = PlotGeometry {
+            width: config.plot_width,
+            depth: config.plot_depth,
+            height: config.plot_height,
+            floor_layers: config.plot_floor_layers,
+            floor_block: block("plot_floor_block", &config.plot_floor_block),
+            road_width: config.plot_road_width,
+            road_block: block("plot_road_block", &config.plot_road_block),
+            border_block: block("plot_border_block", &config.plot_border_block),
+            wall_block: block("plot_wall_block", &config.plot_wall_block),
+            claimed_wall_block: block("plot_claimed_wall_block", &config.plot_claimed_wall_block),
+        };
+        if let Err(err) = geometry.validate() {
+            panic!("Invalid plot configuration: {}", err);
+        }
+        geometry
+    }
+
+    /// Parses either a block name (`sandstone`, `minecraft:glass`) or a raw block state id.
+    fn parse_block(name: &str) -> Option<u32> {
+        if let Ok(id) = name.parse::<u32>() {
+            return Some(id);
+        }
+        Block::from_name(name.trim_start_matches("minecraft:")).map(Block::get_id)
+    }
+
+    /// The number of chunks a plot spans along the x axis
+    pub fn chunks_x(&self) -> i32 {
+        self.width >> 4
+    }
+
+    /// The number of chunks a plot spans along the z axis
+    pub fn chunks_z(&self) -> i32 {
+        self.depth >> 4
+    }
+
+    /// The total number of chunks stored in a plot
+    pub fn chunk_count(&self) -> usize {
+        (self.chunks_x() * self.chunks_z()) as usize
+    }
+
+    /// Returns the coordinates of the plot containing the block at `x` and `z`.
+    /// This includes the road and border surrounding the plot.
+    pub fn plot_at(&self, x: i32, z: i32) -> (i32, i32) {
+        (x.div_euclid(self.width), z.div_euclid(self.depth))
+    }
+
+    /// Returns the coordinates of the plot containing the chunk at `chunk_x` and `chunk_z`.
+    pub fn plot_at_chunk(&self, chunk_x: i32, chunk_z: i32) -> (i32, i32) {
+        (
+            chunk_x.div_euclid(self.chunks_x()),
+            chunk_z.div_euclid(self.chunks_z()),
+        )
+    }
+
+    /// Returns the chunk coordinates of the north-west corner of a plot.
+    pub fn first_chunk(&self, plot_x: i32, plot_z: i32) -> (i32, i32) {
+        (plot_x * self.chunks_x(), plot_z * self.chunks_z())
+    }
+
+    /// Returns true if the block at `x` and `z` is owned by the region. This includes the
+    /// road and border surrounding the region, use `in_build_area` to check if a block
+    /// can be modified.
+    pub fn in_bounds(&self, region: &PlotRegion, x: i32, z: i32) -> bool {
+        let (plot_x, plot_z) = self.plot_at(x, z);
+        region.contains(plot_x, plot_z)
+    }
+
+    pub fn chunk_in_bounds(&self, region: &PlotRegion, chunk_x: i32, chunk_z: i32) -> bool {
+        let (plot_x, plot_z) = self.plot_at_chunk(chunk_x, chunk_z);
+        region.contains(plot_x, plot_z)
+    }
+
+    /// Returns true if `y` is within the build height of a plot.
+    pub fn in_build_height(&self, y: i32) -> bool {
+        y >= 0 && y < self.height
+    }
+
+    /// The number of road blocks on the low (north and west) side of a plot
+    fn road_low(&self) -> i32 {
+        self.road_width / 2
+    }
+
+    /// The number of road blocks on the high (south and east) side of a plot
+    fn road_high(&self) -> i32 {
+        self.road_width - self.road_low()
+    }
+
+    /// Returns the first and last local coordinate inside of the border along an axis
+    /// of length `size`.
+    fn inner_range(&self, size: i32) -> (i32, i32) {
+        (self.road_low() + 1, size - self.road_high() - 2)
+    }
+
+    /// Returns true if the block at `x` and `z` is part of a road.
+    pub fn is_road(&self, x: i32, z: i32) -> bool {
+        let local_x = x.rem_euclid(self.width);
+        let local_z = z.rem_euclid(self.depth);
+        local_x < self.road_low()
+            || local_x >= self.width - self.road_high()
+            || local_z < self.road_low()
+            || local_z >= self.depth - self.road_high()
+    }
+
+    /// Returns true if the block at `x` and `z` is part of the border surrounding a plot.
+    pub fn is_border(&self, x: i32, z: i32) -> bool {
+        !self.is_road(x, z) && !self.is_build_area(x, z)
+    }
+
+    /// Returns true if the block at `x` and `z` is inside the border of any plot.
+    fn is_build_area(&self, x: i32, z: i32) -> bool {
+        let (plot_x, plot_z) = self.plot_at(x, z);
+        self.in_build_area(&PlotRegion::single(plot_x, plot_z), x, z)
+    }
+
+    /// Returns the first and last block coordinates inside of the border of a region
+    pub fn build_area(&self, region: &PlotRegion) -> ((i32, i32), (i32, i32)) {
+        let (min_x, max_x) = self.inner_range(region.size_x() * self.width);
+        let (min_z, max_z) = self.inner_range(region.size_z() * self.depth);
+        let offset_x = region.min_x * self.width;
+        let offset_z = region.min_z * self.depth;
+        (
+            (offset_x + min_x, offset_z + min_z),
+            (offset_x + max_x, offset_z + max_z),
+        )
+    }
+
+    /// Returns true if players are allowed to modify the block at `x` and `z` while
+    /// they are in the region.
+    pub fn in_build_area(&self, region: &PlotRegion, x: i32, z: i32) -> bool {
+        let ((min_x, min_z), (max_x, max_z)) = self.build_area(region);
+        x >= min_x && x <= max_x && z >= min_z && z <= max_z
+    }
+
+    /// Returns the x and z coordinates of every border block of a region.
+    pub fn border_positions(&self, region: &PlotRegion) -> Vec<(i32, i32)> {
+        let ((min_x, min_z), (max_x, max_z)) = self.build_area(region);
+        let (min_x, max_x) = (min_x - 1, max_x + 1);
+        let (min_z, max_z) = (min_z - 1, max_z + 1);
+        let mut positions = Vec::new();
+        for x in min_x..=max_x {
+            positions.push((x, min_z));
+            positions.push((x, max_z));
+        }
+        for z in min_z + 1..max_z {
+            positions.push((min_x, z));
+            positions.push((max_x, z));
+        }
+        positions
+    }
+
+    /// Returns the x and z coordinates of the roads and borders between the plots of a
+    /// merged region. These are part of the build area of the region.
+    pub fn inner_road_positions(&self, region: &PlotRegion) -> Vec<(i32, i32)> {
+        let ((min_x, min_z), (max_x, max_z)) = self.build_area(region);
+        let mut positions = Vec::new();
+        for x in min_x..=max_x {
+            for z in min_z..=max_z {
+                if !self.is_build_area(x, z) {
+                    positions.push((x, z));
+                }
+            }
+        }
+        positions
+    }
+
+    /// Returns the index of a chunk in the region's chunk storage, or `None` if the chunk
+    /// does not belong to the region. The chunks of each plot are stored one after another.
+    pub fn chunk_index(&self, region: &PlotRegion, chunk_x: i32, chunk_z: i32) -> Option<usize> {
+        let (plot_x, plot_z) = self.plot_at_chunk(chunk_x, chunk_z);
+        if !region.contains(plot_x, plot_z) {
+            return None;
+        }
+        let (first_x, first_z) = self.first_chunk(plot_x, plot_z);
+        let local_index = (chunk_x - first_x) * self.chunks_z() + (chunk_z - first_z);
+        Some(region.plot_index(plot_x, plot_z) * self.chunk_count() + local_index as usize)
+    }
+
+    /// Returns the chunk coordinates of the chunk stored at `index` in a plot's chunk storage.
+    pub fn chunk_at_index(&self, plot_x: i32, plot_z: i32, index: usize) -> (i32, i32) {
+        let (first_x, first_z) = self.first_chunk(plot_x, plot_z);
+        (
+            first_x + index as i32 / self.chunks_z(),
+            first_z + index as i32 % self.chunks_z(),
+        )
+    }
+
+    /// Returns the center of a region
+    pub fn region_center(&self, region: &PlotRegion) -> (f64, f64) {
+        let ((min_x, min_z), (max_x, max_z)) = self.build_area(region);
+        (
+            (min_x + max_x + 1) as f64 / 2.0,
+            (min_z + max_z + 1) as f64 / 2.0,
+        )
+    }
+
+    pub fn center(&self, plot_x: i32, plot_z: i32) -> (f64, f64) {
+        (
+            (plot_x * self.width) as f64 + self.width as f64 / 2.0,
+            (plot_z * self.depth) as f64 + self.depth as f64 / 2.0,
+        )
+    }
+
+    /// The y coordinate of the wall on top of the plot border
+    pub fn wall_y(&self) -> i32 {
+        self.floor_layers
+    }
+
+    /// The number of layers that have to be generated for a new plot
+    pub fn generated_layers(&self) -> i32 {
+        (self.wall_y() + 1).min(self.height)
+    }
+
+    /// Returns the block state id that a newly generated, unclaimed plot has at the given position.
+    pub fn generated_block(&self, x: i32, y: i32, z: i32) -> u32 {
+        if y < self.floor_layers {
+            if self.is_road(x, z) {
+                self.road_block
+            } else if self.is_border(x, z) {
+                self.border_block
+            } else {
+                self.floor_block
+            }
+        } else if y == self.wall_y() && self.is_border(x, z) {
+            self.wall_block
+        } else {
+            0
+        }
+    }
+}
+
+impl Default for PlotGeometry {
+    fn default() -> PlotGeometry {
+        PlotGeometry {
+            width: 256,
+            depth: 256,
+            height: 256,
+            floor_layers: 8,
+            floor_block: Block::Sandstone {}.get_id(),
+            road_width: 0,
+            road_block: Block::StoneBricks {}.get_id(),
+            border_block: Block::StoneBricks {}.get_id(),
+            wall_block: Block::SmoothStoneSlab {}.get_id(),
+            claimed_wall_block: Block::QuartzSlab {}.get_id(),
+        }
+    }
+}
+
+#[test]
+fn plot_geometry_coordinates() {
+    let geometry = PlotGeometry {
+        width: 128,
+        depth: 512,
+        ..Default::default()
+    };
+    assert!(geometry.validate().is_ok());
+    assert_eq!(geometry.plot_at(127, 511), (0, 0));
+    assert_eq!(geometry.plot_at(128, 512), (1, 1));
+    assert_eq!(geometry.plot_at(-1, -1), (-1, -1));
+    assert_eq!(geometry.plot_at_chunk(-1, 32), (-1, 1));
+    assert_eq!(geometry.chunk_count(), 8 * 32);
+    assert_eq!(
+        geometry.chunk_index(&PlotRegion::single(1, 0), 8, 0),
+        Some(0)
+    );
+    assert_eq!(geometry.chunk_index(&PlotRegion::single(1, 0), 7, 0), None);
+    assert_eq!(geometry.chunk_at_index(-1, -1, 33), (-7, -31));
+    assert_eq!(
+        geometry.chunk_index(&PlotRegion::single(-1, -1), -7, -31),
+        Some(33)
+    );
+    assert_eq!(geometry.center(-1, 0), (-64.0, 256.0));
+}
+
+#[test]
+fn plot_geometry_roads() {
+    let geometry = PlotGeometry {
+        width: 32,
+        depth: 32,
+        road_width: 5,
+        ..Default::default()
+    };
+    assert!(geometry.validate().is_ok());
+    // Two road blocks on the low side, three on the high side
+    assert!(geometry.is_road(1, 10));
+    assert!(geometry.is_border(2, 10));
+    let plot = PlotRegion::single(0, 0);
+    assert!(geometry.in_build_area(&plot, 3, 10));
+    assert!(geometry.in_build_area(&plot, 27, 27));
+    assert!(geometry.is_border(28, 10));
+    assert!(geometry.is_road(29, 10));
+    assert!(geometry.is_road(-1, 10));
+    assert!(!geometry.in_build_area(&plot, 35, 10));
+    assert!(geometry.in_build_area(&PlotRegion::single(-1, 0), -5, 10));
+
+    let plot = PlotRegion::single(-1, 0);
+    let border = geometry.border_positions(&plot);
+    assert_eq!(border.len(), 4 * 26);
+    assert!(border.iter().all(|&(x, z)| geometry.is_border(x, z)));
+    assert!(border.iter().all(|&(x, z)| geometry.in_bounds(&plot, x, z)));
+}
+
+#[test]
+fn plot_geometry_merged_regions() {
+    let geometry = PlotGeometry {
+        width: 32,
+        depth: 32,
+        road_width: 5,
+        ..Default::default()
+    };
+    let region = PlotRegion::single(0, 0).union(&PlotRegion::single(1, 0));
+    assert_eq!(region.plots(), vec![(0, 0), (1, 0)]);
+    // The road between the two plots is buildable, the outer road isn't
+    assert!(geometry.in_build_area(&region, 30, 10));
+    assert!(geometry.in_build_area(&region, 59, 10));
+    assert!(!geometry.in_build_area(&region, 60, 10));
+    assert!(!geometry.in_build_area(&region, 30, 2));
+    assert_eq!(geometry.chunk_index(&region, 2, 0), Some(4));
+    assert_eq!(geometry.chunk_index(&region, 4, 0), None);
+    assert_eq!(geometry.region_center(&region), (31.5, 15.5));
+    // Two borders and the road between them
+    assert_eq!(geometry.inner_road_positions(&region).len(), 7 * 25);
+    assert_eq!(geometry.border_positions(&region).len(), 2 * 59 + 2 * 25);
+}
+
+#[test]
+fn plot_geometry_validation() {
+    let geometry = |width, height, floor_layers, road_width| PlotGeometry {
+        width,
+        height,
+        floor_layers,
+        road_width,
+        ..Default::default()
+    };
+    assert!(geometry(100, 256, 8, 0).validate().is_err());
+    assert!(geometry(256, 300, 8, 0).validate().is_err());
+    assert!(geometry(256, 256, 256, 0).validate().is_err());
+    assert!(geometry(16, 256, 8, 14).validate().is_err());
+    assert!(geometry(512, 256, 8, 7).validate().is_ok());
+}
@@ -1,26 +1,75 @@
+mod clipboards;
 pub mod commands;
 pub mod database;
+pub mod geometry;
 mod packet_handlers;
+mod profiler;
+mod schematic;
 pub mod worldedit;
 
-use crate::blocks::{Block, BlockEntity, BlockPos};
+use crate::blocks::{Block, BlockEntity, BlockPos, WireAlgorithm};
 use crate::chat::ChatComponent;
+use crate::config::CONFIG;
+use crate::metrics::{self, PlotMetrics, PlotStats};
 use crate::network::packets::clientbound::*;
 use crate::network::packets::SlotData;
+use crate::permissions;
 use crate::player::{Gamemode, Player};
 use crate::server::{BroadcastMessage, Message, PrivMessage};
 use crate::world::storage::{Chunk, ChunkData};
 use crate::world::{TickEntry, TickPriority, World};
 use bus::BusReader;
-use log::warn;
+use geometry::{PlotRegion, PLOT_GEOMETRY};
+use log::{error, warn};
+use profiler::Profiler;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::error::Error;
 use std::fs::{self, OpenOptions};
 use std::io::Write;
 use std::path::Path;
 use std::sync::mpsc::{Receiver, Sender};
 use std::thread;
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
+
+/// The highest rtps a plot can be set to. Plots can run faster with `/rtps unlimited`.
+pub const MAX_RTPS: u32 = 35000;
+/// How long plots running at unlimited or adaptive rtps tick before handling packets again
+const TICK_BATCH: Duration = Duration::from_millis(50);
+/// The share of time plots with adaptive rtps try to spend ticking. The rest is left for
+/// handling packets.
+const ADAPTIVE_TARGET_LOAD: f64 = 0.8;
+
+/// How the rtps of a plot is chosen
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TpsMode {
+    /// The plot ticks at the rtps it is set to
+    Fixed,
+    /// The plot ticks as fast as it can, handling packets between batches of ticks
+    Unlimited,
+    /// The rtps is adjusted every second to the highest rtps the plot can keep up with,
+    /// but never above `max`
+    Adaptive { max: u32 },
+}
+
+/// A `/rwarp` that is still running. It ticks in batches so the plot keeps handling
+/// packets while it runs.
+struct Warp {
+    /// The uuid of the player that started the warp
+    player: u128,
+    ticks: u32,
+    max_ticks: u32,
+    /// Only send players the final state of the changed blocks
+    final_state_only: bool,
+    start_time: Instant,
+}
+
+/// The uuid of the boss bar that shows the performance of the plot
+const PERFORMANCE_BAR_UUID: u128 = 0x4d43_4850_5253_0000_0000_0000_7274_7073;
+const BOSS_BAR_RED: i32 = 2;
+const BOSS_BAR_GREEN: i32 = 3;
+const BOSS_BAR_YELLOW: i32 = 4;
+const BOSS_BAR_WHITE: i32 = 6;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PlotData {
@@ -30,6 +79,42 @@ pub struct PlotData {
     pub pending_ticks: Vec<TickEntry>,
 }
 
+/// Settings that the owner of a plot can change with `/p set`. These are stored in the
+/// database so the server thread can find plots that need to be kept loaded.
+#[derive(Debug, Clone)]
+pub struct PlotSettings {
+    /// Keep the plot loaded even when there are no players in it
+    pub keep_loaded: bool,
+    /// The rtps the plot is set to when it loads
+    pub default_rtps: Option<u32>,
+    /// Stop ticking the plot when there are no players in it
+    pub auto_pause: bool,
+    /// Allow players other than the owner to build in the plot. This includes changing
+    /// repeater delays, comparator modes and wire shapes. Everyone can use levers and
+    /// buttons either way.
+    pub public_build: bool,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Sent to players when they enter the plot
+    pub greeting: Option<String>,
+    pub wire_algorithm: WireAlgorithm,
+}
+
+impl Default for PlotSettings {
+    fn default() -> PlotSettings {
+        PlotSettings {
+            keep_loaded: false,
+            default_rtps: None,
+            auto_pause: true,
+            public_build: true,
+            name: None,
+            description: None,
+            greeting: None,
+            wire_algorithm: WireAlgorithm::default(),
+        }
+    }
+}
+
 pub struct Plot {
     message_receiver: BusReader<BroadcastMessage>,
     message_sender: Sender<Message>,
@@ -37,6 +122,7 @@ pub struct Plot {
     // It's kinda dumb making this pub but it would be too much work to do it differently.
     pub players: Vec<Player>,
     tps: u32,
+    tps_mode: TpsMode,
     to_be_ticked: Vec<TickEntry>,
     last_update_time: SystemTime,
     lag_time: Duration,
@@ -45,18 +131,35 @@ pub struct Plot {
     running: bool,
     x: i32,
     z: i32,
+    /// The plots this plot is made up of. This only differs from the plot at `x` and `z`
+    /// when plots are merged.
+    region: PlotRegion,
     show_redstone: bool,
     always_running: bool,
+    settings: PlotSettings,
+    /// The uuid of the owner of the first plot, or `None` if it couldn't be loaded from the
+    /// database. It is loaded with the plot and refreshed when a plot of the region is
+    /// claimed.
+    owner: Option<Option<u128>>,
+    /// Whether players or worldedit changed any blocks since the plot was last saved. Changes
+    /// made by redstone don't count.
+    modified: bool,
+    stats: PlotStats,
+    /// The block update profiler started with `/rprofile`. It is kept after it stops so
+    /// the report and heatmap can still be shown.
+    profiler: Option<Profiler>,
+    /// The `/rwarp` in progress. Normal ticking is paused while it runs.
+    warp: Option<Warp>,
     chunks: Vec<Chunk>,
 }
 
 impl World for Plot {
     /// Sets a block in storage without sending a block change packet to the client. Returns true if a block was changed.
     fn set_block_raw(&mut self, pos: BlockPos, block: u32) -> bool {
-        let chunk_index = self.get_chunk_index_for_block(pos.x, pos.z);
-        if chunk_index >= 256 || pos.y > 256 {
-            return false;
-        }
+        let chunk_index = match self.get_chunk_index_for_block(pos.x, pos.z) {
+            Some(chunk_index) if PLOT_GEOMETRY.in_build_height(pos.y) => chunk_index,
+            _ => return false,
+        };
         let chunk = &mut self.chunks[chunk_index];
         chunk.set_block_raw(
             (pos.x & 0xF) as u32,
@@ -70,11 +173,14 @@ impl World for Plot {
     /// If the block was changed it will be sent to all players
     /// and the function will return true.
     fn set_block(&mut self, pos: BlockPos, block: Block) -> bool {
-        let block_id = Block::get_id(block);
-        let chunk_index = self.get_chunk_index_for_block(pos.x, pos.z);
-        if chunk_index >= 256 || pos.y > 256 {
-            return false;
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record_set_block(pos, block);
         }
+        let block_id = Block::get_id(block);
+        let chunk_index = match self.get_chunk_index_for_block(pos.x, pos.z) {
+            Some(chunk_index) if PLOT_GEOMETRY.in_build_height(pos.y) => chunk_index,
+            _ => return false,
+        };
         let chunk = &mut self.chunks[chunk_index];
         chunk.set_block(
             (pos.x & 0xF) as u32,
@@ -86,10 +192,10 @@ impl World for Plot {
 
     /// Returns the block state id of the block at `pos`
     fn get_block_raw(&self, pos: BlockPos) -> u32 {
-        let chunk_index = self.get_chunk_index_for_block(pos.x, pos.z);
-        if chunk_index >= 256 {
-            return 0;
-        }
+        let chunk_index = match self.get_chunk_index_for_block(pos.x, pos.z) {
+            Some(chunk_index) if PLOT_GEOMETRY.in_build_height(pos.y) => chunk_index,
+            _ => return 0,
+        };
         let chunk = &self.chunks[chunk_index];
         chunk.get_block((pos.x & 0xF) as u32, pos.y as u32, (pos.z & 0xF) as u32)
     }
@@ -99,28 +205,25 @@ impl World for Plot {
     }
 
     fn delete_block_entity(&mut self, pos: BlockPos) {
-        let chunk_index = self.get_chunk_index_for_block(pos.x, pos.z);
-        if chunk_index >= 256 {
-            return;
-        }
+        let chunk_index = match self.get_chunk_index_for_block(pos.x, pos.z) {
+            Some(chunk_index) => chunk_index,
+            None => return,
+        };
         let chunk = &mut self.chunks[chunk_index];
         chunk.delete_block_entity(BlockPos::new(pos.x & 0xF, pos.y, pos.z & 0xF))
     }
 
     fn get_block_entity(&self, pos: BlockPos) -> Option<&BlockEntity> {
-        let chunk_index = self.get_chunk_index_for_block(pos.x, pos.z);
-        if chunk_index >= 256 {
-            return None;
-        }
+        let chunk_index = self.get_chunk_index_for_block(pos.x, pos.z)?;
         let chunk = &self.chunks[chunk_index];
         chunk.get_block_entity(BlockPos::new(pos.x & 0xF, pos.y, pos.z & 0xF))
     }
 
     fn set_block_entity(&mut self, pos: BlockPos, block_entity: BlockEntity) {
-        let chunk_index = self.get_chunk_index_for_block(pos.x, pos.z);
-        if chunk_index >= 256 {
-            return;
-        }
+        let chunk_index = match self.get_chunk_index_for_block(pos.x, pos.z) {
+            Some(chunk_index) => chunk_index,
+            None => return,
+        };
         if let Some(nbt) = block_entity.to_nbt(pos) {
             let block_entity_data = C09BlockEntityData {
                 x: pos.x,
@@ -140,11 +243,12 @@ impl World for Plot {
     }
 
     fn get_chunk(&self, x: i32, z: i32) -> Option<&Chunk> {
-        self.chunks.get(self.get_chunk_index_for_chunk(x, z))
+        let chunk_idx = self.get_chunk_index_for_chunk(x, z)?;
+        self.chunks.get(chunk_idx)
     }
 
     fn get_chunk_mut(&mut self, x: i32, z: i32) -> Option<&mut Chunk> {
-        let chunk_idx = self.get_chunk_index_for_chunk(x, z);
+        let chunk_idx = self.get_chunk_index_for_chunk(x, z)?;
         self.chunks.get_mut(chunk_idx)
     }
 
@@ -154,7 +258,11 @@ impl World for Plot {
         }
         while self.to_be_ticked.first().map(|e| e.ticks_left).unwrap_or(1) == 0 {
             let entry = self.to_be_ticked.remove(0);
-            self.get_block(entry.pos).tick(self, entry.pos);
+            let block = self.get_block(entry.pos);
+            if let Some(profiler) = &mut self.profiler {
+                profiler.record_tick(entry.pos, block);
+            }
+            block.tick(self, entry.pos);
         }
     }
 
@@ -171,19 +279,25 @@ impl World for Plot {
     fn pending_tick_at(&mut self, pos: BlockPos) -> bool {
         self.to_be_ticked.iter().any(|e| e.pos == pos)
     }
+
+    fn on_block_update(&mut self, pos: BlockPos, block: Block) {
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record_update(pos, block);
+        }
+    }
+
+    fn wire_algorithm(&self) -> WireAlgorithm {
+        self.settings.wire_algorithm
+    }
 }
 
 impl Plot {
-    fn get_chunk_index_for_chunk(&self, chunk_x: i32, chunk_z: i32) -> usize {
-        let local_x = chunk_x - self.x * 16;
-        let local_z = chunk_z - self.z * 16;
-        (local_x * 16 + local_z).abs() as usize
+    fn get_chunk_index_for_chunk(&self, chunk_x: i32, chunk_z: i32) -> Option<usize> {
+        PLOT_GEOMETRY.chunk_index(&self.region, chunk_x, chunk_z)
     }
 
-    fn get_chunk_index_for_block(&self, block_x: i32, block_z: i32) -> usize {
-        let chunk_x = (block_x - (self.x << 8)) >> 4;
-        let chunk_z = (block_z - (self.z << 8)) >> 4;
-        ((chunk_x << 4) + chunk_z).abs() as usize
+    fn get_chunk_index_for_block(&self, block_x: i32, block_z: i32) -> Option<usize> {
+        self.get_chunk_index_for_chunk(block_x >> 4, block_z >> 4)
     }
 
     /// Send a block change to all connected players
@@ -222,6 +336,9 @@ impl Plot {
 
     fn enter_plot(&mut self, mut player: Player) {
         self.save();
+        if let Some(greeting) = &self.settings.greeting {
+            player.send_system_message(greeting);
+        }
         let spawn_player = C04SpawnPlayer {
             entity_id: player.entity_id as i32,
             uuid: player.uuid,
@@ -309,9 +426,179 @@ impl Plot {
             }
         }
 
-        player.send_system_message(&format!("Entering plot ({}, {})", self.x, self.z));
+        player.send_system_message(&format!("Entering plot ({})", self.region));
+        if let Err(err) = database::touch_plots_visited(self.region) {
+            error!(
+                "Error updating the last visited time of plot {},{}: {}",
+                self.x, self.z, err
+            );
+        }
         self.players.push(player);
         self.update_view_pos_for_player(self.players.len() - 1, true);
+        if self.players[self.players.len() - 1].show_performance {
+            self.show_performance_bar(self.players.len() - 1);
+        }
+    }
+
+    /// Returns the title in json format, the health and the color of the boss bar that
+    /// shows the rtps and mspt of the plot
+    fn performance_bar(&self) -> (String, f32, i32) {
+        let stats = match &self.stats.last {
+            Some(stats) => stats,
+            None => {
+                return (
+                    json!({ "text": "Measuring..." }).to_string(),
+                    0.0,
+                    BOSS_BAR_WHITE,
+                )
+            }
+        };
+        if self.tps_mode == TpsMode::Unlimited {
+            let title = json!({
+                "text": format!("{:.0} rtps (unlimited) | {:.3} mspt", stats.rtps, stats.mspt)
+            });
+            return (title.to_string(), 1.0, BOSS_BAR_GREEN);
+        }
+        if self.tps == 0 {
+            let title = json!({ "text": format!("Paused | {:.3} mspt", stats.mspt) });
+            return (title.to_string(), 0.0, BOSS_BAR_WHITE);
+        }
+        let ratio = (stats.rtps / self.tps as f64).min(1.0);
+        let color = if ratio >= 0.95 {
+            BOSS_BAR_GREEN
+        } else if ratio >= 0.5 {
+            BOSS_BAR_YELLOW
+        } else {
+            BOSS_BAR_RED
+        };
+        let title = json!({
+            "text": format!("{:.0} / {} rtps | {:.3} mspt", stats.rtps, self.tps, stats.mspt)
+        });
+        (title.to_string(), ratio as f32, color)
+    }
+
+    fn show_performance_bar(&mut self, player: usize) {
+        let (title, health, color) = self.performance_bar();
+        let add_bar = C0CBossBar::Add {
+            uuid: PERFORMANCE_BAR_UUID,
+            title,
+            health,
+            color,
+            division: 0,
+            flags: 0,
+        }
+        .encode();
+        self.players[player].client.send_packet(&add_bar);
+    }
+
+    fn hide_performance_bar(player: &mut Player) {
+        let remove_bar = C0CBossBar::Remove(PERFORMANCE_BAR_UUID).encode();
+        player.client.send_packet(&remove_bar);
+    }
+
+    /// Shows or hides the performance boss bar for a player
+    pub fn toggle_performance_bar(&mut self, player: usize) {
+        let show = !self.players[player].show_performance;
+        self.players[player].show_performance = show;
+        if show {
+            self.show_performance_bar(player);
+        } else {
+            Plot::hide_performance_bar(&mut self.players[player]);
+        }
+    }
+
+    fn update_performance_bars(&mut self) {
+        if !self.players.iter().any(|p| p.show_performance) {
+            return;
+        }
+        let (title, health, color) = self.performance_bar();
+        let update_title = C0CBossBar::UpdateTitle(PERFORMANCE_BAR_UUID, title).encode();
+        let update_health = C0CBossBar::UpdateHealth(PERFORMANCE_BAR_UUID, health).encode();
+        let update_style = C0CBossBar::UpdateStyle(PERFORMANCE_BAR_UUID, color, 0).encode();
+        for player in &mut self.players {
+            if player.show_performance {
+                player.client.send_packet(&update_title);
+                player.client.send_packet(&update_health);
+                player.client.send_packet(&update_style);
+            }
+        }
+    }
+
+    /// Sends the report to everyone in the plot once the profiler stops on its own
+    fn update_profiler(&mut self) {
+        let report = match &mut self.profiler {
+            Some(profiler) => {
+                if !profiler.update() {
+                    return;
+                }
+                profiler.report()
+            }
+            None => return,
+        };
+        for player in &mut self.players {
+            player.send_system_message("Block update profiling finished.");
+            for line in &report {
+                player.send_system_message(line);
+            }
+        }
+    }
+
+    /// Recolors the profiled blocks for the player to show how active they were. The
+    /// blocks are only changed on the client.
+    fn show_heatmap(&mut self, player: usize) {
+        let profiler = match &mut self.profiler {
+            Some(profiler) => profiler,
+            None => return,
+        };
+        for (pos, block) in profiler.heatmap() {
+            let block_change = C0BBlockChange {
+                block_id: block.get_id() as i32,
+                x: pos.x,
+                y: pos.y,
+                z: pos.z,
+            }
+            .encode();
+            self.players[player].client.send_packet(&block_change);
+        }
+        let uuid = self.players[player].uuid;
+        if !profiler.heatmap_viewers.contains(&uuid) {
+            profiler.heatmap_viewers.push(uuid);
+        }
+    }
+
+    /// Sends the real blocks back to the player
+    fn hide_heatmap(&mut self, player: usize) {
+        let heatmap = match &mut self.profiler {
+            Some(profiler) => {
+                let uuid = self.players[player].uuid;
+                profiler.heatmap_viewers.retain(|viewer| *viewer != uuid);
+                profiler.heatmap()
+            }
+            None => return,
+        };
+        for (pos, _) in heatmap {
+            let block_change = C0BBlockChange {
+                block_id: self.get_block_raw(pos) as i32,
+                x: pos.x,
+                y: pos.y,
+                z: pos.z,
+            }
+            .encode();
+            self.players[player].client.send_packet(&block_change);
+        }
+    }
+
+    /// Hides the heatmap from everyone that is seeing it
+    fn hide_heatmaps(&mut self) {
+        let viewers = match &self.profiler {
+            Some(profiler) => profiler.heatmap_viewers.clone(),
+            None => return,
+        };
+        for uuid in viewers {
+            if let Some(player) = self.players.iter().position(|p| p.uuid == uuid) {
+                self.hide_heatmap(player);
+            }
+        }
     }
 
     fn get_chunk_distance(x1: i32, z1: i32, x2: i32, z2: i32) -> u32 {
@@ -332,13 +619,13 @@ impl Plot {
             let unload_chunk = C1CUnloadChunk { chunk_x, chunk_z }.encode();
             self.players[player_idx].client.send_packet(&unload_chunk);
         } else if !was_loaded && should_be_loaded {
-            if !Plot::chunk_in_plot_bounds(self.x, self.z, chunk_x, chunk_z) {
+            if !Plot::chunk_in_plot_bounds(&self.region, chunk_x, chunk_z) {
                 self.players[player_idx]
                     .client
                     .send_packet(&Chunk::empty(chunk_x, chunk_z).encode_packet(true))
             } else {
-                let chunk_data = self.chunks[self.get_chunk_index_for_chunk(chunk_x, chunk_z)]
-                    .encode_packet(true);
+                let chunk_index = self.get_chunk_index_for_chunk(chunk_x, chunk_z).unwrap();
+                let chunk_data = self.chunks[chunk_index].encode_packet(true);
                 self.players[player_idx].client.send_packet(&chunk_data);
             }
         }
@@ -399,19 +686,23 @@ impl Plot {
 
     fn leave_plot(&mut self, player_index: usize) -> Player {
         let mut player = self.players.remove(player_index);
+        if player.show_performance {
+            Plot::hide_performance_bar(&mut player);
+        }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.heatmap_viewers.retain(|uuid| *uuid != player.uuid);
+        }
         let mut entity_ids = Vec::new();
         for player in &self.players {
             entity_ids.push(player.entity_id as i32);
         }
         let destroy_other_entities = C36DestroyEntities { entity_ids }.encode();
         player.client.send_packet(&destroy_other_entities);
-        let chunk_offset_x = self.x << 4;
-        let chunk_offset_z = self.z << 4;
         for chunk in &self.chunks {
             player.client.send_packet(
                 &C1CUnloadChunk {
-                    chunk_x: chunk_offset_x + chunk.x,
-                    chunk_z: chunk_offset_z + chunk.z,
+                    chunk_x: chunk.x,
+                    chunk_z: chunk.z,
                 }
                 .encode(),
             );
@@ -420,30 +711,239 @@ impl Plot {
         player
     }
 
-    fn chunk_in_plot_bounds(plot_x: i32, plot_z: i32, chunk_x: i32, chunk_z: i32) -> bool {
-        chunk_x >= plot_x * 16
-            && chunk_x < (plot_x + 1) * 16
-            && chunk_z >= plot_z * 16
-            && chunk_z < (plot_z + 1) * 16
+    fn chunk_in_plot_bounds(region: &PlotRegion, chunk_x: i32, chunk_z: i32) -> bool {
+        PLOT_GEOMETRY.chunk_in_bounds(region, chunk_x, chunk_z)
     }
 
-    fn in_plot_bounds(plot_x: i32, plot_z: i32, x: i32, z: i32) -> bool {
-        x >= plot_x * 256 && x < (plot_x + 1) * 256 && z >= plot_z * 256 && z < (plot_z + 1) * 256
+    /// Returns true if the block at `x` and `z` can be modified by players in the plot.
+    /// Roads and plot borders are outside of the plot bounds.
+    fn in_plot_bounds(region: &PlotRegion, x: i32, z: i32) -> bool {
+        PLOT_GEOMETRY.in_build_area(region, x, z)
     }
 
-    pub fn claim_plot(&mut self, plot_x: i32, plot_z: i32, player: usize) {
+    /// Sets the redstone ticks per second of the plot
+    fn set_tps(&mut self, tps: u32) {
+        self.tps_mode = TpsMode::Fixed;
+        self.apply_tps(tps);
+    }
+
+    /// Makes the plot tick as fast as it can or adapt its rtps to the circuit
+    fn set_tps_mode(&mut self, mode: TpsMode) {
+        self.tps_mode = mode;
+        match mode {
+            TpsMode::Fixed => {}
+            TpsMode::Unlimited => {
+                self.sleep_time = Duration::from_millis(1);
+                self.lag_time = Duration::from_millis(0);
+            }
+            // Start low and let the rtps climb to what the circuit can handle
+            TpsMode::Adaptive { max } => self.apply_tps(self.tps.clamp(10, max)),
+        }
+    }
+
+    /// Changes the rtps of an adaptive plot based on how much time the plot spent ticking
+    /// during the last second
+    fn adapt_tps(&mut self, max: u32) {
+        let tps = match &self.stats.last {
+            Some(stats) => adapted_tps(self.tps, max, stats),
+            None => return,
+        };
+        if tps != self.tps {
+            // Ticks the plot is behind on still run at the new rtps
+            let lag_time = self.lag_time;
+            self.apply_tps(tps);
+            self.lag_time = lag_time;
+        }
+    }
+
+    fn apply_tps(&mut self, tps: u32) {
+        if tps > 10 {
+            self.sleep_time = Duration::from_micros(1_000_000 / tps as u64);
+        } else {
+            self.sleep_time = Duration::from_millis(2);
+        }
+        self.lag_time = Duration::from_millis(0);
+        self.tps = tps;
+    }
+
+    /// Returns true if the player is allowed to build in this plot. Anyone can build in
+    /// unclaimed plots and plots with public build enabled.
+    pub fn can_build(&self, player: usize) -> bool {
+        if self.settings.public_build {
+            return true;
+        }
+        match self.owner {
+            Some(Some(owner)) => owner == self.players[player].uuid,
+            Some(None) => true,
+            None => false,
+        }
+    }
+
+    /// Loads the owner of the plot from the database
+    fn load_owner(&mut self) {
+        self.owner = match database::get_plot_owner(self.x, self.z) {
+            Ok(owner) => Some(owner.and_then(|owner| u128::from_str_radix(&owner, 16).ok())),
+            Err(err) => {
+                error!(
+                    "Error getting the owner of plot {},{}: {}",
+                    self.x, self.z, err
+                );
+                None
+            }
+        };
+    }
+
+    /// Returns true if the player has the permission node. Players without it are told so.
+    pub fn check_permission(&mut self, player: usize, node: &str) -> bool {
+        if permissions::has_permission(self.players[player].uuid, node) {
+            true
+        } else {
+            self.players[player].send_error_message("You don't have permission to do that!");
+            false
+        }
+    }
+
+    /// Returns true if players in this plot are allowed to modify the block at `pos`.
+    pub fn in_build_area(&self, pos: BlockPos) -> bool {
+        Plot::in_plot_bounds(&self.region, pos.x, pos.z)
+    }
+
+    /// Returns the position in the build area that is closest to `pos`
+    pub fn clamp_to_build_area(&self, pos: BlockPos) -> BlockPos {
+        let ((min_x, min_z), (max_x, max_z)) = PLOT_GEOMETRY.build_area(&self.region);
+        pos.max(BlockPos::new(min_x, 0, min_z)).min(BlockPos::new(
+            max_x,
+            PLOT_GEOMETRY.height - 1,
+            max_z,
+        ))
+    }
+
+    /// Shrinks the box between `first_pos` and `second_pos` to the part of it that players
+    /// can build in. Returns the corners of that part, or `None` if there isn't any.
+    pub fn clip_to_build_area(
+        &self,
+        first_pos: BlockPos,
+        second_pos: BlockPos,
+    ) -> Option<(BlockPos, BlockPos)> {
+        let ((min_x, min_z), (max_x, max_z)) = PLOT_GEOMETRY.build_area(&self.region);
+        let start = first_pos
+            .min(second_pos)
+            .max(BlockPos::new(min_x, 0, min_z));
+        let end =
+            first_pos
+                .max(second_pos)
+                .min(BlockPos::new(max_x, PLOT_GEOMETRY.height - 1, max_z));
+        if start.x > end.x || start.y > end.y || start.z > end.z {
+            None
+        } else {
+            Some((start, end))
+        }
+    }
+
+    /// Updates the wall on top of the plot border to reflect whether the plot is claimed.
+    /// Blocks that aren't part of the wall are left alone. This only runs when the plot is
+    /// claimed or newly generated, so walls players changed afterwards stay that way.
+    fn update_plot_wall(&mut self) {
+        let y = PLOT_GEOMETRY.wall_y();
+        if !PLOT_GEOMETRY.in_build_height(y) {
+            return;
+        }
+        let claimed = database::is_claimed(self.x, self.z).unwrap_or(false);
+        let (old_wall, new_wall) = if claimed {
+            (PLOT_GEOMETRY.wall_block, PLOT_GEOMETRY.claimed_wall_block)
+        } else {
+            (PLOT_GEOMETRY.claimed_wall_block, PLOT_GEOMETRY.wall_block)
+        };
+        for (x, z) in PLOT_GEOMETRY.border_positions(&self.region) {
+            let pos = BlockPos::new(x, y, z);
+            let block = self.get_block_raw(pos);
+            if block == old_wall || block == 0 {
+                self.set_block(pos, Block::from_id(new_wall));
+            }
+        }
+        if claimed {
+            if let Err(err) = database::set_walls_built(self.region) {
+                error!(
+                    "Error saving the wall of plot {},{}: {}",
+                    self.x, self.z, err
+                );
+            }
+        }
+    }
+
+    /// Replaces the roads and borders between the plots of a merged region with floor so
+    /// the whole region can be built on.
+    fn clear_merged_roads(&mut self) {
+        let wall_y = PLOT_GEOMETRY.wall_y();
+        let walls = [PLOT_GEOMETRY.wall_block, PLOT_GEOMETRY.claimed_wall_block];
+        for (x, z) in PLOT_GEOMETRY.inner_road_positions(&self.region) {
+            for y in 0..PLOT_GEOMETRY.generated_layers() {
+                let pos = BlockPos::new(x, y, z);
+                if y < PLOT_GEOMETRY.floor_layers {
+                    self.set_block(pos, Block::from_id(PLOT_GEOMETRY.floor_block));
+                } else if y == wall_y && walls.contains(&self.get_block_raw(pos)) {
+                    self.set_block(pos, Block::Air {});
+                }
+            }
+        }
+    }
+
+    /// Returns the number of blocks built on the roads and borders between the plots of a
+    /// merged region, which would be lost or left on the roads when the region is unmerged.
+    pub fn merged_road_builds(&self) -> usize {
+        let mut builds = 0;
+        for (x, z) in PLOT_GEOMETRY.inner_road_positions(&self.region) {
+            for y in 0..PLOT_GEOMETRY.height {
+                let block = self.get_block_raw(BlockPos::new(x, y, z));
+                let cleared = if y < PLOT_GEOMETRY.floor_layers {
+                    PLOT_GEOMETRY.floor_block
+                } else {
+                    0
+                };
+                if block != cleared {
+                    builds += 1;
+                }
+            }
+        }
+        builds
+    }
+
+    /// Puts the roads and borders between the plots of a merged region back.
+    pub fn restore_merged_roads(&mut self) {
+        for (x, z) in PLOT_GEOMETRY.inner_road_positions(&self.region) {
+            for y in 0..PLOT_GEOMETRY.generated_layers() {
+                let mut block = PLOT_GEOMETRY.generated_block(x, y, z);
+                // Every plot of a merged region is claimed
+                if block == PLOT_GEOMETRY.wall_block {
+                    block = PLOT_GEOMETRY.claimed_wall_block;
+                }
+                self.set_block(BlockPos::new(x, y, z), Block::from_id(block));
+            }
+        }
+    }
+
+    pub fn claim_plot(&mut self, plot_x: i32, plot_z: i32, player: usize) -> database::Result<()> {
         database::claim_plot(
             plot_x,
             plot_z,
             format!("{:032x}", self.players[player].uuid),
-        );
+        )?;
+        if self.region.contains(plot_x, plot_z) {
+            self.load_owner();
+            self.update_plot_wall();
+        } else {
+            self.message_sender
+                .send(Message::PlotClaimed(plot_x, plot_z))
+                .unwrap();
+        }
         let center = Plot::get_center(plot_x, plot_z);
         self.players[player].teleport(center.0, 64.0, center.1);
         self.players[player].send_system_message(&format!("Claimed plot {},{}", plot_x, plot_z));
+        Ok(())
     }
 
     pub fn get_center(plot_x: i32, plot_z: i32) -> (f64, f64) {
-        (plot_x as f64 * 256.0 + 128.0, plot_z as f64 * 256.0 + 128.0)
+        PLOT_GEOMETRY.center(plot_x, plot_z)
     }
 
     pub fn get_next_plot(plot_x: i32, plot_z: i32) -> (i32, i32) {
@@ -490,7 +990,14 @@ impl Plot {
     }
 
     fn handle_messages(&mut self) {
+        let mut messages = Vec::new();
         while let Ok(message) = self.message_receiver.try_recv() {
+            messages.push(message);
+        }
+        let priv_messages: Vec<PrivMessage> = self.priv_message_receiver.try_iter().collect();
+        self.stats
+            .record_queue_depths(messages.len(), priv_messages.len());
+        for message in messages {
             match message {
                 BroadcastMessage::Chat(sender, message) => {
                     for player in &mut self.players {
@@ -532,6 +1039,12 @@ impl Plot {
                     self.running = false;
                     return;
                 }
+                BroadcastMessage::Save => {
+                    self.save();
+                    for player in &self.players {
+                        player.save();
+                    }
+                }
                 BroadcastMessage::PlayerUpdateGamemode(uuid, gamemode) => {
                     let player_info = C32PlayerInfo::UpdateGamemode(uuid, gamemode).encode();
                     for player in &mut self.players {
@@ -541,7 +1054,7 @@ impl Plot {
             }
         }
         // Handle messages from the private message channel
-        while let Ok(message) = self.priv_message_receiver.try_recv() {
+        for message in priv_messages {
             match message {
                 PrivMessage::PlayerEnterPlot(player) => {
                     self.enter_plot(player);
@@ -552,17 +1065,141 @@ impl Plot {
                     }
                     self.enter_plot(player);
                 }
+                PrivMessage::Unload => {
+                    // Send everyone back to the server so they can enter the reloaded plot
+                    while !self.players.is_empty() {
+                        let player = self.leave_plot(0);
+                        self.message_sender
+                            .send(Message::PlayerLeavePlot(player))
+                            .unwrap();
+                    }
+                    self.always_running = false;
+                    self.running = false;
+                    return;
+                }
+                PrivMessage::ClearMergedRoads => self.clear_merged_roads(),
+                PrivMessage::PlotClaimed => {
+                    self.load_owner();
+                    self.update_plot_wall();
+                }
+                PrivMessage::ConsoleCommand(command, reply) => {
+                    self.handle_console_command(&command, reply)
+                }
+                PrivMessage::KickPlayer(uuid, reason) => {
+                    if let Some(player) = self.players.iter_mut().find(|p| p.uuid == uuid) {
+                        player.kick(json!({ "text": reason }).to_string());
+                        player.client.close_connection();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sends the queued block changes to every player in the plot
+    fn send_block_changes(&mut self) {
+        let mut multi_block_packets = Vec::new();
+        for chunk in &mut self.chunks {
+            multi_block_packets.append(&mut chunk.drain_multi_block());
+        }
+        for packet in multi_block_packets {
+            let encoded = packet.encode();
+            for player in &mut self.players {
+                player.client.send_packet(&encoded);
             }
         }
     }
 
+    /// Runs the next batch of ticks of a `/rwarp` and reports to the player once the
+    /// circuit became idle or the tick limit was reached
+    fn update_warp(&mut self, mut warp: Warp) {
+        let batch_start = Instant::now();
+        while !self.to_be_ticked.is_empty()
+            && warp.ticks < warp.max_ticks
+            && batch_start.elapsed() < TICK_BATCH
+        {
+            self.tick();
+            warp.ticks += 1;
+        }
+        if !self.to_be_ticked.is_empty() && warp.ticks < warp.max_ticks {
+            if !warp.final_state_only {
+                self.send_block_changes();
+            }
+            self.warp = Some(warp);
+            return;
+        }
+
+        if warp.final_state_only {
+            for chunk in &mut self.chunks {
+                chunk.compact_multi_block();
+            }
+        }
+        self.send_block_changes();
+        // Don't catch up on the ticks missed during the warp
+        self.last_update_time = SystemTime::now();
+        let message = if self.to_be_ticked.is_empty() {
+            format!(
+                "The circuit became idle after {} ticks ({:?})",
+                warp.ticks,
+                warp.start_time.elapsed()
+            )
+        } else {
+            format!(
+                "Stopped after {} ticks ({:?}), the circuit is still running",
+                warp.ticks,
+                warp.start_time.elapsed()
+            )
+        };
+        if let Some(player) = self.players.iter_mut().find(|p| p.uuid == warp.player) {
+            player.send_system_message(&message);
+        }
+    }
+
     fn update(&mut self) {
         self.handle_messages();
+        let target_rtps = match self.tps_mode {
+            TpsMode::Unlimited => 0,
+            _ => self.tps,
+        };
+        self.stats.sample_tick_times =
+            CONFIG.metrics_enabled || self.players.iter().any(|p| p.show_performance);
+        let new_stats = self.stats.update(
+            self.x,
+            self.z,
+            target_rtps,
+            self.to_be_ticked.len(),
+            self.players.len(),
+        );
+        if new_stats {
+            if let TpsMode::Adaptive { max } = self.tps_mode {
+                self.adapt_tps(max);
+            }
+            self.update_performance_bars();
+        }
+        self.update_profiler();
 
-        // Only tick if there are players in the plot
         if !self.players.is_empty() {
             self.last_player_time = SystemTime::now();
-            if self.tps != 0 {
+        }
+
+        if let Some(warp) = self.warp.take() {
+            self.update_warp(warp);
+        } else if self.players.is_empty() && self.settings.auto_pause {
+            // Don't count the time the plot was paused as lag
+            self.last_update_time = SystemTime::now();
+        } else {
+            if self.tps_mode == TpsMode::Unlimited {
+                let batch_start = Instant::now();
+                while batch_start.elapsed() < TICK_BATCH {
+                    // There is nothing to do until something schedules a tick
+                    if self.to_be_ticked.is_empty() {
+                        break;
+                    }
+                    let tick_start = Instant::now();
+                    self.tick();
+                    self.stats.record_tick(tick_start.elapsed());
+                }
+                self.last_update_time = SystemTime::now();
+            } else if self.tps != 0 {
                 let dur_per_tick = Duration::from_micros(1_000_000 / self.tps as u64);
                 let elapsed_time = self.last_update_time.elapsed().unwrap();
                 self.lag_time += elapsed_time;
@@ -572,41 +1209,35 @@ impl Plot {
                     .as_micros()
                     .checked_div(dur_per_tick.as_micros())
                     .unwrap_or_default();
-                if ticks > 4000 {
+                // Adaptive plots lower their rtps instead of skipping ticks when they fall
+                // behind, and catch up in batches so packets are still handled
+                let adaptive = matches!(self.tps_mode, TpsMode::Adaptive { .. });
+                if ticks > 4000 && !adaptive {
                     warn!("Is the plot overloaded? Skipping {} ticks.", ticks);
+                    self.stats.record_skipped_ticks(ticks as u64);
                     self.lag_time = Duration::from_secs(0);
                 }
-                // let start_time = Instant::now();
+                let batch_start = Instant::now();
                 while self.lag_time >= dur_per_tick {
+                    if adaptive && batch_start.elapsed() >= TICK_BATCH {
+                        break;
+                    }
+                    let tick_start = Instant::now();
                     self.tick();
+                    self.stats.record_tick(tick_start.elapsed());
                     self.lag_time -= dur_per_tick;
                 }
-                // if ticks > 0 {
-                //     let tick_time = start_time.elapsed().as_micros();
-                //     let time_per_tick = tick_time / ticks;
-                //     if time_per_tick > (1_000_000 / self.tps) as u128 {
-                //         let new_tps = 1_000_000 / time_per_tick;
-                //         self.broadcast_plot_chat_message(format!("Plot is overloaded, setting rtps down to {}", new_tps));
-                //         self.tps = new_tps as u32;
-                //     }
-                // }
-            }
-
-            let mut multi_block_packets = Vec::new();
-            for chunk in &mut self.chunks {
-                multi_block_packets.append(&mut chunk.drain_multi_block());
-            }
-            for packet in multi_block_packets {
-                let encoded = packet.encode();
-                for player in &mut self.players {
-                    player.client.send_packet(&encoded);
-                }
-            }
-        } else {
-            // Unload plot after 600 seconds unless the plot should be always loaded
-            if self.last_player_time.elapsed().unwrap().as_secs() > 600 && !self.always_running {
-                self.running = false;
             }
+            self.send_block_changes();
+        }
+
+        // Unload plot after 600 seconds unless the plot should be always loaded
+        if self.players.is_empty()
+            && self.last_player_time.elapsed().unwrap().as_secs() > 600
+            && !self.always_running
+            && !self.settings.keep_loaded
+        {
+            self.running = false;
         }
         // Update players
         for player_idx in 0..self.players.len() {
@@ -643,11 +1274,10 @@ impl Plot {
         // Remove players outside of the plot
         let mut outside_players = Vec::new();
         for player in 0..self.players.len() {
-            if !Plot::in_plot_bounds(
-                self.x,
-                self.z,
-                self.players[player].x as i32,
-                self.players[player].z as i32,
+            if !PLOT_GEOMETRY.in_bounds(
+                &self.region,
+                self.players[player].x.floor() as i32,
+                self.players[player].z.floor() as i32,
             ) {
                 outside_players.push(player);
             }
@@ -659,37 +1289,116 @@ impl Plot {
         }
     }
 
-    fn load_from_file(
-        data: Vec<u8>,
-        x: i32,
-        z: i32,
+    /// Reads plot data from a file. Files saved with a different plot size are rejected,
+    /// because their chunks wouldn't line up with the plot.
+    fn read_plot_file(path: &str) -> Result<PlotData, Box<dyn Error>> {
+        let data: PlotData = bincode::deserialize(&fs::read(path)?)?;
+        if data.chunk_data.len() != PLOT_GEOMETRY.chunk_count() {
+            return Err(format!(
+                "it has {} chunks, but plots in this world have {}",
+                data.chunk_data.len(),
+                PLOT_GEOMETRY.chunk_count()
+            )
+            .into());
+        }
+        Ok(data)
+    }
+
+    /// Reads the data of a single plot from its file. New plots are copied from the
+    /// template plot if there is one, otherwise they are generated. Also returns true if
+    /// the plot is new.
+    fn load_plot_data(plot_x: i32, plot_z: i32) -> (PlotData, bool) {
+        let path = format!("./world/plots/p{},{}", plot_x, plot_z);
+        if Path::new(&path).exists() {
+            match Plot::read_plot_file(&path) {
+                Ok(data) => return (data, false),
+                Err(err) => {
+                    // Move the file away so it isn't overwritten when the plot is saved
+                    let invalid_path = format!("{}.invalid", path);
+                    error!(
+                        "Plot {},{} can't be loaded: {}. The file was moved to {} and the plot is generated again.",
+                        plot_x, plot_z, err, invalid_path
+                    );
+                    if let Err(err) = fs::rename(&path, &invalid_path) {
+                        error!("Error moving {}: {}", path, err);
+                    }
+                }
+            }
+        }
+        let template_path = "./world/plots/pTEMPLATE";
+        if Path::new(template_path).exists() {
+            match Plot::read_plot_file(template_path) {
+                Ok(data) => return (data, true),
+                Err(err) => error!(
+                    "The template plot can't be loaded: {}. Plot {},{} is generated instead.",
+                    err, plot_x, plot_z
+                ),
+            }
+        }
+        let chunk_data = (0..PLOT_GEOMETRY.chunk_count())
+            .map(|i| {
+                let (chunk_x, chunk_z) = PLOT_GEOMETRY.chunk_at_index(plot_x, plot_z, i);
+                Chunk::generate(
+                    PLOT_GEOMETRY.generated_layers(),
+                    chunk_x,
+                    chunk_z,
+                    |x, y, z| PLOT_GEOMETRY.generated_block(x, y, z),
+                )
+                .save()
+            })
+            .collect();
+        let data = PlotData {
+            tps: 10,
+            show_redstone: true,
+            chunk_data,
+            pending_ticks: Vec::new(),
+        };
+        (data, true)
+    }
+
+    fn load(
+        region: PlotRegion,
         rx: BusReader<BroadcastMessage>,
         tx: Sender<Message>,
         priv_rx: Receiver<PrivMessage>,
         always_running: bool,
     ) -> Plot {
-        let chunk_x_offset = x << 4;
-        let chunk_z_offset = z << 4;
-        let plot_data: PlotData = bincode::deserialize(&data).unwrap();
-        let chunks: Vec<Chunk> = plot_data
-            .chunk_data
-            .into_iter()
-            .enumerate()
-            .map(|(i, c)| {
-                Chunk::load(
-                    chunk_x_offset + i as i32 / 16,
-                    chunk_z_offset + i as i32 % 16,
-                    c,
-                )
-            })
-            .collect();
-        Plot {
+        let (x, z) = (region.min_x, region.min_z);
+        let mut chunks = Vec::new();
+        let mut to_be_ticked = Vec::new();
+        // The tps and redstone visibility of merged plots are taken from the first plot
+        let mut tps = None;
+        let mut show_redstone = true;
+        let mut new_plots = false;
+        for (plot_x, plot_z) in region.plots() {
+            let (plot_data, new) = Plot::load_plot_data(plot_x, plot_z);
+            new_plots |= new;
+            if tps.is_none() {
+                tps = Some(plot_data.tps);
+                show_redstone = plot_data.show_redstone;
+            }
+            chunks.extend(plot_data.chunk_data.into_iter().enumerate().map(|(i, c)| {
+                let (chunk_x, chunk_z) = PLOT_GEOMETRY.chunk_at_index(plot_x, plot_z, i);
+                Chunk::load(chunk_x, chunk_z, c)
+            }));
+            to_be_ticked.extend(plot_data.pending_ticks);
+        }
+        to_be_ticked.sort_by_key(|e| (e.ticks_left, e.tick_priority.clone()));
+        let tps = tps.unwrap_or(10);
+        let settings = match database::get_plot_settings(x, z) {
+            Ok(settings) => settings.unwrap_or_default(),
+            Err(err) => {
+                error!("Error loading the settings of plot {},{}: {}", x, z, err);
+                PlotSettings::default()
+            }
+        };
+        let mut plot = Plot {
             last_player_time: SystemTime::now(),
             last_update_time: SystemTime::now(),
             lag_time: Duration::new(0, 0),
             sleep_time: Duration::from_micros(
                 (1_000_000 as u64)
-                    .checked_div((plot_data.tps as u64).max(20))
+                    .checked_div((tps as u64).max(20))
                     .unwrap_or(0),
             ),
             message_receiver: rx,
@@ -697,79 +1406,73 @@ impl Plot {
             priv_message_receiver: priv_rx,
             players: Vec::new(),
             running: true,
-            show_redstone: plot_data.show_redstone,
-            tps: plot_data.tps,
+            show_redstone,
+            tps,
+            tps_mode: TpsMode::Fixed,
             x,
             z,
+            region,
             always_running,
+            settings,
+            owner: None,
+            modified: false,
+            stats: Default::default(),
+            profiler: None,
+            warp: None,
             chunks,
-            to_be_ticked: plot_data.pending_ticks,
+            to_be_ticked,
+        };
+        plot.load_owner();
+        // Plots claimed while they weren't loaded still have the unclaimed wall
+        let wall_outdated = database::is_wall_outdated(region).unwrap_or_else(|err| {
+            error!("Error loading the wall of plot {},{}: {}", x, z, err);
+            false
+        });
+        if new_plots || wall_outdated {
+            plot.update_plot_wall();
         }
+        plot
     }
 
-    fn load(
-        x: i32,
-        z: i32,
-        rx: BusReader<BroadcastMessage>,
-        tx: Sender<Message>,
-        priv_rx: Receiver<PrivMessage>,
-        always_running: bool,
-    ) -> Plot {
-        if let Ok(data) = fs::read(format!("./world/plots/p{},{}", x, z)) {
-            Plot::load_from_file(data, x, z, rx, tx, priv_rx, always_running)
-        } else if Path::new("./world/plots/pTEMPLATE").exists() {
-            let data = fs::read("./world/plots/pTEMPLATE").unwrap();
-            Plot::load_from_file(data, x, z, rx, tx, priv_rx, always_running)
-        } else {
-            let chunk_x_offset = x << 4;
-            let chunk_z_offset = z << 4;
-            let mut chunks = Vec::new();
-            for chunk_x in 0..16 {
-                for chunk_z in 0..16 {
-                    chunks.push(Chunk::generate(
-                        8,
-                        chunk_x + chunk_x_offset,
-                        chunk_z + chunk_z_offset,
-                    ));
-                }
+    /// Saves every plot in the region to its own file so merged plots can be unmerged later.
+    fn save(&mut self) {
+        if self.modified {
+            if let Err(err) = database::touch_plots_modified(self.region) {
+                error!(
+                    "Error updating the last modified time of plot {},{}: {}",
+                    self.x, self.z, err
+                );
             }
-            Plot {
-                last_player_time: SystemTime::now(),
-                last_update_time: SystemTime::now(),
-                lag_time: Duration::new(0, 0),
-                sleep_time: Duration::from_millis(50),
-                message_receiver: rx,
-                message_sender: tx,
-                priv_message_receiver: priv_rx,
-                players: Vec::new(),
-                running: true,
-                show_redstone: true,
-                tps: 10,
-                x,
-                z,
-                always_running,
-                chunks,
-                to_be_ticked: Vec::new(),
-            }
-        }
-    }
-
-    fn save(&self) {
-        let mut file = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .open(format!("./world/plots/p{},{}", self.x, self.z))
+            self.modified = false;
+        }
+        let chunk_count = PLOT_GEOMETRY.chunk_count();
+        for (i, (plot_x, plot_z)) in self.region.plots().into_iter().enumerate() {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(format!("./world/plots/p{},{}", plot_x, plot_z))
+                .unwrap();
+            let chunk_data: Vec<ChunkData> = self.chunks[i * chunk_count..(i + 1) * chunk_count]
+                .iter()
+                .map(|c| c.save())
+                .collect();
+            let pending_ticks = self
+                .to_be_ticked
+                .iter()
+                .filter(|e| PLOT_GEOMETRY.plot_at(e.pos.x, e.pos.z) == (plot_x, plot_z))
+                .cloned()
+                .collect();
+            let encoded: Vec<u8> = bincode::serialize(&PlotData {
+                tps: self.tps,
+                show_redstone: self.show_redstone,
+                chunk_data,
+                pending_ticks,
+            })
             .unwrap();
-        let chunk_data: Vec<ChunkData> = self.chunks.iter().map(|c| c.save()).collect();
-        let encoded: Vec<u8> = bincode::serialize(&PlotData {
-            tps: self.tps,
-            show_redstone: self.show_redstone,
-            chunk_data,
-            pending_ticks: self.to_be_ticked.clone(),
-        })
-        .unwrap();
-        file.write_all(&encoded).unwrap();
-        file.sync_data().unwrap();
+            file.write_all(&encoded).unwrap();
+            file.sync_data().unwrap();
+        }
     }
 
     fn run(&mut self, initial_player: Option<Player>) {
@@ -783,17 +1486,19 @@ impl Plot {
     }
 
     pub fn load_and_run(
-        x: i32,
-        z: i32,
+        region: PlotRegion,
         rx: BusReader<BroadcastMessage>,
         tx: Sender<Message>,
         priv_rx: Receiver<PrivMessage>,
         always_running: bool,
         initial_player: Option<Player>,
     ) {
-        let mut plot = Plot::load(x, z, rx, tx, priv_rx, always_running);
+        let mut plot = Plot::load(region, rx, tx, priv_rx, always_running);
+        if let Some(tps) = plot.settings.default_rtps {
+            plot.set_tps(tps);
+        }
         thread::Builder::new()
-            .name(format!("p{},{}", x, z))
+            .name(format!("p{},{}", region.min_x, region.min_z))
             .spawn(move || {
                 plot.run(initial_player);
             })
@@ -820,12 +1525,46 @@ impl Drop for Plot {
             }
         }
         self.save();
+        metrics::remove_plot(self.x, self.z);
         self.message_sender
             .send(Message::PlotUnload(self.x, self.z))
             .unwrap();
     }
 }
 
+/// Returns the rtps an adaptive plot running at `tps` should switch to, so that it spends
+/// `ADAPTIVE_TARGET_LOAD` of its time ticking
+fn adapted_tps(tps: u32, max: u32, stats: &PlotMetrics) -> u32 {
+    // Without ticks, such as while the plot is paused, there is nothing to adapt to
+    if stats.rtps <= 0.0 || stats.mspt <= 0.0 {
+        return tps;
+    }
+    let load = stats.rtps * stats.mspt / 1000.0;
+    let factor = (ADAPTIVE_TARGET_LOAD / load).clamp(0.5, 2.0);
+    // Small changes aren't worth it
+    if (factor - 1.0).abs() < 0.05 {
+        return tps;
+    }
+    ((tps as f64 * factor) as u32).clamp(1, max)
+}
+
+#[test]
+fn adaptive_tps() {
+    let stats = |rtps, mspt| PlotMetrics {
+        rtps,
+        mspt,
+        ..Default::default()
+    };
+    // A plot that didn't tick stays at its rtps instead of climbing to the max
+    assert_eq!(adapted_tps(100, 1000, &stats(0.0, 0.0)), 100);
+    // 100 ticks of 1ms is a load of 0.1, so the rtps doubles
+    assert_eq!(adapted_tps(100, 1000, &stats(100.0, 1.0)), 200);
+    assert_eq!(adapted_tps(800, 1000, &stats(800.0, 0.1)), 1000);
+    // 100 ticks of 16ms is a load of 1.6, so the rtps halves
+    assert_eq!(adapted_tps(100, 1000, &stats(100.0, 16.0)), 50);
+    assert_eq!(adapted_tps(100, 1000, &stats(100.0, 8.1)), 100);
+}
+
 #[test]
 fn chunk_save_and_load_test() {
     let mut chunk = Chunk::empty(1, 1);
@@ -1,4 +1,4 @@
-use super::Plot;
+use super::{worldedit, Plot};
 use crate::blocks::{BlockEntity, BlockFace, BlockPos, SignBlockEntity};
 use crate::items::{Item, ItemStack, UseOnBlockContext};
 use crate::network::packets::clientbound::*;
@@ -16,6 +16,7 @@ impl Plot {
         for packet in packets {
             packet.handle(self, player);
         }
+        self.players[player].worldedit_tool_used = false;
     }
 }
 
@@ -115,12 +116,35 @@ impl ServerBoundPacketHandler for Plot {
             player_block_placement.z,
         );
 
-        if !Plot::in_plot_bounds(self.x, self.z, block_pos.x, block_pos.z) {
+        // Clicking the inside of a plot border is fine as long as the block is placed inside
+        // the plot. Placement itself is checked when the item is used.
+        let placement_pos = block_pos.offset(block_face);
+        if !self.in_build_area(block_pos) && !self.in_build_area(placement_pos) {
             self.players[player].send_system_message("Can't interact with blocks outside of plot");
-            self.send_block_change(block_pos.offset(block_face), 0);
+            self.send_block_change(placement_pos, self.get_block_raw(placement_pos));
             return;
         }
 
+        if self.players[player].worldedit_tool_used
+            || (player_block_placement.hand == 0
+                && worldedit::use_tool(self, player, block_pos, false))
+        {
+            // Undo anything the client placed or changed on its own
+            self.send_block_change(block_pos, self.get_block_raw(block_pos));
+            self.send_block_change(placement_pos, self.get_block_raw(placement_pos));
+            self.players[player].worldedit_tool_used = true;
+            return;
+        }
+
+        let crouching = self.players[player].crouching;
+        if !crouching && self.get_block(block_pos).on_use_changes_build() && !self.can_build(player)
+        {
+            self.players[player].send_system_message("You are not allowed to build in this plot");
+            self.send_block_change(block_pos, self.get_block_raw(block_pos));
+            return;
+        }
+
+        self.modified = true;
         if let Some(item) = item_in_hand {
             item.use_on_block(
                 self,
@@ -335,11 +359,16 @@ impl ServerBoundPacketHandler for Plot {
         if player_digging.status == 0 {
             let block_pos = BlockPos::new(player_digging.x, player_digging.y, player_digging.z);
 
-            if !Plot::in_plot_bounds(self.x, self.z, block_pos.x, block_pos.z) {
+            if !Plot::in_plot_bounds(&self.region, block_pos.x, block_pos.z) {
                 self.players[player].send_system_message("Can't break blocks outside of plot");
                 return;
             }
 
+            if worldedit::use_tool(self, player, block_pos, true) {
+                self.send_block_change(block_pos, self.get_block_raw(block_pos));
+                return;
+            }
+
             // This worldedit wand stuff should probably be done in another file. It's good enough for now.
             let item_in_hand = self.players[player].inventory
                 [self.players[player].selected_slot as usize + 36]
@@ -362,7 +391,15 @@ impl ServerBoundPacketHandler for Plot {
                 }
             }
 
+            if !self.can_build(player) {
+                self.players[player]
+                    .send_system_message("You are not allowed to build in this plot");
+                self.send_block_change(block_pos, self.get_block_raw(block_pos));
+                return;
+            }
+
             let other_block = self.get_block(block_pos);
+            self.modified = true;
             other_block.destroy(self, block_pos);
 
             let effect = C21Effect {
@@ -464,8 +501,27 @@ impl ServerBoundPacketHandler for Plot {
         self.players[player].selected_slot = held_item_change.slot as u32;
     }
 
-    fn handle_update_sign(&mut self, packet: S2BUpdateSign, _player: usize) {
+    fn handle_use_item(&mut self, use_item: S2FUseItem, player: usize) {
+        if use_item.hand != 0 || self.players[player].worldedit_tool_used {
+            return;
+        }
+        let slot = self.players[player].selected_slot;
+        if !self.players[player].worldedit_tools.contains_key(&slot) {
+            return;
+        }
+        match worldedit::raycast(self, player) {
+            Some(pos) => {
+                worldedit::use_tool(self, player, pos, false);
+            }
+            None => self.players[player].send_error_message("No block in sight!"),
+        }
+    }
+
+    fn handle_update_sign(&mut self, packet: S2BUpdateSign, player: usize) {
         let pos = BlockPos::new(packet.x, packet.y, packet.z);
+        if !self.in_build_area(pos) || !self.can_build(player) {
+            return;
+        }
         let mut rows = packet
             .lines
             .iter()
@@ -478,6 +534,7 @@ impl ServerBoundPacketHandler for Plot {
                 rows.next().unwrap(),
             ],
         }));
+        self.modified = true;
         self.set_block_entity(pos, block_entity);
     }
 }
@@ -0,0 +1,288 @@
+use crate::blocks::{Block, BlockColorVariant, BlockPos};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long profiling runs for when no duration is given
+pub const DEFAULT_PROFILE_DURATION: Duration = Duration::from_secs(10);
+/// The size of the cubes blocks are grouped into to find the hottest regions
+const REGION_SIZE: i32 = 8;
+/// How many entries each section of the report lists
+const REPORT_ENTRIES: usize = 5;
+/// The most blocks the heatmap recolors, so big builds don't flood the client
+const MAX_HEATMAP_BLOCKS: usize = 4096;
+/// The colors of the heatmap, from the least to the most active blocks
+const HEATMAP_COLORS: [BlockColorVariant; 6] = [
+    BlockColorVariant::Blue,
+    BlockColorVariant::LightBlue,
+    BlockColorVariant::Lime,
+    BlockColorVariant::Yellow,
+    BlockColorVariant::Orange,
+    BlockColorVariant::Red,
+];
+
+/// The counts of a region and how active each block type in it was
+type RegionCounts = (BlockCounts, HashMap<String, u64>);
+
+#[derive(Debug, Clone, Copy, Default)]
+struct BlockCounts {
+    updates: u64,
+    ticks: u64,
+    set_blocks: u64,
+}
+
+impl BlockCounts {
+    fn total(&self) -> u64 {
+        self.updates + self.ticks + self.set_blocks
+    }
+
+    fn add(&mut self, other: &BlockCounts) {
+        self.updates += other.updates;
+        self.ticks += other.ticks;
+        self.set_blocks += other.set_blocks;
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} ({} updates, {} ticks, {} changes)",
+            self.total(),
+            self.updates,
+            self.ticks,
+            self.set_blocks
+        )
+    }
+}
+
+/// Counts how often each block in a plot is updated, ticked and changed over a window
+/// of time to find out what is making a plot slow.
+pub struct Profiler {
+    started: Instant,
+    duration: Duration,
+    /// How long profiling ran for, once it has stopped
+    elapsed: Option<Duration>,
+    /// The counts of each position and the block that was last seen there
+    counts: HashMap<BlockPos, (Block, BlockCounts)>,
+    /// The uuids of the players that are seeing the heatmap
+    pub heatmap_viewers: Vec<u128>,
+}
+
+impl Profiler {
+    pub fn new(duration: Duration) -> Profiler {
+        Profiler {
+            started: Instant::now(),
+            duration,
+            elapsed: None,
+            counts: HashMap::new(),
+            heatmap_viewers: Vec::new(),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.elapsed.is_none()
+    }
+
+    fn entry(&mut self, pos: BlockPos, block: Block) -> &mut BlockCounts {
+        let entry = self
+            .counts
+            .entry(pos)
+            .or_insert_with(|| (block, BlockCounts::default()));
+        entry.0 = block;
+        &mut entry.1
+    }
+
+    pub fn record_update(&mut self, pos: BlockPos, block: Block) {
+        // Air is updated all the time and never does anything
+        if self.is_running() && block != (Block::Air {}) {
+            self.entry(pos, block).updates += 1;
+        }
+    }
+
+    pub fn record_tick(&mut self, pos: BlockPos, block: Block) {
+        if self.is_running() {
+            self.entry(pos, block).ticks += 1;
+        }
+    }
+
+    pub fn record_set_block(&mut self, pos: BlockPos, block: Block) {
+        if self.is_running() {
+            self.entry(pos, block).set_blocks += 1;
+        }
+    }
+
+    /// Returns the time left until profiling stops
+    pub fn time_left(&self) -> Duration {
+        self.duration.saturating_sub(self.started.elapsed())
+    }
+
+    pub fn stop(&mut self) {
+        if self.is_running() {
+            self.elapsed = Some(self.started.elapsed());
+        }
+    }
+
+    /// Stops profiling once the window is over. Returns true if profiling was stopped.
+    pub fn update(&mut self) -> bool {
+        if self.is_running() && self.time_left() == Duration::from_secs(0) {
+            self.stop();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns the lines of the profiling report that gets sent in chat
+    pub fn report(&self) -> Vec<String> {
+        let elapsed = self.elapsed.unwrap_or_else(|| self.started.elapsed());
+        let mut total = BlockCounts::default();
+        let mut regions: HashMap<(i32, i32, i32), RegionCounts> = HashMap::new();
+        let mut types: HashMap<String, BlockCounts> = HashMap::new();
+        for (pos, (block, counts)) in &self.counts {
+            let name = block_type_name(*block);
+            total.add(counts);
+            let region = regions
+                .entry((
+                    pos.x.div_euclid(REGION_SIZE),
+                    pos.y.div_euclid(REGION_SIZE),
+                    pos.z.div_euclid(REGION_SIZE),
+                ))
+                .or_default();
+            region.0.add(counts);
+            *region.1.entry(name.clone()).or_default() += counts.total();
+            types.entry(name).or_default().add(counts);
+        }
+
+        let mut lines = vec![format!(
+            "Profiled for {:.1}s: {} in {} blocks",
+            elapsed.as_secs_f64(),
+            total.describe(),
+            self.counts.len()
+        )];
+        if self.counts.is_empty() {
+            return lines;
+        }
+
+        let mut regions: Vec<_> = regions.into_iter().collect();
+        regions.sort_by_key(|(pos, (counts, _))| (std::cmp::Reverse(counts.total()), *pos));
+        lines.push("Hottest regions:".to_owned());
+        for (i, ((x, y, z), (counts, region_types))) in
+            regions.iter().take(REPORT_ENTRIES).enumerate()
+        {
+            let (x, y, z) = (x * REGION_SIZE, y * REGION_SIZE, z * REGION_SIZE);
+            let most_common = region_types
+                .iter()
+                .max_by_key(|(name, count)| (*count, std::cmp::Reverse(*name)))
+                .map(|(name, _)| name.as_str())
+                .unwrap_or_default();
+            lines.push(format!(
+                "{}. {},{},{} to {},{},{}: {}, mostly {}",
+                i + 1,
+                x,
+                y,
+                z,
+                x + REGION_SIZE - 1,
+                y + REGION_SIZE - 1,
+                z + REGION_SIZE - 1,
+                counts.describe(),
+                most_common
+            ));
+        }
+
+        let mut types: Vec<_> = types.into_iter().collect();
+        types.sort_by(|(a_name, a), (b_name, b)| {
+            b.total().cmp(&a.total()).then_with(|| a_name.cmp(b_name))
+        });
+        lines.push("Hottest block types:".to_owned());
+        for (i, (name, counts)) in types.iter().take(REPORT_ENTRIES).enumerate() {
+            lines.push(format!("{}. {}: {}", i + 1, name, counts.describe()));
+        }
+
+        lines.push("Hottest blocks:".to_owned());
+        for (i, (pos, (block, counts))) in self.hottest().take(REPORT_ENTRIES).enumerate() {
+            lines.push(format!(
+                "{}. {},{},{} {}: {}",
+                i + 1,
+                pos.x,
+                pos.y,
+                pos.z,
+                block_type_name(*block),
+                counts.describe()
+            ));
+        }
+        lines
+    }
+
+    /// Returns the profiled blocks from the most to the least active
+    fn hottest(&self) -> impl Iterator<Item = (&BlockPos, &(Block, BlockCounts))> {
+        let mut blocks: Vec<_> = self.counts.iter().collect();
+        blocks.sort_by_key(|(pos, (_, counts))| {
+            (std::cmp::Reverse(counts.total()), pos.x, pos.y, pos.z)
+        });
+        blocks.into_iter()
+    }
+
+    /// Returns the blocks to show in place of the profiled blocks. The colors are on a
+    /// logarithmic scale because a few blocks are often far more active than the rest.
+    pub fn heatmap(&self) -> Vec<(BlockPos, Block)> {
+        let blocks: Vec<_> = self
+            .hottest()
+            .filter(|(_, (block, _))| *block != (Block::Air {}))
+            .take(MAX_HEATMAP_BLOCKS)
+            .collect();
+        let max = match blocks.first() {
+            Some((_, (_, counts))) => (counts.total() as f64).ln_1p(),
+            None => return Vec::new(),
+        };
+        blocks
+            .into_iter()
+            .map(|(pos, (_, counts))| {
+                let heat = (counts.total() as f64).ln_1p() / max;
+                let level = (heat * (HEATMAP_COLORS.len() - 1) as f64).round() as usize;
+                let color = HEATMAP_COLORS[level.min(HEATMAP_COLORS.len() - 1)];
+                (*pos, Block::Concrete { color })
+            })
+            .collect()
+    }
+}
+
+/// Returns the name of the block without its properties
+fn block_type_name(block: Block) -> String {
+    let name = format!("{:?}", block);
+    match name.find(&[' ', '{'][..]) {
+        Some(end) => name[..end].to_owned(),
+        None => name,
+    }
+}
+
+#[test]
+fn profiler_report() {
+    let mut profiler = Profiler::new(DEFAULT_PROFILE_DURATION);
+    let lamp = Block::RedstoneLamp { lit: false };
+    for _ in 0..3 {
+        profiler.record_update(BlockPos::new(1, 0, 1), lamp);
+    }
+    profiler.record_tick(BlockPos::new(20, 0, 1), lamp);
+    profiler.record_update(BlockPos::new(2, 0, 1), Block::Air {});
+    profiler.stop();
+    // Nothing is counted after profiling stopped
+    profiler.record_update(BlockPos::new(20, 0, 1), lamp);
+
+    let report = profiler.report();
+    assert!(report[0].ends_with("4 (3 updates, 1 ticks, 0 changes) in 2 blocks"));
+    assert_eq!(
+        report[2],
+        "1. 0,0,0 to 7,7,7: 3 (3 updates, 0 ticks, 0 changes), mostly RedstoneLamp"
+    );
+    assert_eq!(
+        report[5],
+        "1. RedstoneLamp: 4 (3 updates, 1 ticks, 0 changes)"
+    );
+    let heatmap = profiler.heatmap();
+    assert_eq!(
+        heatmap[0],
+        (
+            BlockPos::new(1, 0, 1),
+            Block::Concrete {
+                color: BlockColorVariant::Red
+            }
+        )
+    );
+}
@@ -0,0 +1,277 @@
+const COLORS: [&str; 16] = [
+    "white",
+    "orange",
+    "magenta",
+    "light_blue",
+    "yellow",
+    "lime",
+    "pink",
+    "gray",
+    "light_gray",
+    "cyan",
+    "purple",
+    "blue",
+    "brown",
+    "green",
+    "red",
+    "black",
+];
+
+/// The order of horizontal directions in data values
+const HORIZONTAL: [&str; 4] = ["south", "west", "north", "east"];
+/// The directions of blocks that can face any way, like pistons and dispensers
+const FACING: [&str; 6] = ["down", "up", "north", "south", "west", "east"];
+const WOOD_TYPES: [&str; 6] = ["oak", "spruce", "birch", "jungle", "acacia", "dark_oak"];
+/// The slab types of stone slabs and double stone slabs
+const SLAB_TYPES: [&str; 8] = [
+    "smooth_stone",
+    "sandstone",
+    "petrified_oak",
+    "cobblestone",
+    "brick",
+    "stone_brick",
+    "nether_brick",
+    "quartz",
+];
+
+/// Returns the block state of a legacy block id and data value, such as
+/// `repeater[delay=2,facing=north,powered=false]`. Returns `None` if the block or data
+/// value is unknown.
+pub fn block_state(id: u16, data: u8) -> Option<String> {
+    let powered = data & 8 != 0;
+    let horizontal = HORIZONTAL[(data & 3) as usize];
+    let color = COLORS[(data & 15) as usize];
+    // Chests, furnaces and wall signs only use the horizontal directions of `FACING`
+    let facing = FACING.get((data & 7) as usize).copied();
+    let wall_facing = facing.filter(|_| (2..6).contains(&data));
+    Some(match id {
+        0 => "air".to_owned(),
+        1 => [
+            "stone",
+            "granite",
+            "polished_granite",
+            "diorite",
+            "polished_diorite",
+            "andesite",
+            "polished_andesite",
+        ]
+        .get(data as usize)?
+        .to_string(),
+        2 => "grass_block".to_owned(),
+        3 => ["dirt", "coarse_dirt", "podzol"]
+            .get(data as usize)?
+            .to_string(),
+        4 => "cobblestone".to_owned(),
+        5 => format!("{}_planks", WOOD_TYPES.get(data as usize)?),
+        7 => "bedrock".to_owned(),
+        8 | 9 => format!("water[level={}]", data),
+        10 | 11 => format!("lava[level={}]", data),
+        12 => ["sand", "red_sand"].get(data as usize)?.to_string(),
+        13 => "gravel".to_owned(),
+        17 => {
+            let wood = WOOD_TYPES[(data & 3) as usize];
+            match data >> 2 {
+                0 => format!("{}_log[axis=y]", wood),
+                1 => format!("{}_log[axis=x]", wood),
+                2 => format!("{}_log[axis=z]", wood),
+                _ => format!("{}_wood[axis=y]", wood),
+            }
+        }
+        18 => format!("{}_leaves", WOOD_TYPES[(data & 3) as usize]),
+        20 => "glass".to_owned(),
+        23 | 158 => format!(
+            "{}[facing={},triggered={}]",
+            if id == 23 { "dispenser" } else { "dropper" },
+            facing?,
+            powered
+        ),
+        24 => ["sandstone", "chiseled_sandstone", "cut_sandstone"]
+            .get(data as usize)?
+            .to_string(),
+        25 => "note_block".to_owned(),
+        29 | 33 => format!(
+            "{}[extended={},facing={}]",
+            if id == 29 { "sticky_piston" } else { "piston" },
+            powered,
+            facing?
+        ),
+        34 => format!(
+            "piston_head[facing={},type={}]",
+            facing?,
+            if powered { "sticky" } else { "normal" }
+        ),
+        35 => format!("{}_wool", color),
+        41 => "gold_block".to_owned(),
+        42 => "iron_block".to_owned(),
+        43 => match data {
+            8 => "smooth_stone".to_owned(),
+            9 => "smooth_sandstone".to_owned(),
+            _ => format!("{}_slab[type=double]", SLAB_TYPES.get(data as usize)?),
+        },
+        44 => {
+            let half = if powered { "top" } else { "bottom" };
+            format!("{}_slab[type={}]", SLAB_TYPES[(data & 7) as usize], half)
+        }
+        46 => "tnt".to_owned(),
+        49 => "obsidian".to_owned(),
+        54 | 146 => format!(
+            "{}[facing={}]",
+            if id == 54 { "chest" } else { "trapped_chest" },
+            wall_facing?
+        ),
+        55 => format!("redstone_wire[power={}]", data),
+        57 => "diamond_block".to_owned(),
+        61 | 62 => format!("furnace[facing={},lit={}]", wall_facing?, id == 62),
+        63 => format!("oak_sign[rotation={}]", data),
+        68 => format!("oak_wall_sign[facing={}]", wall_facing?),
+        69 => {
+            let (face, facing) = match data & 7 {
+                0 => ("ceiling", "west"),
+                1 => ("wall", "east"),
+                2 => ("wall", "west"),
+                3 => ("wall", "south"),
+                4 => ("wall", "north"),
+                5 => ("floor", "north"),
+                6 => ("floor", "west"),
+                _ => ("ceiling", "north"),
+            };
+            format!("lever[face={},facing={},powered={}]", face, facing, powered)
+        }
+        70 => format!("stone_pressure_plate[powered={}]", data != 0),
+        75 | 76 => {
+            let lit = id == 76;
+            match data {
+                1 => format!("redstone_wall_torch[facing=east,lit={}]", lit),
+                2 => format!("redstone_wall_torch[facing=west,lit={}]", lit),
+                3 => format!("redstone_wall_torch[facing=south,lit={}]", lit),
+                4 => format!("redstone_wall_torch[facing=north,lit={}]", lit),
+                _ => format!("redstone_torch[lit={}]", lit),
+            }
+        }
+        77 | 143 => {
+            let (face, facing) = match data & 7 {
+                0 => ("ceiling", "north"),
+                1 => ("wall", "east"),
+                2 => ("wall", "west"),
+                3 => ("wall", "south"),
+                4 => ("wall", "north"),
+                _ => ("floor", "north"),
+            };
+            format!(
+                "{}[face={},facing={},powered={}]",
+                if id == 77 {
+                    "stone_button"
+                } else {
+                    "oak_button"
+                },
+                face,
+                facing,
+                powered
+            )
+        }
+        93 | 94 => format!(
+            "repeater[delay={},facing={},powered={}]",
+            (data >> 2) + 1,
+            horizontal,
+            id == 94
+        ),
+        95 => format!("{}_stained_glass", color),
+        98 => [
+            "stone_bricks",
+            "mossy_stone_bricks",
+            "cracked_stone_bricks",
+            "chiseled_stone_bricks",
+        ]
+        .get(data as usize)?
+        .to_string(),
+        123 | 124 => format!("redstone_lamp[lit={}]", id == 124),
+        131 => format!(
+            "tripwire_hook[attached={},facing={},powered={}]",
+            data & 4 != 0,
+            horizontal,
+            powered
+        ),
+        137 => "command_block".to_owned(),
+        149 | 150 => {
+            let mode = if data & 4 != 0 { "subtract" } else { "compare" };
+            format!(
+                "comparator[facing={},mode={},powered={}]",
+                horizontal,
+                mode,
+                powered || id == 150
+            )
+        }
+        152 => "redstone_block".to_owned(),
+        154 => {
+            // Hoppers can't face up
+            let facing = facing.filter(|&facing| facing != "up")?;
+            format!("hopper[enabled={},facing={}]", !powered, facing)
+        }
+        155 => match data {
+            0 => "quartz_block".to_owned(),
+            1 => "chiseled_quartz_block".to_owned(),
+            2 => "quartz_pillar[axis=y]".to_owned(),
+            3 => "quartz_pillar[axis=x]".to_owned(),
+            4 => "quartz_pillar[axis=z]".to_owned(),
+            _ => return None,
+        },
+        159 => format!("{}_terracotta", color),
+        165 => "slime_block".to_owned(),
+        172 => "terracotta".to_owned(),
+        218 => {
+            let facing = match data & 7 {
+                0 => "down",
+                1 => "up",
+                2 => "north",
+                3 => "south",
+                4 => "west",
+                _ => "east",
+            };
+            format!("observer[facing={},powered={}]", facing, powered)
+        }
+        251 => format!("{}_concrete", color),
+        _ => return None,
+    })
+}
+
+/// Converts the id of a legacy block entity, such as `Comparator`, to its modern id
+pub fn block_entity_id(id: &str) -> String {
+    if id.contains(':') {
+        id.to_owned()
+    } else {
+        format!("minecraft:{}", id.to_lowercase())
+    }
+}
+
+#[test]
+fn legacy_redstone() {
+    assert_eq!(
+        block_state(94, 6).unwrap(),
+        "repeater[delay=2,facing=north,powered=true]"
+    );
+    assert_eq!(
+        block_state(149, 13).unwrap(),
+        "comparator[facing=west,mode=subtract,powered=true]"
+    );
+    assert_eq!(
+        block_state(75, 4).unwrap(),
+        "redstone_wall_torch[facing=north,lit=false]"
+    );
+    assert_eq!(block_state(35, 14).unwrap(), "red_wool");
+    assert_eq!(
+        block_state(23, 13).unwrap(),
+        "dispenser[facing=east,triggered=true]"
+    );
+    assert_eq!(
+        block_state(154, 10).unwrap(),
+        "hopper[enabled=false,facing=north]"
+    );
+    assert_eq!(block_state(5, 2).unwrap(), "birch_planks");
+    assert_eq!(block_state(44, 13).unwrap(), "stone_brick_slab[type=top]");
+    assert_eq!(block_state(98, 1).unwrap(), "mossy_stone_bricks");
+    // Data values that don't belong to any block aren't guessed
+    assert_eq!(block_state(5, 7), None);
+    assert_eq!(block_state(154, 1), None);
+    assert_eq!(block_entity_id("Comparator"), "minecraft:comparator");
+    assert_eq!(block_entity_id("minecraft:sign"), "minecraft:sign");
+}
@@ -0,0 +1,897 @@
+mod legacy;
+
+use super::worldedit::WorldEditClipboard;
+use crate::blocks::{Block, BlockEntity, BlockPos, RedstoneWire};
+use crate::world::storage::{Chunk, PalettedBitBuffer};
+use crate::world::{TickPriority, World};
+use nbt::Value;
+use regex::Regex;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The folder schematics are loaded from
+const SCHEMATICS_DIR: &str = "./schems";
+const SCHEMATIC_EXTENSIONS: [&str; 3] = [".schem", ".schematic", ".litematic"];
+/// The most blocks a Litematica schematic can cover once its regions are combined
+const MAX_LITEMATIC_VOLUME: usize = 1 << 28;
+
+macro_rules! get_tag {
+    ($nbt:expr, $name:expr, $p:path) => {
+        match $nbt.get($name) {
+            Some($p(val)) => val,
+            Some(_) => return Err(SchematicError::WrongTagType($name)),
+            None => return Err(SchematicError::MissingTag($name)),
+        }
+    };
+}
+
+#[derive(Debug)]
+pub enum SchematicError {
+    InvalidName(String),
+    NotFound(String),
+    Io(io::Error),
+    Nbt(nbt::Error),
+    MissingTag(&'static str),
+    WrongTagType(&'static str),
+    InvalidBlockState(String),
+    WrongLength(&'static str),
+    /// The varint for the block at this index is too long or cut off
+    BadVarint(usize),
+    UnknownPaletteId(u32),
+    Unsupported(String),
+}
+
+impl fmt::Display for SchematicError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchematicError::InvalidName(name) => write!(
+                f,
+                "invalid schematic name: {}. Names can only use letters, numbers, -, _ and . with / between folders.",
+                name
+            ),
+            SchematicError::NotFound(name) => write!(f, "there is no schematic named {}", name),
+            SchematicError::Io(err) => write!(f, "{}", err),
+            SchematicError::Nbt(err) => write!(f, "the file is not valid NBT: {}", err),
+            SchematicError::MissingTag(tag) => write!(f, "the {} tag is missing", tag),
+            SchematicError::WrongTagType(tag) => write!(f, "the {} tag has the wrong type", tag),
+            SchematicError::InvalidBlockState(state) => {
+                write!(f, "the palette has an invalid block state: {}", state)
+            }
+            SchematicError::WrongLength(tag) => write!(f, "the {} tag has the wrong length", tag),
+            SchematicError::BadVarint(index) => {
+                write!(f, "the BlockData has a bad varint for block {}", index)
+            }
+            SchematicError::UnknownPaletteId(id) => {
+                write!(f, "the block data uses {}, which isn't in the palette", id)
+            }
+            SchematicError::Unsupported(what) => write!(f, "{} aren't supported", what),
+        }
+    }
+}
+
+impl From<io::Error> for SchematicError {
+    fn from(err: io::Error) -> SchematicError {
+        SchematicError::Io(err)
+    }
+}
+
+impl From<nbt::Error> for SchematicError {
+    fn from(err: nbt::Error) -> SchematicError {
+        SchematicError::Nbt(err)
+    }
+}
+
+pub type SchematicResult<T> = Result<T, SchematicError>;
+
+pub struct Schematic {
+    pub clipboard: WorldEditClipboard,
+    /// The block states in the schematic's palette and how many blocks use each of them
+    pub palette: Vec<(String, usize)>,
+    /// The block states that aren't supported and were replaced with air
+    pub unknown_blocks: Vec<String>,
+}
+
+pub struct SchematicEntry {
+    /// The path of the schematic relative to the schematics folder
+    pub name: String,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+fn is_valid_component(component: &str) -> bool {
+    !component.is_empty()
+        && !component.starts_with('.')
+        && component
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Returns the path of a schematic in the schematics folder. Names can have subfolders,
+/// but can't reach outside of the schematics folder. Names without an extension use the
+/// first format that has a file with the name.
+pub fn schematic_path(name: &str) -> SchematicResult<PathBuf> {
+    if !name.split('/').all(is_valid_component) {
+        return Err(SchematicError::InvalidName(name.to_owned()));
+    }
+    if SCHEMATIC_EXTENSIONS.iter().any(|ext| name.ends_with(ext)) {
+        return Ok(Path::new(SCHEMATICS_DIR).join(name));
+    }
+    let paths: Vec<PathBuf> = SCHEMATIC_EXTENSIONS
+        .iter()
+        .map(|ext| Path::new(SCHEMATICS_DIR).join(format!("{}{}", name, ext)))
+        .collect();
+    Ok(paths
+        .iter()
+        .find(|path| path.exists())
+        .unwrap_or(&paths[0])
+        .clone())
+}
+
+/// Loads a Sponge `.schem`, MCEdit `.schematic` or Litematica `.litematic` schematic from
+/// the schematics folder
+pub fn load(name: &str) -> SchematicResult<Schematic> {
+    let path = schematic_path(name)?;
+    let mut file = match File::open(&path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            return Err(SchematicError::NotFound(name.to_owned()))
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let nbt = nbt::Blob::from_gzip_reader(&mut file)?;
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("schematic") => read_mcedit(&nbt),
+        Some("litematic") => read_litematic(&nbt),
+        _ => read_sponge(&nbt),
+    }
+}
+
+/// Parses a block state such as `minecraft:repeater[delay=2]`. Returns `None` if the block
+/// isn't supported.
+fn parse_block_state(state: &str) -> SchematicResult<Option<Block>> {
+    lazy_static! {
+        static ref RE: Regex =
+            Regex::new(r"^(?:minecraft:)?([a-z0-9_]+)(?:\[([a-z0-9_=,]*)\])?$").unwrap();
+    }
+    let captures = RE
+        .captures(state)
+        .ok_or_else(|| SchematicError::InvalidBlockState(state.to_owned()))?;
+    let mut block = match Block::from_name(&captures[1]) {
+        Some(block) => block,
+        None => return Ok(None),
+    };
+    if let Some(properties) = captures.get(2) {
+        for property in properties.as_str().split(',') {
+            if let Some((key, value)) = property.split_once('=') {
+                block.set_property(key, value);
+            }
+        }
+    }
+    Ok(Some(block))
+}
+
+/// Collects the block states of a schematic and how often they are used
+#[derive(Default)]
+struct PaletteBuilder {
+    states: Vec<(String, usize)>,
+    block_ids: Vec<u32>,
+    indices: HashMap<String, usize>,
+    unknown_blocks: Vec<String>,
+}
+
+impl PaletteBuilder {
+    /// Adds a block state and returns its index. Blocks that aren't supported become air.
+    fn add(&mut self, state: &str) -> SchematicResult<usize> {
+        let state = state.trim_start_matches("minecraft:");
+        if let Some(&index) = self.indices.get(state) {
+            return Ok(index);
+        }
+        let block = match parse_block_state(state)? {
+            Some(block) => block,
+            None => {
+                let name = state.split('[').next().unwrap_or(state);
+                self.add_unknown(name);
+                Block::Air {}
+            }
+        };
+        Ok(self.insert(state.to_owned(), block.get_id()))
+    }
+
+    fn add_unknown(&mut self, name: &str) -> usize {
+        if !self.unknown_blocks.iter().any(|unknown| unknown == name) {
+            self.unknown_blocks.push(name.to_owned());
+        }
+        if let Some(&index) = self.indices.get(name) {
+            return index;
+        }
+        self.insert(name.to_owned(), 0)
+    }
+
+    fn insert(&mut self, state: String, block_id: u32) -> usize {
+        let index = self.states.len();
+        self.indices.insert(state.clone(), index);
+        self.states.push((state, 0));
+        self.block_ids.push(block_id);
+        index
+    }
+
+    /// Counts a block with the state at `index` and returns its block id
+    fn use_state(&mut self, index: usize) -> u32 {
+        self.states[index].1 += 1;
+        self.block_ids[index]
+    }
+
+    fn finish(mut self, clipboard: WorldEditClipboard) -> Schematic {
+        self.states
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        self.unknown_blocks.sort();
+        Schematic {
+            clipboard,
+            palette: self.states,
+            unknown_blocks: self.unknown_blocks,
+        }
+    }
+}
+
+/// Returns the number of blocks in a region of the size, or `None` if it doesn't fit in a `usize`
+fn checked_volume(size_x: u32, size_y: u32, size_z: u32) -> Option<usize> {
+    (size_x as usize)
+        .checked_mul(size_y as usize)?
+        .checked_mul(size_z as usize)
+}
+
+fn read_varint(data: &[u8], i: &mut usize) -> Option<u32> {
+    let mut value = 0;
+    // Max varint length is 5
+    for len in 0..5 {
+        let byte = *data.get(*i)?;
+        *i += 1;
+        value |= ((byte & 127) as u32) << (len * 7);
+        if byte & 128 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+fn read_block_entity(
+    block_entities: &mut HashMap<BlockPos, BlockEntity>,
+    pos: BlockPos,
+    nbt: &HashMap<String, Value>,
+) {
+    if let Some(parsed) = BlockEntity::from_nbt(nbt) {
+        block_entities.insert(pos, parsed);
+    }
+}
+
+fn read_sponge(nbt: &nbt::Blob) -> SchematicResult<Schematic> {
+    let size_x = *get_tag!(nbt, "Width", Value::Short) as u16 as u32;
+    let size_z = *get_tag!(nbt, "Length", Value::Short) as u16 as u32;
+    let size_y = *get_tag!(nbt, "Height", Value::Short) as u16 as u32;
+    let nbt_palette = get_tag!(nbt, "Palette", Value::Compound);
+    let metadata = get_tag!(nbt, "Metadata", Value::Compound);
+    let offset_x = -get_tag!(metadata, "WEOffsetX", Value::Int);
+    let offset_y = -get_tag!(metadata, "WEOffsetY", Value::Int);
+    let offset_z = -get_tag!(metadata, "WEOffsetZ", Value::Int);
+    let mut palette = PaletteBuilder::default();
+    // Maps the ids used in the schematic to indices into the palette
+    let mut ids: HashMap<u32, usize> = HashMap::new();
+    for (k, v) in nbt_palette {
+        let id = match v {
+            Value::Int(id) => *id as u32,
+            _ => return Err(SchematicError::WrongTagType("Palette")),
+        };
+        ids.insert(id, palette.add(k)?);
+    }
+    let blocks: Vec<u8> = get_tag!(nbt, "BlockData", Value::ByteArray)
+        .iter()
+        .map(|b| *b as u8)
+        .collect();
+    // Every block takes at least one byte, so this is checked before allocating the buffer
+    let volume = checked_volume(size_x, size_y, size_z)
+        .filter(|&volume| volume <= blocks.len())
+        .ok_or(SchematicError::WrongLength("BlockData"))?;
+    let mut data = PalettedBitBuffer::with_entries(volume);
+    let mut i = 0;
+    for index in 0..volume {
+        let blockstate_id = read_varint(&blocks, &mut i).ok_or(SchematicError::BadVarint(index))?;
+        let palette_idx = *ids
+            .get(&blockstate_id)
+            .ok_or(SchematicError::UnknownPaletteId(blockstate_id))?;
+        data.set_entry(index, palette.use_state(palette_idx));
+    }
+    let mut block_entities = HashMap::new();
+    if let Some(Value::List(nbt_block_entities)) = nbt.get("BlockEntities") {
+        for block_entity in nbt_block_entities {
+            let val = match block_entity {
+                Value::Compound(val) => val,
+                _ => return Err(SchematicError::WrongTagType("BlockEntities")),
+            };
+            let pos_array = get_tag!(val, "Pos", Value::IntArray);
+            if pos_array.len() != 3 {
+                return Err(SchematicError::WrongTagType("Pos"));
+            }
+            let pos = BlockPos::new(pos_array[0], pos_array[1], pos_array[2]);
+            read_block_entity(&mut block_entities, pos, val);
+        }
+    }
+    Ok(palette.finish(WorldEditClipboard {
+        offset_x,
+        offset_y,
+        offset_z,
+        size_x,
+        size_y,
+        size_z,
+        data,
+        block_entities,
+    }))
+}
+
+/// Reads an MCEdit schematic, which stores blocks as the numeric ids and data values used
+/// before 1.13
+fn read_mcedit(nbt: &nbt::Blob) -> SchematicResult<Schematic> {
+    let size_x = *get_tag!(nbt, "Width", Value::Short) as u16 as u32;
+    let size_z = *get_tag!(nbt, "Length", Value::Short) as u16 as u32;
+    let size_y = *get_tag!(nbt, "Height", Value::Short) as u16 as u32;
+    let materials = get_tag!(nbt, "Materials", Value::String);
+    if materials != "Alpha" {
+        return Err(SchematicError::Unsupported(format!(
+            "{} materials",
+            materials
+        )));
+    }
+    let block_ids = get_tag!(nbt, "Blocks", Value::ByteArray);
+    let block_data = get_tag!(nbt, "Data", Value::ByteArray);
+    // Block ids above 255 keep their upper 4 bits here, two blocks to a byte
+    let add_blocks = match nbt.get("AddBlocks") {
+        Some(Value::ByteArray(add_blocks)) => &add_blocks[..],
+        Some(_) => return Err(SchematicError::WrongTagType("AddBlocks")),
+        None => &[],
+    };
+    // WorldEdit saves the offset, but MCEdit doesn't
+    let offset = |tag| match nbt.get(tag) {
+        Some(Value::Int(offset)) => -offset,
+        _ => 0,
+    };
+    let volume = checked_volume(size_x, size_y, size_z)
+        .filter(|&volume| volume <= block_ids.len())
+        .ok_or(SchematicError::WrongLength("Blocks"))?;
+    if block_data.len() < volume {
+        return Err(SchematicError::WrongLength("Data"));
+    }
+    let mut palette = PaletteBuilder::default();
+    let mut states: HashMap<(u16, u8), usize> = HashMap::new();
+    let mut data = PalettedBitBuffer::with_entries(volume);
+    for index in 0..volume {
+        let mut id = block_ids[index] as u8 as u16;
+        if let Some(&add) = add_blocks.get(index >> 1) {
+            let add = add as u8 as u16;
+            id |= if index & 1 == 0 {
+                (add & 0x0F) << 8
+            } else {
+                (add & 0xF0) << 4
+            };
+        }
+        let meta = block_data[index] as u8 & 15;
+        let palette_idx = match states.get(&(id, meta)) {
+            Some(&palette_idx) => palette_idx,
+            None => {
+                let palette_idx = match legacy::block_state(id, meta) {
+                    Some(state) => palette.add(&state)?,
+                    None => palette.add_unknown(&format!("legacy block {}:{}", id, meta)),
+                };
+                states.insert((id, meta), palette_idx);
+                palette_idx
+            }
+        };
+        data.set_entry(index, palette.use_state(palette_idx));
+    }
+    let mut block_entities = HashMap::new();
+    if let Some(Value::List(tile_entities)) = nbt.get("TileEntities") {
+        for tile_entity in tile_entities {
+            let mut val = match tile_entity {
+                Value::Compound(val) => val.clone(),
+                _ => return Err(SchematicError::WrongTagType("TileEntities")),
+            };
+            let pos = BlockPos::new(
+                *get_tag!(val, "x", Value::Int),
+                *get_tag!(val, "y", Value::Int),
+                *get_tag!(val, "z", Value::Int),
+            );
+            if let Some(Value::String(id)) = val.get("id") {
+                let id = legacy::block_entity_id(id);
+                val.insert("id".to_owned(), Value::String(id));
+            }
+            read_block_entity(&mut block_entities, pos, &val);
+        }
+    }
+    let mut clipboard = WorldEditClipboard {
+        offset_x: offset("WEOffsetX"),
+        offset_y: offset("WEOffsetY"),
+        offset_z: offset("WEOffsetZ"),
+        size_x,
+        size_y,
+        size_z,
+        data,
+        block_entities,
+    };
+    connect_wires(&mut clipboard);
+    Ok(palette.finish(clipboard))
+}
+
+fn read_vec(nbt: &HashMap<String, Value>, tag: &'static str) -> SchematicResult<[i32; 3]> {
+    let vec = get_tag!(nbt, tag, Value::Compound);
+    let mut coords = [0; 3];
+    for (coord, axis) in coords.iter_mut().zip(&["x", "y", "z"]) {
+        *coord = match vec.get(*axis) {
+            Some(Value::Int(val)) => *val,
+            _ => return Err(SchematicError::WrongTagType(tag)),
+        };
+    }
+    Ok(coords)
+}
+
+/// A region of a Litematica schematic
+struct LitematicRegion<'a> {
+    nbt: &'a HashMap<String, Value>,
+    /// The corner of the region with the lowest coordinates
+    min: [i32; 3],
+    size: [u32; 3],
+}
+
+/// Reads a Litematica schematic. Its regions are combined into one clipboard that covers
+/// all of them.
+fn read_litematic(nbt: &nbt::Blob) -> SchematicResult<Schematic> {
+    let nbt_regions = get_tag!(nbt, "Regions", Value::Compound);
+    let mut regions = Vec::new();
+    for region in nbt_regions.values() {
+        let region = match region {
+            Value::Compound(region) => region,
+            _ => return Err(SchematicError::WrongTagType("Regions")),
+        };
+        let position = read_vec(region, "Position")?;
+        let size = read_vec(region, "Size")?;
+        let mut min = [0; 3];
+        let mut abs_size = [0; 3];
+        for axis in 0..3 {
+            // Negative sizes grow the region towards negative coordinates
+            let end = if size[axis] < 0 {
+                position[axis].checked_add(size[axis] + 1)
+            } else {
+                position[axis].checked_add(size[axis] - 1)
+            };
+            let end = end.ok_or(SchematicError::WrongLength("Size"))?;
+            min[axis] = position[axis].min(end);
+            abs_size[axis] = size[axis].unsigned_abs();
+        }
+        // Every block takes at least 2 bits, so the size is checked before allocating
+        let longs = get_tag!(region, "BlockStates", Value::LongArray);
+        checked_volume(abs_size[0], abs_size[1], abs_size[2])
+            .filter(|&volume| volume <= longs.len() * 32)
+            .ok_or(SchematicError::WrongLength("BlockStates"))?;
+        regions.push(LitematicRegion {
+            nbt: region,
+            min,
+            size: abs_size,
+        });
+    }
+    if regions.is_empty() {
+        return Err(SchematicError::WrongLength("Regions"));
+    }
+    // The coordinates are widened so regions at the edges of the i32 range can't overflow
+    let mut min = regions[0].min.map(i64::from);
+    let mut max = min;
+    for region in &regions {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(region.min[axis] as i64);
+            max[axis] = max[axis].max(region.min[axis] as i64 + region.size[axis] as i64);
+        }
+    }
+    let size = |axis: usize| u32::try_from(max[axis] - min[axis]).ok();
+    // Regions far apart from each other make a clipboard much bigger than the file
+    let (size_x, size_y, size_z, volume) = match (size(0), size(1), size(2)) {
+        (Some(x), Some(y), Some(z)) => match checked_volume(x, y, z) {
+            Some(volume) if volume <= MAX_LITEMATIC_VOLUME => (x, y, z, volume),
+            _ => return Err(SchematicError::WrongLength("Regions")),
+        },
+        _ => return Err(SchematicError::WrongLength("Regions")),
+    };
+    let min = min.map(|coord| coord as i32);
+    let mut data = PalettedBitBuffer::with_entries(volume);
+    let mut palette = PaletteBuilder::default();
+    let mut block_entities = HashMap::new();
+    for region in &regions {
+        let mut region_palette = Vec::new();
+        for state in get_tag!(region.nbt, "BlockStatePalette", Value::List) {
+            let state = match state {
+                Value::Compound(state) => state,
+                _ => return Err(SchematicError::WrongTagType("BlockStatePalette")),
+            };
+            let name = get_tag!(state, "Name", Value::String);
+            let mut properties = Vec::new();
+            if let Some(Value::Compound(nbt_properties)) = state.get("Properties") {
+                for (key, value) in nbt_properties {
+                    match value {
+                        Value::String(value) => properties.push(format!("{}={}", key, value)),
+                        _ => return Err(SchematicError::WrongTagType("Properties")),
+                    }
+                }
+            }
+            properties.sort();
+            let state = if properties.is_empty() {
+                name.clone()
+            } else {
+                format!("{}[{}]", name, properties.join(","))
+            };
+            region_palette.push(palette.add(&state)?);
+        }
+        let longs = get_tag!(region.nbt, "BlockStates", Value::LongArray);
+        let [rx, ry, rz] = region.size;
+        let volume = rx as usize * ry as usize * rz as usize;
+        // Entries take at least 2 bits and can be split between two longs
+        let bits = (usize::BITS - region_palette.len().saturating_sub(1).leading_zeros()).max(2);
+        if longs.len() * 64 < volume * bits as usize {
+            return Err(SchematicError::WrongLength("BlockStates"));
+        }
+        let mask = (1u64 << bits) - 1;
+        let offset = [
+            region.min[0] - min[0],
+            region.min[1] - min[1],
+            region.min[2] - min[2],
+        ];
+        let mut index = 0;
+        for y in 0..ry {
+            for z in 0..rz {
+                for x in 0..rx {
+                    let start_bit = index * bits as usize;
+                    let start_long = start_bit >> 6;
+                    let end_long = (start_bit + bits as usize - 1) >> 6;
+                    let start_offset = start_bit & 63;
+                    let mut value = longs[start_long] as u64 >> start_offset;
+                    if start_long != end_long {
+                        value |= (longs[end_long] as u64) << (64 - start_offset);
+                    }
+                    let state = (value & mask) as usize;
+                    index += 1;
+                    let palette_idx = *region_palette
+                        .get(state)
+                        .ok_or(SchematicError::UnknownPaletteId(state as u32))?;
+                    let block_id = palette.use_state(palette_idx);
+                    // Regions can overlap, so air doesn't replace what other regions set
+                    if block_id != 0 {
+                        let x = (x + offset[0] as u32) as usize;
+                        let y = (y + offset[1] as u32) as usize;
+                        let z = (z + offset[2] as u32) as usize;
+                        data.set_entry((y * size_z as usize + z) * size_x as usize + x, block_id);
+                    }
+                }
+            }
+        }
+        if let Some(Value::List(tile_entities)) = region.nbt.get("TileEntities") {
+            for tile_entity in tile_entities {
+                let val = match tile_entity {
+                    Value::Compound(val) => val,
+                    _ => return Err(SchematicError::WrongTagType("TileEntities")),
+                };
+                let pos = BlockPos::new(
+                    get_tag!(val, "x", Value::Int).wrapping_add(offset[0]),
+                    get_tag!(val, "y", Value::Int).wrapping_add(offset[1]),
+                    get_tag!(val, "z", Value::Int).wrapping_add(offset[2]),
+                );
+                read_block_entity(&mut block_entities, pos, val);
+            }
+        }
+    }
+    Ok(palette.finish(WorldEditClipboard {
+        offset_x: 0,
+        offset_y: 0,
+        offset_z: 0,
+        size_x,
+        size_y,
+        size_z,
+        data,
+        block_entities,
+    }))
+}
+
+/// Lets redstone wire in a clipboard find its neighbors
+struct ClipboardWorld<'a> {
+    clipboard: &'a mut WorldEditClipboard,
+}
+
+impl<'a> ClipboardWorld<'a> {
+    fn index(&self, pos: BlockPos) -> Option<usize> {
+        let cb = &self.clipboard;
+        if pos.x < 0
+            || pos.y < 0
+            || pos.z < 0
+            || pos.x as u32 >= cb.size_x
+            || pos.y as u32 >= cb.size_y
+            || pos.z as u32 >= cb.size_z
+        {
+            return None;
+        }
+        let (x, y, z) = (pos.x as u32, pos.y as u32, pos.z as u32);
+        Some(((y * cb.size_z + z) * cb.size_x + x) as usize)
+    }
+}
+
+impl<'a> World for ClipboardWorld<'a> {
+    fn get_block(&self, pos: BlockPos) -> Block {
+        Block::from_id(self.get_block_raw(pos))
+    }
+
+    fn get_block_raw(&self, pos: BlockPos) -> u32 {
+        match self.index(pos) {
+            Some(index) => self.clipboard.data.get_entry(index),
+            None => 0,
+        }
+    }
+
+    fn set_block(&mut self, pos: BlockPos, block: Block) -> bool {
+        self.set_block_raw(pos, block.get_id())
+    }
+
+    fn set_block_raw(&mut self, pos: BlockPos, block: u32) -> bool {
+        match self.index(pos) {
+            Some(index) => {
+                self.clipboard.data.set_entry(index, block);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn delete_block_entity(&mut self, pos: BlockPos) {
+        self.clipboard.block_entities.remove(&pos);
+    }
+
+    fn get_block_entity(&self, pos: BlockPos) -> Option<&BlockEntity> {
+        self.clipboard.block_entities.get(&pos)
+    }
+
+    fn set_block_entity(&mut self, pos: BlockPos, block_entity: BlockEntity) {
+        self.clipboard.block_entities.insert(pos, block_entity);
+    }
+
+    fn get_chunk(&self, _x: i32, _z: i32) -> Option<&Chunk> {
+        None
+    }
+
+    fn get_chunk_mut(&mut self, _x: i32, _z: i32) -> Option<&mut Chunk> {
+        None
+    }
+
+    fn tick(&mut self) {}
+
+    fn schedule_tick(&mut self, _pos: BlockPos, _delay: u32, _priority: TickPriority) {}
+
+    fn pending_tick_at(&mut self, _pos: BlockPos) -> bool {
+        false
+    }
+}
+
+/// Blocks from before 1.13 don't save which way redstone wire connects, so it is worked out
+/// from the wire's neighbors like when it is placed
+fn connect_wires(clipboard: &mut WorldEditClipboard) {
+    let (size_x, size_y, size_z) = (clipboard.size_x, clipboard.size_y, clipboard.size_z);
+    let mut world = ClipboardWorld { clipboard };
+    for y in 0..size_y as i32 {
+        for z in 0..size_z as i32 {
+            for x in 0..size_x as i32 {
+                let pos = BlockPos::new(x, y, z);
+                if let Block::RedstoneWire { wire } = world.get_block(pos) {
+                    let mut connected = RedstoneWire::get_state_for_placement(&world, pos);
+                    connected.power = wire.power;
+                    world.set_block(pos, Block::RedstoneWire { wire: connected });
+                }
+            }
+        }
+    }
+}
+
+fn list_dir(dir: &Path, prefix: &str, entries: &mut Vec<SchematicEntry>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        if file_name.starts_with('.') {
+            continue;
+        }
+        let name = format!("{}{}", prefix, file_name);
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            list_dir(&entry.path(), &format!("{}/", name), entries)?;
+        } else if SCHEMATIC_EXTENSIONS
+            .iter()
+            .any(|ext| file_name.ends_with(ext))
+        {
+            entries.push(SchematicEntry {
+                name,
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Lists the schematics in the schematics folder and its subfolders, sorted by name
+pub fn list() -> SchematicResult<Vec<SchematicEntry>> {
+    let mut entries = Vec::new();
+    match list_dir(Path::new(SCHEMATICS_DIR), "", &mut entries) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    }
+    entries.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(entries)
+}
+
+pub fn delete(name: &str) -> SchematicResult<()> {
+    match fs::remove_file(schematic_path(name)?) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            Err(SchematicError::NotFound(name.to_owned()))
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+#[test]
+fn schematic_names() {
+    assert_eq!(
+        schematic_path("cpu/alu").unwrap(),
+        PathBuf::from("./schems/cpu/alu.schem")
+    );
+    assert_eq!(
+        schematic_path("alu.schem").unwrap(),
+        PathBuf::from("./schems/alu.schem")
+    );
+    assert!(schematic_path("../Config.toml").is_err());
+    assert!(schematic_path("/etc/passwd").is_err());
+    assert!(schematic_path("cpu//alu").is_err());
+    assert!(schematic_path("cpu\\..\\alu").is_err());
+}
+
+#[test]
+fn sponge_errors() {
+    let mut nbt = nbt::Blob::new();
+    nbt.insert("Width", Value::Short(2)).unwrap();
+    nbt.insert("Length", Value::Short(1)).unwrap();
+    nbt.insert("Height", Value::Short(1)).unwrap();
+    let mut palette = HashMap::new();
+    palette.insert("minecraft:air".to_owned(), Value::Int(0));
+    palette.insert("minecraft:sandstone".to_owned(), Value::Int(1));
+    palette.insert("minecraft:not_a_block".to_owned(), Value::Int(200));
+    nbt.insert("Palette", Value::Compound(palette)).unwrap();
+    assert!(matches!(
+        read_sponge(&nbt),
+        Err(SchematicError::MissingTag("Metadata"))
+    ));
+
+    let mut metadata = HashMap::new();
+    for tag in &["WEOffsetX", "WEOffsetY", "WEOffsetZ"] {
+        metadata.insert(tag.to_string(), Value::Int(0));
+    }
+    nbt.insert("Metadata", Value::Compound(metadata)).unwrap();
+    // 200 takes two bytes as a varint, and the second one is missing
+    nbt.insert("BlockData", Value::ByteArray(vec![1, 200u8 as i8]))
+        .unwrap();
+    assert!(matches!(
+        read_sponge(&nbt),
+        Err(SchematicError::BadVarint(1))
+    ));
+
+    nbt.insert("BlockData", Value::ByteArray(vec![1, 200u8 as i8, 1]))
+        .unwrap();
+    let schematic = read_sponge(&nbt).unwrap();
+    assert_eq!(schematic.unknown_blocks, vec!["not_a_block".to_owned()]);
+    assert_eq!(
+        Block::from_id(schematic.clipboard.data.get_entry(0)),
+        Block::from_name("sandstone").unwrap()
+    );
+    assert_eq!(
+        Block::from_id(schematic.clipboard.data.get_entry(1)),
+        Block::Air {}
+    );
+
+    // The size is checked against the block data before anything is allocated
+    nbt.insert("Height", Value::Short(-1)).unwrap();
+    assert!(matches!(
+        read_sponge(&nbt),
+        Err(SchematicError::WrongLength("BlockData"))
+    ));
+}
+
+#[test]
+fn mcedit_wires() {
+    let mut nbt = nbt::Blob::new();
+    nbt.insert("Width", Value::Short(3)).unwrap();
+    nbt.insert("Length", Value::Short(1)).unwrap();
+    nbt.insert("Height", Value::Short(1)).unwrap();
+    nbt.insert("Materials", Value::String("Alpha".to_owned()))
+        .unwrap();
+    nbt.insert("Blocks", Value::ByteArray(vec![55, 55, 1]))
+        .unwrap();
+    nbt.insert("Data", Value::ByteArray(vec![15, 14, 0]))
+        .unwrap();
+    let schematic = read_mcedit(&nbt).unwrap();
+    assert_eq!(schematic.unknown_blocks, vec!["stone".to_owned()]);
+    match Block::from_id(schematic.clipboard.data.get_entry(1)) {
+        Block::RedstoneWire { wire } => {
+            assert_eq!(wire.power, 14);
+            assert!(!wire.west.is_none() && !wire.east.is_none());
+            assert!(wire.north.is_none() && wire.south.is_none());
+        }
+        block => panic!("expected redstone wire, got {:?}", block),
+    }
+}
+
+#[test]
+fn litematic_regions() {
+    let vec = |x, y, z| {
+        let mut vec = HashMap::new();
+        vec.insert("x".to_owned(), Value::Int(x));
+        vec.insert("y".to_owned(), Value::Int(y));
+        vec.insert("z".to_owned(), Value::Int(z));
+        Value::Compound(vec)
+    };
+    let state = |name: &str| {
+        let mut state = HashMap::new();
+        state.insert("Name".to_owned(), Value::String(name.to_owned()));
+        Value::Compound(state)
+    };
+    let mut region = HashMap::new();
+    // The region grows towards negative x, from x = 0 to x = -2
+    region.insert("Position".to_owned(), vec(0, 0, 0));
+    region.insert("Size".to_owned(), vec(-3, 1, 1));
+    region.insert(
+        "BlockStatePalette".to_owned(),
+        Value::List(vec![state("minecraft:air"), state("minecraft:sandstone")]),
+    );
+    // Two bits per block: sandstone, air, sandstone
+    region.insert("BlockStates".to_owned(), Value::LongArray(vec![0b01_00_01]));
+    let mut regions = HashMap::new();
+    regions.insert("main".to_owned(), Value::Compound(region.clone()));
+    let mut nbt = nbt::Blob::new();
+    nbt.insert("Regions", Value::Compound(regions.clone()))
+        .unwrap();
+
+    let schematic = read_litematic(&nbt).unwrap();
+    let clipboard = &schematic.clipboard;
+    assert_eq!(
+        (clipboard.size_x, clipboard.size_y, clipboard.size_z),
+        (3, 1, 1)
+    );
+    let sandstone = Block::from_name("sandstone").unwrap().get_id();
+    assert_eq!(clipboard.data.get_entry(0), sandstone);
+    assert_eq!(clipboard.data.get_entry(1), 0);
+    assert_eq!(clipboard.data.get_entry(2), sandstone);
+    assert_eq!(schematic.palette[0], ("sandstone".to_owned(), 2));
+
+    let mut far = region.clone();
+    // Regions at opposite ends of the world would need a huge clipboard
+    far.insert("Position".to_owned(), vec(i32::MAX, 0, 0));
+    far.insert("Size".to_owned(), vec(1, 1, 1));
+    regions.insert("far".to_owned(), Value::Compound(far.clone()));
+    nbt.insert("Regions", Value::Compound(regions.clone()))
+        .unwrap();
+    assert!(matches!(
+        read_litematic(&nbt),
+        Err(SchematicError::WrongLength("Regions"))
+    ));
+
+    // The end of this region is past i32::MAX
+    far.insert("Size".to_owned(), vec(2, 1, 1));
+    regions.insert("far".to_owned(), Value::Compound(far));
+    nbt.insert("Regions", Value::Compound(regions)).unwrap();
+    assert!(matches!(
+        read_litematic(&nbt),
+        Err(SchematicError::WrongLength("Size"))
+    ));
+}
@@ -1,13 +1,18 @@
-use super::Plot;
-use crate::blocks::{Block, BlockEntity, BlockFacing, BlockPos};
+use super::geometry::PLOT_GEOMETRY;
+use super::{clipboards, database, schematic, Plot};
+use crate::blocks::{Block, BlockEntity, BlockFace, BlockFacing, BlockPos};
+use crate::config::CONFIG;
+use crate::items::Item;
 use crate::player::Player;
 use crate::world::storage::PalettedBitBuffer;
 use crate::world::World;
+use chrono::{DateTime, Local};
 use rand::Rng;
 use regex::Regex;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
 use std::fmt;
-use std::fs::File;
 use std::ops::RangeInclusive;
 use std::time::Instant;
 
@@ -18,19 +23,23 @@ pub fn execute_command(
     command: &str,
     args: &mut Vec<&str>,
 ) -> bool {
-    let command = if let Some(command) = COMMANDS.get(command) {
-        command
+    let (name, command) = if let Some((name, command)) = COMMANDS.get_key_value(command) {
+        (*name, command)
     } else if let Some(command) = ALIASES.get(command) {
         let mut alias: Vec<&str> = command.split(' ').collect();
         let command = alias.remove(0);
         if alias.len() > 1 {
             args.append(&mut alias);
         }
-        &COMMANDS[command]
+        (command, &COMMANDS[command])
     } else {
         return false;
     };
 
+    if !plot.check_permission(player_idx, &format!("mchprs.worldedit.{}", name)) {
+        return true;
+    }
+
     let mut ctx = CommandExecuteContext {
         plot,
         player_idx,
@@ -38,9 +47,14 @@ pub fn execute_command(
         flags: Vec::new(),
     };
 
+    if command.modifies_world && !ctx.plot.can_build(player_idx) {
+        ctx.get_player_mut()
+            .send_error_message("You are not allowed to build in this plot.");
+        return true;
+    }
+
     if command.requires_positions {
-        let plot_x = ctx.plot.x;
-        let plot_z = ctx.plot.z;
+        let region = ctx.plot.region;
         let player = ctx.get_player_mut();
         if player.first_position.is_none() || player.second_position.is_none() {
             player.send_error_message("Make a region selection first.");
@@ -48,11 +62,11 @@ pub fn execute_command(
         }
         let first_pos = player.first_position.unwrap();
         let second_pos = player.second_position.unwrap();
-        if !Plot::in_plot_bounds(plot_x, plot_z, first_pos.x, first_pos.z) {
+        if !Plot::in_plot_bounds(&region, first_pos.x, first_pos.z) {
             player.send_system_message("First position is outside plot bounds!");
             return true;
         }
-        if !Plot::in_plot_bounds(plot_x, plot_z, second_pos.x, second_pos.z) {
+        if !Plot::in_plot_bounds(&region, second_pos.x, second_pos.z) {
             player.send_system_message("Second position is outside plot bounds!");
             return true;
         }
@@ -109,7 +123,7 @@ pub fn execute_command(
     }
 
     for (i, arg_desc) in arg_descs.iter().enumerate() {
-        let arg = args.get(i).map(|s| *s);
+        let arg = args.get(i).map(|s| *s).or(arg_desc.default);
         match Argument::parse(&ctx, arg_desc.argument_type, arg) {
             Ok(default_arg) => ctx.arguments.push(default_arg),
             Err(err) => {
@@ -119,6 +133,9 @@ pub fn execute_command(
         }
     }
 
+    if command.modifies_world {
+        ctx.plot.modified = true;
+    }
     (command.execute_fn)(ctx);
     true
 }
@@ -156,15 +173,20 @@ type ArgumentParseResult = Result<Argument, ArgumentParseError>;
 enum ArgumentType {
     UnsignedInteger,
     Direction,
+    Vector,
     Mask,
     Pattern,
+    String,
 }
 
 enum Argument {
     UnsignedInteger(u32),
     Direction(BlockFacing),
+    /// A direction that can be diagonal, made up of at most one direction per axis
+    Vector(Vec<BlockFacing>),
     Pattern(WorldEditPattern),
-    Mask(WorldEditPattern),
+    Mask(WorldEditMask),
+    String(String),
 }
 
 impl Argument {
@@ -182,6 +204,13 @@ impl Argument {
         }
     }
 
+    fn unwrap_vector(&self) -> &[BlockFacing] {
+        match self {
+            Argument::Vector(val) => val,
+            _ => panic!("Argument was not a Vector"),
+        }
+    }
+
     fn unwrap_pattern(&self) -> &WorldEditPattern {
         match self {
             Argument::Pattern(val) => val,
@@ -189,16 +218,25 @@ impl Argument {
         }
     }
 
-    fn unwrap_mask(&self) -> &WorldEditPattern {
+    fn unwrap_mask(&self) -> &WorldEditMask {
         match self {
             Argument::Mask(val) => val,
             _ => panic!("Argument was not a Mask"),
         }
     }
 
+    fn unwrap_string(&self) -> &str {
+        match self {
+            Argument::String(val) => val,
+            _ => panic!("Argument was not a String"),
+        }
+    }
+
     fn get_default(ctx: &CommandExecuteContext, arg_type: ArgumentType) -> ArgumentParseResult {
         match arg_type {
-            ArgumentType::Direction => Argument::parse(ctx, arg_type, Some("me")),
+            ArgumentType::Direction | ArgumentType::Vector => {
+                Argument::parse(ctx, arg_type, Some("me"))
+            }
             ArgumentType::UnsignedInteger => Ok(Argument::UnsignedInteger(1)),
             _ => Err(ArgumentParseError::new(
                 arg_type,
@@ -221,8 +259,34 @@ impl Argument {
                 let player_facing = ctx.get_player().get_facing();
                 match arg {
                     "me" => Ok(Argument::Direction(player_facing)),
-                    _ => Err(ArgumentParseError::new(arg_type, "unknown direction")),
+                    _ => match parse_facing(arg) {
+                        Some(facing) => Ok(Argument::Direction(facing)),
+                        None => Err(ArgumentParseError::new(arg_type, "unknown direction")),
+                    },
+                }
+            }
+            ArgumentType::Vector => {
+                if arg == "me" {
+                    return Ok(Argument::Vector(vec![ctx.get_player().get_facing()]));
+                }
+                let mut facings: Vec<BlockFacing> = Vec::new();
+                for name in arg.split('-') {
+                    let facing = match parse_facing(name) {
+                        Some(facing) => facing,
+                        None => return Err(ArgumentParseError::new(arg_type, "unknown direction")),
+                    };
+                    if facings
+                        .iter()
+                        .any(|f| facing_axis(*f) == facing_axis(facing))
+                    {
+                        return Err(ArgumentParseError::new(
+                            arg_type,
+                            "only one direction per axis can be used",
+                        ));
+                    }
+                    facings.push(facing);
                 }
+                Ok(Argument::Vector(facings))
             }
             ArgumentType::UnsignedInteger => match arg.parse::<u32>() {
                 Ok(num) => Ok(Argument::UnsignedInteger(num)),
@@ -232,11 +296,11 @@ impl Argument {
                 Ok(pattern) => Ok(Argument::Pattern(pattern)),
                 Err(err) => Err(ArgumentParseError::new(arg_type, &err.to_string())),
             },
-            // Masks are net yet implemented, so in the meantime they can be treated as patterns
-            ArgumentType::Mask => match WorldEditPattern::from_str(arg) {
+            ArgumentType::Mask => match WorldEditMask::from_str(arg) {
                 Ok(pattern) => Ok(Argument::Mask(pattern)),
                 Err(err) => Err(ArgumentParseError::new(arg_type, &err.to_string())),
             },
+            ArgumentType::String => Ok(Argument::String(arg.to_owned())),
         }
     }
 }
@@ -245,6 +309,8 @@ struct ArgumentDescription {
     name: &'static str,
     argument_type: ArgumentType,
     description: &'static str,
+    /// The value used when the argument is left out, instead of the type's default
+    default: Option<&'static str>,
 }
 
 macro_rules! argument {
@@ -253,6 +319,15 @@ macro_rules! argument {
             name: $name,
             argument_type: ArgumentType::$type,
             description: $desc,
+            default: None,
+        }
+    };
+    ($name:literal, $type:ident, $desc:literal, $default:literal) => {
+        ArgumentDescription {
+            name: $name,
+            argument_type: ArgumentType::$type,
+            description: $desc,
+            default: Some($default),
         }
     };
 }
@@ -299,6 +374,7 @@ struct WorldeditCommand {
     flags: &'static [FlagDescription],
     requires_positions: bool,
     requires_clipboard: bool,
+    modifies_world: bool,
     execute_fn: fn(CommandExecuteContext),
     description: &'static str,
 }
@@ -312,6 +388,7 @@ impl Default for WorldeditCommand {
             description: "",
             requires_clipboard: false,
             requires_positions: false,
+            modifies_world: false,
         }
     }
 }
@@ -339,12 +416,14 @@ lazy_static! {
         "cut" => WorldeditCommand {
             requires_positions: true,
             execute_fn: execute_cut,
+            modifies_world: true,
             description: "Cut the selection to the clipboard",
             ..Default::default()
         },
         "paste" => WorldeditCommand {
             requires_clipboard: true,
             execute_fn: execute_paste,
+            modifies_world: true,
             description: "Paste the clipboard's contents",
             flags: &[
                 flag!('a', None, "Skip air blocks")
@@ -353,6 +432,7 @@ lazy_static! {
         },
         "undo" => WorldeditCommand {
             execute_fn: execute_undo,
+            modifies_world: true,
             description: "Undo's the last action (from history)",
             ..Default::default()
         },
@@ -363,12 +443,29 @@ lazy_static! {
             ],
             requires_positions: true,
             execute_fn: execute_stack,
+            modifies_world: true,
             description: "Repeat the contents of the selection",
             flags: &[
                 flag!('a', None, "Ignore air blocks")
             ],
             ..Default::default()
         },
+        "rstack" => WorldeditCommand {
+            arguments: &[
+                argument!("count", UnsignedInteger, "# of copies to stack"),
+                argument!("spacing", UnsignedInteger, "The gap between each copy", "0"),
+                argument!("direction", Vector, "The direction to stack, such as up-north")
+            ],
+            requires_positions: true,
+            execute_fn: execute_rstack,
+            modifies_world: true,
+            description: "Repeat the contents of the selection with a gap and diagonally",
+            flags: &[
+                flag!('a', None, "Ignore air blocks"),
+                flag!('e', None, "Expand the selection to cover the stacked copies")
+            ],
+            ..Default::default()
+        },
         "move" => WorldeditCommand {
             arguments: &[
                 argument!("count", UnsignedInteger, "The distance to move"),
@@ -376,6 +473,7 @@ lazy_static! {
             ],
             requires_positions: true,
             execute_fn: execute_move,
+            modifies_world: true,
             description: "Move the contents of the selection",
             flags: &[
                 flag!('a', None, "Ignore air blocks"),
@@ -383,6 +481,68 @@ lazy_static! {
             ],
             ..Default::default()
         },
+        "expand" => WorldeditCommand {
+            arguments: &[
+                argument!("amount", UnsignedInteger, "The amount to expand the selection by"),
+                argument!("direction", Direction, "The direction to expand")
+            ],
+            requires_positions: true,
+            execute_fn: execute_expand,
+            description: "Expand the selection area",
+            ..Default::default()
+        },
+        "contract" => WorldeditCommand {
+            arguments: &[
+                argument!("amount", UnsignedInteger, "The amount to contract the selection by"),
+                argument!("direction", Direction, "The direction to contract")
+            ],
+            requires_positions: true,
+            execute_fn: execute_contract,
+            description: "Contract the selection area",
+            ..Default::default()
+        },
+        "shift" => WorldeditCommand {
+            arguments: &[
+                argument!("amount", UnsignedInteger, "The amount to shift the selection by"),
+                argument!("direction", Direction, "The direction to shift")
+            ],
+            requires_positions: true,
+            execute_fn: execute_shift,
+            description: "Shift the selection area",
+            ..Default::default()
+        },
+        "outset" => WorldeditCommand {
+            arguments: &[
+                argument!("amount", UnsignedInteger, "The amount to expand the selection by")
+            ],
+            requires_positions: true,
+            execute_fn: execute_outset,
+            description: "Expand the selection area in every direction",
+            flags: &[
+                flag!('h', None, "Only expand horizontally"),
+                flag!('v', None, "Only expand vertically")
+            ],
+            ..Default::default()
+        },
+        "inset" => WorldeditCommand {
+            arguments: &[
+                argument!("amount", UnsignedInteger, "The amount to contract the selection by")
+            ],
+            requires_positions: true,
+            execute_fn: execute_inset,
+            description: "Contract the selection area in every direction",
+            flags: &[
+                flag!('h', None, "Only contract horizontally"),
+                flag!('v', None, "Only contract vertically")
+            ],
+            ..Default::default()
+        },
+        "size" => WorldeditCommand {
+            requires_positions: true,
+            execute_fn: execute_size,
+            description: "Get information about the selection",
+            ..Default::default()
+        },
         "count" => WorldeditCommand {
             arguments: &[
                 argument!("mask", Mask, "The mask of blocks to match")
@@ -403,9 +563,123 @@ lazy_static! {
             ],
             requires_positions: true,
             execute_fn: execute_set,
+            modifies_world: true,
             description: "Sets all the blocks in the region",
             ..Default::default()
         },
+        "walls" => WorldeditCommand {
+            arguments: &[
+                argument!("pattern", Pattern, "The pattern of blocks to set")
+            ],
+            requires_positions: true,
+            execute_fn: execute_walls,
+            modifies_world: true,
+            description: "Build the four sides of the selection",
+            ..Default::default()
+        },
+        "faces" => WorldeditCommand {
+            arguments: &[
+                argument!("pattern", Pattern, "The pattern of blocks to set")
+            ],
+            requires_positions: true,
+            execute_fn: execute_faces,
+            modifies_world: true,
+            description: "Build the walls, ceiling, and floor of a selection",
+            ..Default::default()
+        },
+        "line" => WorldeditCommand {
+            arguments: &[
+                argument!("pattern", Pattern, "The pattern of blocks to set")
+            ],
+            requires_positions: true,
+            execute_fn: execute_line,
+            modifies_world: true,
+            description: "Draws a line between the two positions",
+            ..Default::default()
+        },
+        "cyl" => WorldeditCommand {
+            arguments: &[
+                argument!("pattern", Pattern, "The pattern of blocks to set"),
+                argument!("radius", UnsignedInteger, "The radius of the cylinder"),
+                argument!("height", UnsignedInteger, "The height of the cylinder")
+            ],
+            execute_fn: execute_cyl,
+            modifies_world: true,
+            description: "Generates a cylinder",
+            ..Default::default()
+        },
+        "hcyl" => WorldeditCommand {
+            arguments: &[
+                argument!("pattern", Pattern, "The pattern of blocks to set"),
+                argument!("radius", UnsignedInteger, "The radius of the cylinder"),
+                argument!("height", UnsignedInteger, "The height of the cylinder")
+            ],
+            execute_fn: execute_hcyl,
+            modifies_world: true,
+            description: "Generates a hollow cylinder",
+            ..Default::default()
+        },
+        "sphere" => WorldeditCommand {
+            arguments: &[
+                argument!("pattern", Pattern, "The pattern of blocks to set"),
+                argument!("radius", UnsignedInteger, "The radius of the sphere")
+            ],
+            execute_fn: execute_sphere,
+            modifies_world: true,
+            description: "Generates a filled sphere",
+            ..Default::default()
+        },
+        "hsphere" => WorldeditCommand {
+            arguments: &[
+                argument!("pattern", Pattern, "The pattern of blocks to set"),
+                argument!("radius", UnsignedInteger, "The radius of the sphere")
+            ],
+            execute_fn: execute_hsphere,
+            modifies_world: true,
+            description: "Generates a hollow sphere",
+            ..Default::default()
+        },
+        "brush" => WorldeditCommand {
+            arguments: &[
+                argument!("shape", String, "The shape of the brush"),
+                argument!("pattern", String, "The pattern of blocks to set"),
+                argument!("radius", UnsignedInteger, "The radius of the brush", "2")
+            ],
+            execute_fn: execute_brush,
+            description: "Bind a brush to the held item",
+            flags: &[
+                flag!('h', None, "Create hollow spheres instead")
+            ],
+            ..Default::default()
+        },
+        "tool" => WorldeditCommand {
+            arguments: &[
+                argument!("tool", String, "The tool to bind: replacer, info or none"),
+                argument!("pattern", String, "The pattern of blocks the replacer sets", "")
+            ],
+            execute_fn: execute_tool,
+            description: "Bind a tool to the held item",
+            ..Default::default()
+        },
+        "clipboard" => WorldeditCommand {
+            arguments: &[
+                argument!("action", String, "The action: save, load, share, list or delete"),
+                argument!("name", String, "The name of the clipboard or the player to share with", ""),
+                argument!("share_name", String, "The name the shared clipboard is saved under", "")
+            ],
+            execute_fn: execute_clipboard,
+            description: "Save, load and share named clipboards",
+            ..Default::default()
+        },
+        "schem" => WorldeditCommand {
+            arguments: &[
+                argument!("action", String, "The action: load, list, info or delete"),
+                argument!("name", String, "The name of the schematic or the page to list", "")
+            ],
+            execute_fn: execute_schem,
+            description: "Load, list, inspect and delete schematics",
+            ..Default::default()
+        },
         "pos1" => WorldeditCommand {
             execute_fn: execute_pos1,
             description: "Set position 1",
@@ -423,6 +697,7 @@ lazy_static! {
             ],
             requires_positions: true,
             execute_fn: execute_replace,
+            modifies_world: true,
             description: "Replace all blocks in a selection with another",
             ..Default::default()
         }
@@ -438,7 +713,9 @@ lazy_static! {
         "v" => "paste",
         "va" => "paste -a",
         "s" => "stack",
-        "sa" => "stack -a"
+        "sa" => "stack -a",
+        "rs" => "rstack",
+        "outline" => "faces"
     };
 }
 
@@ -461,104 +738,30 @@ pub struct WorldEditClipboard {
 
 #[derive(Clone, Debug)]
 pub struct WorldEditUndo {
-    clipboard: WorldEditClipboard,
-    pos: BlockPos,
+    /// The blocks of each changed area before the change, and where to paste them back
+    areas: Vec<(BlockPos, WorldEditClipboard)>,
     plot_x: i32,
     plot_z: i32,
 }
 
-impl WorldEditClipboard {
-    fn load_from_schematic(file_name: &str) -> Option<WorldEditClipboard> {
-        // I greaty dislike this
-        let mut file = match File::open("./schems/".to_owned() + file_name + ".schem") {
-            Ok(file) => file,
-            Err(_) => return None,
-        };
-        let nbt = match nbt::Blob::from_gzip_reader(&mut file) {
-            Ok(blob) => blob,
-            Err(_) => return None,
-        };
-        use nbt::Value;
-        let size_x = nbt_unwrap_val!(nbt["Width"], Value::Short) as u32;
-        let size_z = nbt_unwrap_val!(nbt["Length"], Value::Short) as u32;
-        let size_y = nbt_unwrap_val!(nbt["Height"], Value::Short) as u32;
-        let nbt_palette = nbt_unwrap_val!(&nbt["Palette"], Value::Compound);
-        let metadata = nbt_unwrap_val!(&nbt["Metadata"], Value::Compound);
-        let offset_x = -nbt_unwrap_val!(metadata["WEOffsetX"], Value::Int);
-        let offset_y = -nbt_unwrap_val!(metadata["WEOffsetY"], Value::Int);
-        let offset_z = -nbt_unwrap_val!(metadata["WEOffsetZ"], Value::Int);
-        lazy_static! {
-            static ref RE: Regex =
-                Regex::new(r"minecraft:([a-z_]+)(?:\[([a-z=,0-9]+)\])?").unwrap();
-        }
-        let mut palette: HashMap<u32, u32> = HashMap::new();
-        for (k, v) in nbt_palette {
-            let id = *nbt_unwrap_val!(v, Value::Int) as u32;
-            let captures = RE.captures(&k)?;
-            let mut block = Block::from_name(captures.get(1)?.as_str()).unwrap_or(Block::Air {});
-            if let Some(properties_match) = captures.get(2) {
-                let properties: Vec<&str> =
-                    properties_match.as_str().split(&[',', '='][..]).collect();
-                for prop_idx in (0..properties.len()).step_by(2) {
-                    block.set_property(properties[prop_idx], properties[prop_idx + 1]);
-                }
-            }
-            palette.insert(id, block.get_id());
-        }
-        let blocks: Vec<u8> = nbt_unwrap_val!(&nbt["BlockData"], Value::ByteArray)
+impl WorldEditUndo {
+    /// The number of blocks kept to undo the change
+    fn volume(&self) -> usize {
+        self.areas
             .iter()
-            .map(|b| *b as u8)
-            .collect();
-        let mut data = PalettedBitBuffer::with_entries((size_x * size_y * size_z) as usize);
-        let mut i = 0;
-        for y_offset in (0..size_y).map(|y| y * size_z * size_x) {
-            for z_offset in (0..size_z).map(|z| z * size_x) {
-                for x in 0..size_x {
-                    let mut blockstate_id = 0;
-                    // Max varint length is 5
-                    for varint_len in 0..=5 {
-                        blockstate_id |= ((blocks[i] & 127) as u32) << (varint_len * 7);
-                        if (blocks[i] & 128) != 128 {
-                            i += 1;
-                            break;
-                        }
-                        i += 1;
-                    }
-                    let entry = *palette.get(&blockstate_id).unwrap();
-                    data.set_entry((y_offset + z_offset + x) as usize, entry);
-                }
-            }
-        }
-        let block_entities = nbt_unwrap_val!(&nbt["BlockEntities"], Value::List);
-        let mut parsed_block_entities = HashMap::new();
-        for block_entity in block_entities {
-            let val = nbt_unwrap_val!(block_entity, Value::Compound);
-            let pos_array = nbt_unwrap_val!(&val["Pos"], Value::IntArray);
-            let pos = BlockPos {
-                x: pos_array[0],
-                y: pos_array[1],
-                z: pos_array[2],
-            };
-            if let Some(parsed) = BlockEntity::from_nbt(val) {
-                parsed_block_entities.insert(pos, parsed);
-            }
-        }
-        Some(WorldEditClipboard {
-            size_x,
-            size_y,
-            size_z,
-            offset_x,
-            offset_y,
-            offset_z,
-            data,
-            block_entities: parsed_block_entities,
-        })
+            .map(|(_, clipboard)| {
+                clipboard.size_x as usize * clipboard.size_y as usize * clipboard.size_z as usize
+            })
+            .sum()
     }
 }
 
+#[derive(Debug)]
 pub enum PatternParseError {
     UnknownBlock(String),
     InvalidPattern(String),
+    /// The block doesn't have the property, or the property can't have the value
+    InvalidProperty(String),
 }
 
 impl fmt::Display for PatternParseError {
@@ -566,6 +769,9 @@ impl fmt::Display for PatternParseError {
         match self {
             PatternParseError::UnknownBlock(block) => write!(f, "unknown block: {}", block),
             PatternParseError::InvalidPattern(pattern) => write!(f, "invalid pattern: {}", pattern),
+            PatternParseError::InvalidProperty(property) => {
+                write!(f, "invalid property for the block: {}", property)
+            }
         }
     }
 }
@@ -576,56 +782,110 @@ pub struct WorldEditPattern {
     pub parts: Vec<WorldEditPatternPart>,
 }
 
-impl WorldEditPattern {
-    pub fn from_str(pattern_str: &str) -> PatternParseResult<WorldEditPattern> {
-        let mut pattern = WorldEditPattern { parts: Vec::new() };
-        for part in pattern_str.split(',') {
-            lazy_static! {
-                static ref RE: Regex = Regex::new(r"^(([0-9]+(\.[0-9]+)?)%)?(=)?([0-9]+|(minecraft:)?[a-zA-Z_]+)(:([0-9]+)|\[(([a-zA-Z_]+=[a-zA-Z0-9]+,?)+?)\])?((\|([^|]*?)){1,4})?$").unwrap();
-            }
-            let pattern_match = RE
-                .captures(part)
-                .ok_or(PatternParseError::InvalidPattern(part.to_owned()))?;
-
-            let block = if pattern_match.get(4).is_some() {
-                Block::from_id(
-                    pattern_match
-                        .get(5)
-                        .map_or("0", |m| m.as_str())
-                        .parse::<u32>()
-                        .unwrap(),
-                )
-            } else {
-                let block_name = pattern_match
-                    .get(5)
-                    .unwrap()
-                    .as_str()
-                    .trim_start_matches("minecraft:");
-                Block::from_name(block_name)
-                    .ok_or_else(|| PatternParseError::UnknownBlock(part.to_owned()))?
-            };
+/// A block of a pattern or mask, such as `50%repeater[delay=4]`
+struct ParsedBlock {
+    weight: f32,
+    block: Block,
+    /// The properties that were given in brackets, which are already applied to `block`
+    properties: Vec<(String, String)>,
+    /// True if the block was given as a numeric state id, which sets every property
+    by_id: bool,
+}
 
-            let weight = pattern_match
-                .get(2)
-                .map_or("100", |m| m.as_str())
-                .parse::<f32>()
+impl ParsedBlock {
+    fn from_str(part: &str) -> PatternParseResult<ParsedBlock> {
+        lazy_static! {
+            static ref RE: Regex = Regex::new(r"^(([0-9]+(\.[0-9]+)?)%)?(=)?([0-9]+|(minecraft:)?[a-zA-Z_]+)(:([0-9]+)|\[(([a-zA-Z_]+=[a-zA-Z0-9]+,?)+?)\])?((\|([^|]*?)){1,4})?$").unwrap();
+        }
+        let pattern_match = RE
+            .captures(part)
+            .ok_or_else(|| PatternParseError::InvalidPattern(part.to_owned()))?;
+
+        let by_id = pattern_match.get(4).is_some();
+        let mut block = if by_id {
+            Block::from_id(
+                pattern_match
+                    .get(5)
+                    .map_or("0", |m| m.as_str())
+                    .parse::<u32>()
+                    .unwrap(),
+            )
+        } else {
+            let block_name = pattern_match
+                .get(5)
                 .unwrap()
-                / 100.0;
+                .as_str()
+                .trim_start_matches("minecraft:");
+            Block::from_name(block_name)
+                .ok_or_else(|| PatternParseError::UnknownBlock(part.to_owned()))?
+        };
+
+        let mut properties = Vec::new();
+        if let Some(props) = pattern_match.get(9) {
+            for prop in props.as_str().split(',').filter(|p| !p.is_empty()) {
+                let (key, val) = prop.split_at(prop.find('=').unwrap());
+                let val = &val[1..];
+                // Properties that don't apply would be ignored, so the mask would match
+                // blocks with any value of them
+                let values = block.property_values(key).unwrap_or_default();
+                if !values.contains(&val) {
+                    return Err(PatternParseError::InvalidProperty(prop.to_owned()));
+                }
+                block.set_property(key, val);
+                properties.push((key.to_owned(), val.to_owned()));
+            }
+        }
+
+        let weight = pattern_match
+            .get(2)
+            .map_or("100", |m| m.as_str())
+            .parse::<f32>()
+            .unwrap()
+            / 100.0;
+
+        Ok(ParsedBlock {
+            weight,
+            block,
+            properties,
+            by_id,
+        })
+    }
+}
+
+/// Splits a list of blocks on the commas that aren't inside of a property list
+fn split_block_list(list: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in list.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&list[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&list[start..]);
+    parts
+}
 
+impl WorldEditPattern {
+    pub fn from_str(pattern_str: &str) -> PatternParseResult<WorldEditPattern> {
+        let mut pattern = WorldEditPattern { parts: Vec::new() };
+        for part in split_block_list(pattern_str) {
+            let parsed = ParsedBlock::from_str(part)?;
             pattern.parts.push(WorldEditPatternPart {
-                weight,
-                block_id: block.get_id(),
+                weight: parsed.weight,
+                block_id: parsed.block.get_id(),
             });
         }
 
         Ok(pattern)
     }
 
-    pub fn matches(&self, block: Block) -> bool {
-        let block_id = block.get_id();
-        self.parts.iter().any(|part| part.block_id == block_id)
-    }
-
     pub fn pick(&self) -> Block {
         let mut weight_sum = 0.0;
         for part in &self.parts {
@@ -652,6 +912,97 @@ impl WorldEditPattern {
     }
 }
 
+pub enum WorldEditMaskPart {
+    /// Matches every block of the same type that has all of the properties
+    Block {
+        block: Block,
+        properties: Vec<(String, String)>,
+    },
+    /// Matches a single block state
+    State(u32),
+    /// `#existing`, which matches every block except air
+    Existing,
+    /// `#solid`, which matches blocks that redstone can power through
+    Solid,
+}
+
+impl WorldEditMaskPart {
+    fn matches(&self, block: Block) -> bool {
+        match self {
+            WorldEditMaskPart::Block {
+                block: mask_block,
+                properties,
+            } => {
+                same_block_type(*mask_block, block)
+                    && properties.iter().all(|(key, val)| {
+                        let mut expected = block;
+                        expected.set_property(key, val);
+                        expected == block
+                    })
+            }
+            WorldEditMaskPart::State(id) => block.get_id() == *id,
+            WorldEditMaskPart::Existing => block != (Block::Air {}),
+            WorldEditMaskPart::Solid => block.is_solid(),
+        }
+    }
+}
+
+/// Returns true if both blocks are the same type of block, whatever their properties are.
+/// Some variants, like wool, are used for several types of blocks.
+fn same_block_type(a: Block, b: Block) -> bool {
+    match (a, b) {
+        (Block::Concrete { color: a }, Block::Concrete { color: b })
+        | (Block::StainedGlass { color: a }, Block::StainedGlass { color: b })
+        | (Block::ColoredTerracotta { color: a }, Block::ColoredTerracotta { color: b })
+        | (Block::Wool { color: a }, Block::Wool { color: b }) => a == b,
+        (Block::Sign { sign_type: a, .. }, Block::Sign { sign_type: b, .. })
+        | (Block::WallSign { sign_type: a, .. }, Block::WallSign { sign_type: b, .. }) => a == b,
+        _ => std::mem::discriminant(&a) == std::mem::discriminant(&b),
+    }
+}
+
+/// A list of blocks to match, such as `repeater[delay=4],#solid`. A leading `!` matches
+/// every block that isn't in the list instead.
+pub struct WorldEditMask {
+    pub parts: Vec<WorldEditMaskPart>,
+    pub negated: bool,
+}
+
+impl WorldEditMask {
+    pub fn from_str(mask_str: &str) -> PatternParseResult<WorldEditMask> {
+        let negated = mask_str.starts_with('!');
+        let mask_str = mask_str.trim_start_matches('!');
+        let mut mask = WorldEditMask {
+            parts: Vec::new(),
+            negated,
+        };
+        for part in split_block_list(mask_str) {
+            let part = match part {
+                "#existing" => WorldEditMaskPart::Existing,
+                "#solid" => WorldEditMaskPart::Solid,
+                _ => {
+                    let parsed = ParsedBlock::from_str(part)?;
+                    if parsed.by_id {
+                        WorldEditMaskPart::State(parsed.block.get_id())
+                    } else {
+                        WorldEditMaskPart::Block {
+                            block: parsed.block,
+                            properties: parsed.properties,
+                        }
+                    }
+                }
+            };
+            mask.parts.push(part);
+        }
+
+        Ok(mask)
+    }
+
+    pub fn matches(&self, block: Block) -> bool {
+        self.parts.iter().any(|part| part.matches(block)) != self.negated
+    }
+}
+
 struct ChunkChangedRecord {
     chunk_x: i32,
     chunk_z: i32,
@@ -781,37 +1132,63 @@ fn execute_set(mut ctx: CommandExecuteContext) {
     ));
 }
 
-fn execute_replace(mut ctx: CommandExecuteContext) {
-    let start_time = Instant::now();
-
-    let filter = ctx.arguments[0].unwrap_mask();
-    let pattern = ctx.arguments[1].unwrap_pattern();
-
-    let mut operation = worldedit_start_operation(ctx.plot, ctx.player_idx);
-    capture_undo(
-        ctx.plot,
-        ctx.player_idx,
-        ctx.get_player().first_position.unwrap(),
-        ctx.get_player().second_position.unwrap(),
-    );
+/// Sets every block in the area between `first_pos` and `second_pos` that is part of the
+/// shape to the pattern, as one operation with a single undo record. Returns the number of
+/// blocks that were changed.
+fn fill_shape(
+    plot: &mut Plot,
+    player: usize,
+    first_pos: BlockPos,
+    second_pos: BlockPos,
+    pattern: &WorldEditPattern,
+    in_shape: impl Fn(BlockPos) -> bool,
+) -> usize {
+    // Shapes around the player can reach past the plot
+    let (first_pos, second_pos) = match plot.clip_to_build_area(first_pos, second_pos) {
+        Some(area) => area,
+        None => return 0,
+    };
+    let mut operation = WorldEditOperation::new(first_pos, second_pos);
+    capture_undo(plot, player, first_pos, second_pos);
     for x in operation.x_range() {
         for y in operation.y_range() {
             for z in operation.z_range() {
                 let block_pos = BlockPos::new(x, y, z);
+                if !in_shape(block_pos) {
+                    continue;
+                }
+                let block_id = pattern.pick().get_id();
 
-                if filter.matches(ctx.plot.get_block(block_pos)) {
-                    let block_id = pattern.pick().get_id();
-
-                    if ctx.plot.set_block_raw(block_pos, block_id) {
-                        operation.update_block(block_pos);
-                    }
+                if plot.set_block_raw(block_pos, block_id) {
+                    operation.update_block(block_pos);
                 }
             }
         }
     }
 
     let blocks_updated = operation.blocks_updated();
-    worldedit_send_operation(ctx.plot, operation);
+    worldedit_send_operation(plot, operation);
+    blocks_updated
+}
+
+/// Fills the shape with the pattern in the first argument
+fn execute_shape(
+    mut ctx: CommandExecuteContext,
+    first_pos: BlockPos,
+    second_pos: BlockPos,
+    in_shape: impl Fn(BlockPos) -> bool,
+) {
+    let start_time = Instant::now();
+    let pattern = ctx.arguments[0].unwrap_pattern();
+
+    let blocks_updated = fill_shape(
+        ctx.plot,
+        ctx.player_idx,
+        first_pos,
+        second_pos,
+        pattern,
+        in_shape,
+    );
 
     ctx.get_player_mut().send_worldedit_message(&format!(
         "Operation completed: {} block(s) affected ({:?})",
@@ -820,24 +1197,227 @@ fn execute_replace(mut ctx: CommandExecuteContext) {
     ));
 }
 
-fn execute_count(mut ctx: CommandExecuteContext) {
-    let start_time = Instant::now();
+fn execute_walls(ctx: CommandExecuteContext) {
+    let first_pos = ctx.get_player().first_position.unwrap();
+    let second_pos = ctx.get_player().second_position.unwrap();
+    let start_pos = first_pos.min(second_pos);
+    let end_pos = first_pos.max(second_pos);
+    execute_shape(ctx, first_pos, second_pos, |pos| {
+        pos.x == start_pos.x || pos.x == end_pos.x || pos.z == start_pos.z || pos.z == end_pos.z
+    });
+}
 
-    let filter = ctx.arguments[0].unwrap_pattern();
+fn execute_faces(ctx: CommandExecuteContext) {
+    let first_pos = ctx.get_player().first_position.unwrap();
+    let second_pos = ctx.get_player().second_position.unwrap();
+    let start_pos = first_pos.min(second_pos);
+    let end_pos = first_pos.max(second_pos);
+    execute_shape(ctx, first_pos, second_pos, |pos| {
+        pos.x == start_pos.x
+            || pos.x == end_pos.x
+            || pos.y == start_pos.y
+            || pos.y == end_pos.y
+            || pos.z == start_pos.z
+            || pos.z == end_pos.z
+    });
+}
 
-    let mut blocks_counted = 0;
-    let operation = worldedit_start_operation(ctx.plot, ctx.player_idx);
-    for x in operation.x_range() {
-        for y in operation.y_range() {
-            for z in operation.z_range() {
-                let block_pos = BlockPos::new(x, y, z);
-                if filter.matches(ctx.plot.get_block(block_pos)) {
-                    blocks_counted += 1;
-                }
-            }
+/// Returns the blocks on the line from `start` to `end`, with one block for every step
+/// along the longest axis
+fn line_blocks(start: BlockPos, end: BlockPos) -> HashSet<BlockPos> {
+    let delta = end - start;
+    let steps = delta.x.abs().max(delta.y.abs()).max(delta.z.abs());
+    let lerp = |from: i32, delta: i32, i: i32| {
+        if steps == 0 {
+            from
+        } else {
+            from + (delta as f64 * i as f64 / steps as f64).round() as i32
         }
-    }
-
+    };
+    (0..=steps)
+        .map(|i| {
+            BlockPos::new(
+                lerp(start.x, delta.x, i),
+                lerp(start.y, delta.y, i),
+                lerp(start.z, delta.z, i),
+            )
+        })
+        .collect()
+}
+
+fn execute_line(ctx: CommandExecuteContext) {
+    let first_pos = ctx.get_player().first_position.unwrap();
+    let second_pos = ctx.get_player().second_position.unwrap();
+    let line = line_blocks(first_pos, second_pos);
+    execute_shape(ctx, first_pos, second_pos, |pos| line.contains(&pos));
+}
+
+/// The largest radius of cylinders and spheres
+const MAX_SHAPE_RADIUS: u32 = 512;
+/// The largest radius of brushes. Brushes are used many times in a row, and every use
+/// keeps an undo, so they are limited to the same radius as in WorldEdit.
+const MAX_BRUSH_RADIUS: u32 = 8;
+/// The most operations each player can undo
+const MAX_UNDO_HISTORY: usize = 15;
+/// The most blocks kept in a player's undo history. The oldest undos are dropped first,
+/// but the newest one is always kept.
+const MAX_UNDO_BLOCKS: usize = 64 << 20;
+
+/// Returns true if the offset from the center of a shape is inside of the given radius
+fn in_radius(x: i32, y: i32, z: i32, radius: u32) -> bool {
+    let (x, y, z) = (x as i64, y as i64, z as i64);
+    // The extra half block rounds the edges the same way as WorldEdit
+    let radius = radius as f64 + 0.5;
+    ((x * x + y * y + z * z) as f64) < radius * radius
+}
+
+/// Returns false and tells the player if the radius is too big for a shape
+fn check_radius(player: &mut Player, radius: u32) -> bool {
+    if radius > MAX_SHAPE_RADIUS {
+        player.send_error_message(&format!("The radius can be at most {}.", MAX_SHAPE_RADIUS));
+        return false;
+    }
+    true
+}
+
+fn cylinder(mut ctx: CommandExecuteContext, hollow: bool) {
+    let radius = ctx.arguments[1].unwrap_uint();
+    if !check_radius(ctx.get_player_mut(), radius) {
+        return;
+    }
+    // Anything taller than the plot is cut off anyway
+    let height = ctx.arguments[2]
+        .unwrap_uint()
+        .clamp(1, PLOT_GEOMETRY.height as u32) as i32;
+    let center = BlockPos::new(
+        ctx.get_player().x.floor() as i32,
+        ctx.get_player().y.floor() as i32,
+        ctx.get_player().z.floor() as i32,
+    );
+    let r = radius as i32;
+    let first_pos = BlockPos::new(center.x - r, center.y, center.z - r);
+    let second_pos = BlockPos::new(center.x + r, center.y + height - 1, center.z + r);
+    execute_shape(ctx, first_pos, second_pos, |pos| {
+        let (x, z) = (pos.x - center.x, pos.z - center.z);
+        in_radius(x, 0, z, radius)
+            && !(hollow
+                && in_radius(x + 1, 0, z, radius)
+                && in_radius(x - 1, 0, z, radius)
+                && in_radius(x, 0, z + 1, radius)
+                && in_radius(x, 0, z - 1, radius))
+    });
+}
+
+/// Returns the corners of the area a sphere covers and whether a block is part of it
+fn sphere_shape(
+    center: BlockPos,
+    radius: u32,
+    hollow: bool,
+) -> (BlockPos, BlockPos, impl Fn(BlockPos) -> bool) {
+    let r = radius as i32;
+    let first_pos = BlockPos::new(center.x - r, center.y - r, center.z - r);
+    let second_pos = BlockPos::new(center.x + r, center.y + r, center.z + r);
+    let in_shape = move |pos: BlockPos| {
+        let (x, y, z) = (pos.x - center.x, pos.y - center.y, pos.z - center.z);
+        in_radius(x, y, z, radius)
+            && !(hollow
+                && in_radius(x + 1, y, z, radius)
+                && in_radius(x - 1, y, z, radius)
+                && in_radius(x, y + 1, z, radius)
+                && in_radius(x, y - 1, z, radius)
+                && in_radius(x, y, z + 1, radius)
+                && in_radius(x, y, z - 1, radius))
+    };
+    (first_pos, second_pos, in_shape)
+}
+
+fn sphere(mut ctx: CommandExecuteContext, hollow: bool) {
+    let radius = ctx.arguments[1].unwrap_uint();
+    if !check_radius(ctx.get_player_mut(), radius) {
+        return;
+    }
+    let center = BlockPos::new(
+        ctx.get_player().x.floor() as i32,
+        ctx.get_player().y.floor() as i32,
+        ctx.get_player().z.floor() as i32,
+    );
+    let (first_pos, second_pos, in_shape) = sphere_shape(center, radius, hollow);
+    execute_shape(ctx, first_pos, second_pos, in_shape);
+}
+
+fn execute_cyl(ctx: CommandExecuteContext) {
+    cylinder(ctx, false);
+}
+
+fn execute_hcyl(ctx: CommandExecuteContext) {
+    cylinder(ctx, true);
+}
+
+fn execute_sphere(ctx: CommandExecuteContext) {
+    sphere(ctx, false);
+}
+
+fn execute_hsphere(ctx: CommandExecuteContext) {
+    sphere(ctx, true);
+}
+
+fn execute_replace(mut ctx: CommandExecuteContext) {
+    let start_time = Instant::now();
+
+    let filter = ctx.arguments[0].unwrap_mask();
+    let pattern = ctx.arguments[1].unwrap_pattern();
+
+    let mut operation = worldedit_start_operation(ctx.plot, ctx.player_idx);
+    capture_undo(
+        ctx.plot,
+        ctx.player_idx,
+        ctx.get_player().first_position.unwrap(),
+        ctx.get_player().second_position.unwrap(),
+    );
+    for x in operation.x_range() {
+        for y in operation.y_range() {
+            for z in operation.z_range() {
+                let block_pos = BlockPos::new(x, y, z);
+
+                if filter.matches(ctx.plot.get_block(block_pos)) {
+                    let block_id = pattern.pick().get_id();
+
+                    if ctx.plot.set_block_raw(block_pos, block_id) {
+                        operation.update_block(block_pos);
+                    }
+                }
+            }
+        }
+    }
+
+    let blocks_updated = operation.blocks_updated();
+    worldedit_send_operation(ctx.plot, operation);
+
+    ctx.get_player_mut().send_worldedit_message(&format!(
+        "Operation completed: {} block(s) affected ({:?})",
+        blocks_updated,
+        start_time.elapsed()
+    ));
+}
+
+fn execute_count(mut ctx: CommandExecuteContext) {
+    let start_time = Instant::now();
+
+    let filter = ctx.arguments[0].unwrap_mask();
+
+    let mut blocks_counted = 0;
+    let operation = worldedit_start_operation(ctx.plot, ctx.player_idx);
+    for x in operation.x_range() {
+        for y in operation.y_range() {
+            for z in operation.z_range() {
+                let block_pos = BlockPos::new(x, y, z);
+                if filter.matches(ctx.plot.get_block(block_pos)) {
+                    blocks_counted += 1;
+                }
+            }
+        }
+    }
+
     ctx.get_player_mut().send_worldedit_message(&format!(
         "Counted {} block(s) ({:?})",
         blocks_counted,
@@ -934,7 +1514,11 @@ fn paste_clipboard(plot: &mut Plot, cb: &WorldEditClipboard, pos: BlockPos, igno
                 if ignore_air && entry == 0 {
                     continue;
                 }
-                plot.set_block_raw(BlockPos::new(x, y, z), entry);
+                let pos = BlockPos::new(x, y, z);
+                // Pasting can reach past the selection, so keep it out of roads and borders
+                if plot.in_build_area(pos) {
+                    plot.set_block_raw(pos, entry);
+                }
             }
         }
     }
@@ -957,20 +1541,41 @@ fn paste_clipboard(plot: &mut Plot, cb: &WorldEditClipboard, pos: BlockPos, igno
             y: pos.y + offset_y,
             z: pos.z + offset_z,
         };
-        plot.set_block_entity(new_pos, block_entity.clone());
+        if plot.in_build_area(new_pos) {
+            plot.set_block_entity(new_pos, block_entity.clone());
+        }
     }
 }
 
 fn capture_undo(plot: &mut Plot, player: usize, first_pos: BlockPos, second_pos: BlockPos) {
-    let origin = first_pos.min(second_pos);
-    let cb = create_clipboard(plot, origin, first_pos, second_pos);
+    capture_undo_areas(plot, player, &[(first_pos, second_pos)]);
+}
+
+/// Captures several areas that are undone together by one `//undo`
+fn capture_undo_areas(plot: &mut Plot, player: usize, areas: &[(BlockPos, BlockPos)]) {
+    let areas = areas
+        .iter()
+        .map(|&(first_pos, second_pos)| {
+            let origin = first_pos.min(second_pos);
+            (
+                origin,
+                create_clipboard(plot, origin, first_pos, second_pos),
+            )
+        })
+        .collect();
     let undo = WorldEditUndo {
-        clipboard: cb,
-        pos: origin,
+        areas,
         plot_x: plot.x,
         plot_z: plot.z,
     };
-    plot.players[player].worldedit_undo.push(undo);
+    let history = &mut plot.players[player].worldedit_undo;
+    history.push(undo);
+    while history.len() > MAX_UNDO_HISTORY
+        || (history.len() > 1
+            && history.iter().map(WorldEditUndo::volume).sum::<usize>() > MAX_UNDO_BLOCKS)
+    {
+        history.remove(0);
+    }
 }
 
 fn execute_copy(mut ctx: CommandExecuteContext) {
@@ -1089,21 +1694,158 @@ fn execute_paste(mut ctx: CommandExecuteContext) {
 pub(super) fn execute_load(plot: &mut Plot, player: usize, file_name: &str) {
     let start_time = Instant::now();
 
-    let clipboard = WorldEditClipboard::load_from_schematic(file_name);
-    match clipboard {
-        Some(cb) => {
-            plot.players[player].worldedit_clipboard = Some(cb);
-            plot.players[player].send_worldedit_message(&format!(
+    let player = &mut plot.players[player];
+    match schematic::load(file_name) {
+        Ok(schematic) => {
+            player.worldedit_clipboard = Some(schematic.clipboard);
+            player.send_worldedit_message(&format!(
                 "The schematic was loaded to your clipboard. Do //paste to birth it into the world. ({:?})",
                 start_time.elapsed()
             ));
+            if !schematic.unknown_blocks.is_empty() {
+                player.send_system_message(&format!(
+                    "These blocks aren't supported and were replaced with air: {}",
+                    schematic.unknown_blocks.join(", ")
+                ));
+            }
         }
-        None => {
-            plot.players[player].send_error_message("There was an error loading the schematic.");
+        Err(err) => {
+            player.send_error_message(&format!(
+                "There was an error loading the schematic: {}",
+                err
+            ));
         }
     }
 }
 
+const SCHEMATICS_PER_PAGE: usize = 10;
+
+fn execute_schem(mut ctx: CommandExecuteContext) {
+    let action = ctx.arguments[0].unwrap_string().to_owned();
+    let name = ctx.arguments[1].unwrap_string().to_owned();
+    if action != "list" && name.is_empty() {
+        ctx.get_player_mut()
+            .send_error_message(&format!("//schem {} <name>", action));
+        return;
+    }
+    match action.as_str() {
+        "load" => {
+            if ctx
+                .plot
+                .check_permission(ctx.player_idx, "mchprs.worldedit.load")
+            {
+                execute_load(ctx.plot, ctx.player_idx, &name);
+            }
+        }
+        "list" => {
+            let page = if name.is_empty() {
+                1
+            } else if let Ok(page) = name.parse::<usize>() {
+                page.max(1)
+            } else {
+                ctx.get_player_mut()
+                    .send_error_message(&format!("Invalid page: {}", name));
+                return;
+            };
+            let player = ctx.get_player_mut();
+            let schematics = match schematic::list() {
+                Ok(schematics) => schematics,
+                Err(err) => {
+                    player.send_error_message(&format!("Could not list schematics: {}", err));
+                    return;
+                }
+            };
+            if schematics.is_empty() {
+                player.send_worldedit_message("There are no schematics.");
+                return;
+            }
+            let pages = (schematics.len() - 1) / SCHEMATICS_PER_PAGE + 1;
+            let page = page.min(pages);
+            player.send_worldedit_message(&format!("Schematics (page {}/{}):", page, pages));
+            for entry in schematics
+                .iter()
+                .skip((page - 1) * SCHEMATICS_PER_PAGE)
+                .take(SCHEMATICS_PER_PAGE)
+            {
+                let modified = entry
+                    .modified
+                    .map(|modified| {
+                        DateTime::<Local>::from(modified)
+                            .format("%Y-%m-%d %H:%M")
+                            .to_string()
+                    })
+                    .unwrap_or_default();
+                player.send_system_message(&format!(
+                    "{} - {:.1} KB - {}",
+                    entry.name,
+                    entry.size as f64 / 1024.0,
+                    modified
+                ));
+            }
+        }
+        "info" => {
+            // Reading a schematic costs as much as loading it
+            if !ctx
+                .plot
+                .check_permission(ctx.player_idx, "mchprs.worldedit.load")
+            {
+                return;
+            }
+            let player = ctx.get_player_mut();
+            let schematic = match schematic::load(&name) {
+                Ok(schematic) => schematic,
+                Err(err) => {
+                    player.send_error_message(&format!(
+                        "There was an error loading the schematic: {}",
+                        err
+                    ));
+                    return;
+                }
+            };
+            let clipboard = &schematic.clipboard;
+            player.send_worldedit_message(&format!(
+                "{}: {}x{}x{} ({} blocks), {} block entities",
+                name,
+                clipboard.size_x,
+                clipboard.size_y,
+                clipboard.size_z,
+                clipboard.size_x * clipboard.size_y * clipboard.size_z,
+                clipboard.block_entities.len()
+            ));
+            let palette: Vec<String> = schematic
+                .palette
+                .iter()
+                .filter(|(_, count)| *count > 0)
+                .map(|(block, count)| format!("{}x {}", count, block))
+                .collect();
+            player.send_system_message(&format!("Palette: {}", palette.join(", ")));
+            if !schematic.unknown_blocks.is_empty() {
+                player.send_system_message(&format!(
+                    "Unsupported blocks: {}",
+                    schematic.unknown_blocks.join(", ")
+                ));
+            }
+        }
+        "delete" => {
+            if !ctx
+                .plot
+                .check_permission(ctx.player_idx, "mchprs.worldedit.schem.delete")
+            {
+                return;
+            }
+            let player = ctx.get_player_mut();
+            match schematic::delete(&name) {
+                Ok(()) => player.send_worldedit_message(&format!("{} was deleted.", name)),
+                Err(err) => player.send_error_message(&format!("Could not delete: {}", err)),
+            }
+        }
+        action => ctx.get_player_mut().send_error_message(&format!(
+            "Unknown action: {}. The actions are load, list, info and delete.",
+            action
+        )),
+    }
+}
+
 fn execute_stack(mut ctx: CommandExecuteContext) {
     let start_time = Instant::now();
 
@@ -1134,6 +1876,117 @@ fn execute_stack(mut ctx: CommandExecuteContext) {
     ));
 }
 
+fn parse_facing(name: &str) -> Option<BlockFacing> {
+    Some(match name {
+        "north" | "n" => BlockFacing::North,
+        "south" | "s" => BlockFacing::South,
+        "east" | "e" => BlockFacing::East,
+        "west" | "w" => BlockFacing::West,
+        "up" | "u" => BlockFacing::Up,
+        "down" | "d" => BlockFacing::Down,
+        _ => return None,
+    })
+}
+
+/// Returns the axis a direction is on, as 0 for x, 1 for y and 2 for z
+fn facing_axis(facing: BlockFacing) -> u8 {
+    match facing {
+        BlockFacing::East | BlockFacing::West => 0,
+        BlockFacing::Up | BlockFacing::Down => 1,
+        BlockFacing::North | BlockFacing::South => 2,
+    }
+}
+
+/// Returns how far each copy is moved from the previous one when stacking a selection of
+/// `size` with `spacing` blocks between the copies
+fn stack_offset(size: BlockPos, spacing: u32, directions: &[BlockFacing]) -> BlockPos {
+    directions
+        .iter()
+        .fold(BlockPos::new(0, 0, 0), |offset, direction| {
+            let size = match facing_axis(*direction) {
+                0 => size.x,
+                1 => size.y,
+                _ => size.z,
+            };
+            direction.offset_pos(offset, size + spacing as i32)
+        })
+}
+
+/// Returns where a corner of the selection ends up in copy `i` of a stack, or `None` if it
+/// would be outside of the range of positions
+fn stack_copy_pos(pos: BlockPos, offset: BlockPos, i: u32) -> Option<BlockPos> {
+    let i = i32::try_from(i).ok()?;
+    Some(BlockPos::new(
+        pos.x.checked_add(offset.x.checked_mul(i)?)?,
+        pos.y.checked_add(offset.y.checked_mul(i)?)?,
+        pos.z.checked_add(offset.z.checked_mul(i)?)?,
+    ))
+}
+
+fn execute_rstack(mut ctx: CommandExecuteContext) {
+    let start_time = Instant::now();
+
+    let stack_amt = ctx.arguments[0].unwrap_uint();
+    // Spacing this large already puts every copy outside of the plot
+    let spacing = ctx.arguments[1].unwrap_uint().min(u16::MAX as u32);
+    let directions = ctx.arguments[2].unwrap_vector();
+    let first_pos = ctx.get_player().first_position.unwrap();
+    let second_pos = ctx.get_player().second_position.unwrap();
+    let start_pos = first_pos.min(second_pos);
+    let end_pos = first_pos.max(second_pos);
+
+    let size = end_pos - start_pos;
+    let size = BlockPos::new(size.x + 1, size.y + 1, size.z + 1);
+    let offset = stack_offset(size, spacing, directions);
+
+    // Each copy is further away than the last, so the stack ends at the first copy that
+    // doesn't reach into the plot. Only the parts of the copies inside the plot can change.
+    let mut copies = Vec::new();
+    let mut areas = Vec::new();
+    for i in 1..=stack_amt {
+        let copy = stack_copy_pos(start_pos, offset, i);
+        let area = copy
+            .zip(stack_copy_pos(end_pos, offset, i))
+            .and_then(|(start, end)| ctx.plot.clip_to_build_area(start, end));
+        match copy.zip(area) {
+            Some((copy, area)) => {
+                copies.push(copy);
+                areas.push(area);
+            }
+            None => break,
+        }
+    }
+    if copies.is_empty() {
+        ctx.get_player_mut()
+            .send_error_message("None of the copies would be inside the plot.");
+        return;
+    }
+
+    let clipboard = create_clipboard(ctx.plot, start_pos, start_pos, end_pos);
+    // The whole stack is undone at once, instead of one copy at a time
+    capture_undo_areas(ctx.plot, ctx.player_idx, &areas);
+    let ignore_air = ctx.has_flag('a');
+    for pos in &copies {
+        paste_clipboard(ctx.plot, &clipboard, *pos, ignore_air);
+    }
+
+    if ctx.has_flag('e') {
+        let (result_start, result_end) = areas.iter().fold(
+            (start_pos, end_pos),
+            |(start, end), &(area_start, area_end)| (start.min(area_start), end.max(area_end)),
+        );
+        let player = ctx.get_player_mut();
+        player.worldedit_set_first_position(result_start.x, result_start.y, result_start.z);
+        player.worldedit_set_second_position(result_end.x, result_end.y, result_end.z);
+    }
+
+    ctx.get_player_mut().send_worldedit_message(&format!(
+        "Your selection was stacked {} time(s). ({:?})",
+        copies.len(),
+        start_time.elapsed()
+    ));
+}
+
 fn execute_undo(mut ctx: CommandExecuteContext) {
     if ctx.get_player().worldedit_undo.is_empty() {
         ctx.get_player_mut()
@@ -1146,7 +1999,9 @@ fn execute_undo(mut ctx: CommandExecuteContext) {
             .send_error_message("Cannot undo outside of your current plot.");
         return;
     }
-    paste_clipboard(ctx.plot, &undo.clipboard, undo.pos, false);
+    for (pos, clipboard) in undo.areas.iter().rev() {
+        paste_clipboard(ctx.plot, clipboard, *pos, false);
+    }
 }
 
 fn execute_sel(mut ctx: CommandExecuteContext) {
@@ -1177,6 +2032,643 @@ fn execute_pos2(mut ctx: CommandExecuteContext) {
     player.worldedit_set_second_position(x, y, z);
 }
 
+/// Moves the face of the selection in `direction` out by `amount`, or in if `amount` is
+/// negative. The face never moves in past the opposite face. Returns the new positions.
+fn move_selection_face(
+    first_pos: BlockPos,
+    second_pos: BlockPos,
+    direction: BlockFacing,
+    amount: i32,
+) -> (BlockPos, BlockPos) {
+    let axis = facing_axis(direction);
+    let coord = |pos: BlockPos| match axis {
+        0 => pos.x,
+        1 => pos.y,
+        _ => pos.z,
+    };
+    let positive = coord(direction.offset_pos(BlockPos::new(0, 0, 0), 1)) > 0;
+    let (first, second) = (coord(first_pos), coord(second_pos));
+    let first_is_face = if positive {
+        first >= second
+    } else {
+        first <= second
+    };
+    let (face, other) = if first_is_face {
+        (first, second)
+    } else {
+        (second, first)
+    };
+    let moved = if positive {
+        face.saturating_add(amount).max(other)
+    } else {
+        face.saturating_sub(amount).min(other)
+    };
+    let move_face = |mut pos: BlockPos| {
+        match axis {
+            0 => pos.x = moved,
+            1 => pos.y = moved,
+            _ => pos.z = moved,
+        }
+        pos
+    };
+    if first_is_face {
+        (move_face(first_pos), second_pos)
+    } else {
+        (first_pos, move_face(second_pos))
+    }
+}
+
+/// Sets both positions of the player's selection at once and updates the CUI
+fn set_selection(player: &mut Player, first_pos: BlockPos, second_pos: BlockPos) {
+    player.first_position = Some(first_pos);
+    player.second_position = Some(second_pos);
+    player.worldedit_send_cui(&format!(
+        "p|0|{}|{}|{}|0",
+        first_pos.x, first_pos.y, first_pos.z
+    ));
+    player.worldedit_send_cui(&format!(
+        "p|1|{}|{}|{}|0",
+        second_pos.x, second_pos.y, second_pos.z
+    ));
+}
+
+/// Returns the dimensions of the selection between both positions
+fn selection_size(first_pos: BlockPos, second_pos: BlockPos) -> BlockPos {
+    let size = first_pos.max(second_pos) - first_pos.min(second_pos);
+    BlockPos::new(size.x + 1, size.y + 1, size.z + 1)
+}
+
+/// Returns the amount argument of a selection command. Amounts past the size of the world
+/// are the same as moving to its edge, and they keep the coordinates from overflowing.
+fn selection_amount(ctx: &CommandExecuteContext) -> i32 {
+    ctx.arguments[0].unwrap_uint().min(MAX_SELECTION_AMOUNT) as i32
+}
+
+/// Moves the given faces of the selection out by `amount` and reports the change in size.
+/// The selection stays inside of the plot.
+fn resize_selection(ctx: &mut CommandExecuteContext, faces: &[BlockFacing], amount: i32) {
+    let mut first_pos = ctx.get_player().first_position.unwrap();
+    let mut second_pos = ctx.get_player().second_position.unwrap();
+    let old_volume = selection_volume(first_pos, second_pos);
+    for face in faces {
+        let (first, second) = move_selection_face(first_pos, second_pos, *face, amount);
+        first_pos = first;
+        second_pos = second;
+    }
+    let first_pos = ctx.plot.clamp_to_build_area(first_pos);
+    let second_pos = ctx.plot.clamp_to_build_area(second_pos);
+    let new_volume = selection_volume(first_pos, second_pos);
+
+    let player = ctx.get_player_mut();
+    set_selection(player, first_pos, second_pos);
+    // Keeping the selection in the plot can make it smaller even when expanding
+    let message = match new_volume.checked_sub(old_volume) {
+        Some(change) if amount >= 0 => format!("Region expanded {} block(s).", change),
+        _ => format!(
+            "Region contracted {} block(s).",
+            old_volume.saturating_sub(new_volume)
+        ),
+    };
+    player.send_worldedit_message(&message);
+}
+
+fn selection_volume(first_pos: BlockPos, second_pos: BlockPos) -> u64 {
+    let size = |a: i32, b: i32| (a as i64 - b as i64).unsigned_abs() + 1;
+    size(first_pos.x, second_pos.x)
+        .saturating_mul(size(first_pos.y, second_pos.y))
+        .saturating_mul(size(first_pos.z, second_pos.z))
+}
+
+/// Returns the faces `//outset` and `//inset` move, which the `h` and `v` flags limit
+fn outset_faces(ctx: &CommandExecuteContext) -> Vec<BlockFacing> {
+    let mut faces = Vec::new();
+    if !ctx.has_flag('v') {
+        faces.extend_from_slice(&[
+            BlockFacing::North,
+            BlockFacing::South,
+            BlockFacing::East,
+            BlockFacing::West,
+        ]);
+    }
+    if !ctx.has_flag('h') {
+        faces.extend_from_slice(&[BlockFacing::Up, BlockFacing::Down]);
+    }
+    faces
+}
+
+fn execute_expand(mut ctx: CommandExecuteContext) {
+    let amount = selection_amount(&ctx);
+    let direction = *ctx.arguments[1].unwrap_direction();
+    resize_selection(&mut ctx, &[direction], amount);
+}
+
+fn execute_contract(mut ctx: CommandExecuteContext) {
+    let amount = selection_amount(&ctx);
+    // Contracting towards a direction pulls in the face on the opposite side
+    let direction = match ctx.arguments[1].unwrap_direction() {
+        BlockFacing::North => BlockFacing::South,
+        BlockFacing::South => BlockFacing::North,
+        BlockFacing::East => BlockFacing::West,
+        BlockFacing::West => BlockFacing::East,
+        BlockFacing::Up => BlockFacing::Down,
+        BlockFacing::Down => BlockFacing::Up,
+    };
+    resize_selection(&mut ctx, &[direction], -amount);
+}
+
+fn execute_outset(mut ctx: CommandExecuteContext) {
+    let amount = selection_amount(&ctx);
+    let faces = outset_faces(&ctx);
+    resize_selection(&mut ctx, &faces, amount);
+}
+
+fn execute_inset(mut ctx: CommandExecuteContext) {
+    let amount = selection_amount(&ctx);
+    let faces = outset_faces(&ctx);
+    resize_selection(&mut ctx, &faces, -amount);
+}
+
+fn execute_shift(mut ctx: CommandExecuteContext) {
+    let amount = selection_amount(&ctx);
+    let direction = ctx.arguments[1].unwrap_direction();
+    let first_pos = direction.offset_pos(ctx.get_player().first_position.unwrap(), amount);
+    let second_pos = direction.offset_pos(ctx.get_player().second_position.unwrap(), amount);
+    if ctx.plot.clamp_to_build_area(first_pos) != first_pos
+        || ctx.plot.clamp_to_build_area(second_pos) != second_pos
+    {
+        ctx.get_player_mut()
+            .send_error_message("The selection can't be shifted out of the plot.");
+        return;
+    }
+
+    let player = ctx.get_player_mut();
+    set_selection(player, first_pos, second_pos);
+    player.send_worldedit_message(&format!("Region shifted {} block(s).", amount));
+}
+
+fn execute_size(mut ctx: CommandExecuteContext) {
+    let first_pos = ctx.get_player().first_position.unwrap();
+    let second_pos = ctx.get_player().second_position.unwrap();
+    let size = selection_size(first_pos, second_pos);
+    let volume = selection_volume(first_pos, second_pos);
+
+    let player = ctx.get_player_mut();
+    player.send_worldedit_message(&format!(
+        "Position 1: ({}, {}, {})",
+        first_pos.x, first_pos.y, first_pos.z
+    ));
+    player.send_worldedit_message(&format!(
+        "Position 2: ({}, {}, {})",
+        second_pos.x, second_pos.y, second_pos.z
+    ));
+    player.send_worldedit_message(&format!("Size: {} x {} x {}", size.x, size.y, size.z));
+    player.send_worldedit_message(&format!("# of blocks: {}", volume));
+}
+
+/// The most blocks selection commands like `//expand` move the selection, which is the
+/// distance from the center of a Minecraft world to its border
+const MAX_SELECTION_AMOUNT: u32 = 30_000_000;
+
+/// How far away brushes reach when the player isn't clicking on a block
+const MAX_TOOL_DISTANCE: f64 = 300.0;
+
+/// A worldedit action bound to one of the player's hotbar slots. Patterns are kept as they
+/// were typed so the tool can be saved with the player.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum WorldEditTool {
+    /// Generates a sphere at the block the player right clicks
+    SphereBrush {
+        pattern: String,
+        radius: u32,
+        hollow: bool,
+    },
+    /// Right clicking replaces the block with the pattern and left clicking sets the pattern
+    /// to the clicked block
+    Replacer { pattern: String },
+    /// Shows the state and power level of the clicked block
+    Info,
+}
+
+/// Binds a tool to the slot the player is holding. Blocks can't be tools, because using
+/// them would place them.
+fn bind_tool(player: &mut Player, tool: Option<WorldEditTool>) -> bool {
+    let slot = player.selected_slot;
+    match &player.inventory[slot as usize + 36] {
+        Some(item) if item.item_type.is_block() => {
+            player.send_error_message("Blocks can't be used as tools.");
+            return false;
+        }
+        Some(item) if item.item_type == (Item::WEWand {}) => {
+            player.send_error_message("The wand can't be used as a tool.");
+            return false;
+        }
+        None => {
+            player.send_error_message("Hold the item you want to bind the tool to.");
+            return false;
+        }
+        _ => {}
+    }
+    match tool {
+        Some(tool) => player.worldedit_tools.insert(slot, tool),
+        None => player.worldedit_tools.remove(&slot),
+    };
+    true
+}
+
+fn execute_brush(mut ctx: CommandExecuteContext) {
+    let shape = ctx.arguments[0].unwrap_string().to_owned();
+    let pattern = ctx.arguments[1].unwrap_string().to_owned();
+    let radius = ctx.arguments[2].unwrap_uint();
+    let hollow = ctx.has_flag('h');
+
+    if shape != "sphere" {
+        ctx.get_player_mut()
+            .send_error_message(&format!("Unknown brush shape: {}", shape));
+        return;
+    }
+    if radius > MAX_BRUSH_RADIUS {
+        ctx.get_player_mut().send_error_message(&format!(
+            "The brush radius can be at most {}.",
+            MAX_BRUSH_RADIUS
+        ));
+        return;
+    }
+    if let Err(err) = WorldEditPattern::from_str(&pattern) {
+        ctx.get_player_mut().send_error_message(&err.to_string());
+        return;
+    }
+
+    let tool = WorldEditTool::SphereBrush {
+        pattern,
+        radius,
+        hollow,
+    };
+    let player = ctx.get_player_mut();
+    if bind_tool(player, Some(tool)) {
+        player.send_worldedit_message(&format!("Sphere brush shape equipped ({}).", radius));
+    }
+}
+
+fn execute_tool(mut ctx: CommandExecuteContext) {
+    let pattern = ctx.arguments[1].unwrap_string().to_owned();
+    let tool = ctx.arguments[0].unwrap_string().to_owned();
+    let (tool, message) = match tool.as_str() {
+        "replacer" => {
+            if let Err(err) = WorldEditPattern::from_str(&pattern) {
+                ctx.get_player_mut().send_error_message(&err.to_string());
+                return;
+            }
+            (
+                Some(WorldEditTool::Replacer { pattern }),
+                "Block replacer tool bound to your held item.",
+            )
+        }
+        "info" => (
+            Some(WorldEditTool::Info),
+            "Info tool bound to your held item.",
+        ),
+        "none" => (None, "Tool unbound from your held item."),
+        tool => {
+            ctx.get_player_mut().send_error_message(&format!(
+                "Unknown tool: {}. The tools are replacer, info and none.",
+                tool
+            ));
+            return;
+        }
+    };
+    let player = ctx.get_player_mut();
+    if bind_tool(player, tool) {
+        player.send_worldedit_message(message);
+    }
+}
+
+fn execute_clipboard(mut ctx: CommandExecuteContext) {
+    let action = ctx.arguments[0].unwrap_string().to_owned();
+    let name = ctx.arguments[1].unwrap_string().to_owned();
+    let share_name = ctx.arguments[2].unwrap_string().to_owned();
+    let player = ctx.get_player_mut();
+    let uuid = player.uuid;
+    match action.as_str() {
+        "save" => {
+            let clipboard = match &player.worldedit_clipboard {
+                Some(clipboard) => clipboard,
+                None => {
+                    player.send_error_message("Your clipboard is empty. Use //copy first.");
+                    return;
+                }
+            };
+            match clipboards::save(uuid, &name, clipboard, true) {
+                Ok(()) => {
+                    player.send_worldedit_message(&format!("Your clipboard was saved as {}.", name))
+                }
+                Err(err) => player.send_error_message(&format!("Could not save: {}", err)),
+            }
+        }
+        "load" => match clipboards::load(uuid, &name) {
+            Ok(clipboard) => {
+                player.worldedit_clipboard = Some(clipboard);
+                player.send_worldedit_message(&format!(
+                    "{} was loaded to your clipboard. Do //paste to birth it into the world.",
+                    name
+                ));
+            }
+            Err(err) => player.send_error_message(&format!("Could not load: {}", err)),
+        },
+        "share" => {
+            if name.is_empty() {
+                player.send_error_message("//clipboard share <player> [name]");
+                return;
+            }
+            let clipboard = match &player.worldedit_clipboard {
+                Some(clipboard) => clipboard,
+                None => {
+                    player.send_error_message("Your clipboard is empty. Use //copy first.");
+                    return;
+                }
+            };
+            let target = match database::get_cached_uuid(&name) {
+                Ok(Some(target)) => u128::from_str_radix(&target, 16).unwrap_or_default(),
+                Ok(None) => {
+                    player.send_error_message("Player not found!");
+                    return;
+                }
+                Err(err) => {
+                    player.send_error_message(&format!("Could not share: {}", err));
+                    return;
+                }
+            };
+            let share_name = if share_name.is_empty() {
+                player.username.clone()
+            } else {
+                share_name
+            };
+            // Players shouldn't be able to replace each other's saved clipboards
+            match clipboards::save(target, &share_name, clipboard, false) {
+                Ok(()) => player.send_worldedit_message(&format!(
+                    "Your clipboard was shared with {}. They can load it with //clipboard load {}.",
+                    name, share_name
+                )),
+                Err(err) => player.send_error_message(&format!("Could not share: {}", err)),
+            }
+        }
+        "list" => match clipboards::list(uuid) {
+            Ok(saved) if saved.is_empty() => {
+                player.send_worldedit_message("You don't have any saved clipboards.")
+            }
+            Ok(saved) => {
+                let used: u64 = saved.iter().map(|(_, size)| size).sum();
+                let names: Vec<_> = saved.into_iter().map(|(name, _)| name).collect();
+                player.send_worldedit_message(&format!(
+                    "Saved clipboards ({}/{}, {:.1}/{} MB): {}",
+                    names.len(),
+                    CONFIG.clipboard_quota,
+                    used as f64 / (1024.0 * 1024.0),
+                    CONFIG.clipboard_quota_mb,
+                    names.join(", ")
+                ));
+            }
+            Err(err) => player.send_error_message(&format!("Could not list: {}", err)),
+        },
+        "delete" => match clipboards::delete(uuid, &name) {
+            Ok(true) => player.send_worldedit_message(&format!("{} was deleted.", name)),
+            Ok(false) => {
+                player.send_error_message(&format!("There is no clipboard named {}.", name))
+            }
+            Err(err) => player.send_error_message(&format!("Could not delete: {}", err)),
+        },
+        action => player.send_error_message(&format!(
+            "Unknown action: {}. The actions are save, load, share, list and delete.",
+            action
+        )),
+    }
+}
+
+/// Returns the first block that isn't air in the direction the player is looking
+pub(super) fn raycast(plot: &Plot, player: usize) -> Option<BlockPos> {
+    let player = &plot.players[player];
+    let (yaw, pitch) = (
+        (player.yaw as f64).to_radians(),
+        (player.pitch as f64).to_radians(),
+    );
+    let direction = (
+        -yaw.sin() * pitch.cos(),
+        -pitch.sin(),
+        yaw.cos() * pitch.cos(),
+    );
+    // Start at the player's eyes
+    let start = (player.x, player.y + 1.62, player.z);
+    let step = 0.1;
+    let mut distance = 0.0;
+    while distance < MAX_TOOL_DISTANCE {
+        let pos = BlockPos::new(
+            (start.0 + direction.0 * distance).floor() as i32,
+            (start.1 + direction.1 * distance).floor() as i32,
+            (start.2 + direction.2 * distance).floor() as i32,
+        );
+        if plot.get_block(pos) != (Block::Air {}) {
+            return Some(pos);
+        }
+        distance += step;
+    }
+    None
+}
+
+/// Uses the tool bound to the slot the player is holding on the block at `pos`. Returns
+/// false if there is no tool bound to the slot.
+pub(super) fn use_tool(plot: &mut Plot, player: usize, pos: BlockPos, left_click: bool) -> bool {
+    let slot = plot.players[player].selected_slot;
+    let tool = match plot.players[player].worldedit_tools.get(&slot) {
+        Some(tool) => tool.clone(),
+        None => return false,
+    };
+    // Tools are saved with the player, so permissions that were taken away since the tool
+    // was bound need to be checked again
+    let nodes: &[&str] = match tool {
+        WorldEditTool::SphereBrush { .. } => &["mchprs.worldedit.brush", "mchprs.worldedit.sphere"],
+        _ => &["mchprs.worldedit.tool"],
+    };
+    if !nodes.iter().all(|node| plot.check_permission(player, node)) {
+        return true;
+    }
+    match tool {
+        WorldEditTool::Info => {
+            let block = plot.get_block(pos);
+            let power = BlockFace::values()
+                .iter()
+                .map(|face| block.get_redstone_power(plot, pos, *face))
+                .max()
+                .unwrap_or(0);
+            plot.players[player].send_worldedit_message(&format!(
+                "Block at ({}, {}, {}): {:?} (id {}), power level {}",
+                pos.x,
+                pos.y,
+                pos.z,
+                block,
+                block.get_id(),
+                power
+            ));
+        }
+        WorldEditTool::Replacer { .. } if left_click => {
+            let block = plot.get_block(pos);
+            plot.players[player].worldedit_tools.insert(
+                slot,
+                WorldEditTool::Replacer {
+                    pattern: format!("={}", block.get_id()),
+                },
+            );
+            plot.players[player]
+                .send_worldedit_message(&format!("Replacer pattern set to {:?}.", block));
+        }
+        WorldEditTool::Replacer { pattern } => {
+            if !plot.can_build(player) || !plot.in_build_area(pos) {
+                return true;
+            }
+            // Patterns were checked when the tool was bound
+            if let Ok(pattern) = WorldEditPattern::from_str(&pattern) {
+                plot.modified = true;
+                capture_undo(plot, player, pos, pos);
+                let block_id = pattern.pick().get_id();
+                plot.set_block_raw(pos, block_id);
+                plot.send_block_change(pos, block_id);
+            }
+        }
+        WorldEditTool::SphereBrush { .. } if left_click => {}
+        WorldEditTool::SphereBrush {
+            pattern,
+            radius,
+            hollow,
+        } => {
+            if !plot.can_build(player) {
+                plot.players[player]
+                    .send_error_message("You are not allowed to build in this plot.");
+                return true;
+            }
+            if let Ok(pattern) = WorldEditPattern::from_str(&pattern) {
+                // Brushes bound before the radius was limited can still be bigger
+                let radius = radius.min(MAX_BRUSH_RADIUS);
+                let (first_pos, second_pos, in_shape) = sphere_shape(pos, radius, hollow);
+                plot.modified = true;
+                fill_shape(plot, player, first_pos, second_pos, &pattern, in_shape);
+            }
+        }
+    }
+    true
+}
+
 fn execute_unimplemented(_ctx: CommandExecuteContext) {
     unimplemented!("Unimplimented worldedit command");
 }
+
+#[test]
+fn rstack_offsets() {
+    let size = BlockPos::new(1, 2, 3);
+    let offset = stack_offset(size, 0, &[BlockFacing::East]);
+    assert_eq!(offset, BlockPos::new(1, 0, 0));
+    let offset = stack_offset(size, 2, &[BlockFacing::Up, BlockFacing::North]);
+    assert_eq!(offset, BlockPos::new(0, 4, -5));
+    assert_eq!(
+        stack_copy_pos(BlockPos::new(1, 1, 1), offset, 3),
+        Some(BlockPos::new(1, 13, -14))
+    );
+    assert_eq!(
+        stack_copy_pos(BlockPos::new(0, 0, 0), offset, u32::MAX),
+        None
+    );
+}
+
+#[test]
+fn masks_match_block_types() {
+    let repeater = |delay| {
+        let mut block = Block::from_name("repeater").unwrap();
+        block.set_property("delay", delay);
+        block.set_property("facing", "north");
+        block
+    };
+    let sandstone = Block::from_name("sandstone").unwrap();
+
+    let mask = WorldEditMask::from_str("repeater").unwrap();
+    assert!(mask.matches(repeater("1")) && mask.matches(repeater("4")));
+    assert!(!mask.matches(sandstone));
+    let mask = WorldEditMask::from_str("white_wool").unwrap();
+    assert!(!mask.matches(Block::from_name("red_wool").unwrap()));
+
+    let mask = WorldEditMask::from_str("repeater[delay=4,facing=north]").unwrap();
+    assert!(mask.matches(repeater("4")));
+    assert!(!mask.matches(repeater("2")));
+
+    let mask = WorldEditMask::from_str("!air").unwrap();
+    assert!(mask.matches(sandstone) && !mask.matches(Block::Air {}));
+    let mask = WorldEditMask::from_str("#existing").unwrap();
+    assert!(mask.matches(repeater("1")) && !mask.matches(Block::Air {}));
+    let mask = WorldEditMask::from_str("#solid").unwrap();
+    assert!(mask.matches(sandstone) && !mask.matches(repeater("1")));
+}
+
+#[test]
+fn patterns_apply_properties() {
+    let pattern = WorldEditPattern::from_str("repeater[delay=3,facing=east],sandstone").unwrap();
+    let mut repeater = Block::from_name("repeater").unwrap();
+    repeater.set_property("delay", "3");
+    repeater.set_property("facing", "east");
+    assert_eq!(pattern.parts.len(), 2);
+    assert_eq!(Block::from_id(pattern.parts[0].block_id), repeater);
+
+    // Properties the block doesn't have, or values they can't have, aren't ignored
+    for invalid in &[
+        "repeater[dealy=4]",
+        "repeater[delay=9]",
+        "sandstone[lit=true]",
+    ] {
+        assert!(matches!(
+            WorldEditPattern::from_str(invalid),
+            Err(PatternParseError::InvalidProperty(_))
+        ));
+        assert!(matches!(
+            WorldEditMask::from_str(invalid),
+            Err(PatternParseError::InvalidProperty(_))
+        ));
+    }
+}
+
+#[test]
+fn selection_faces() {
+    let first_pos = BlockPos::new(0, 0, 5);
+    let second_pos = BlockPos::new(3, 2, 0);
+    assert_eq!(
+        move_selection_face(first_pos, second_pos, BlockFacing::East, 2),
+        (first_pos, BlockPos::new(5, 2, 0))
+    );
+    assert_eq!(
+        move_selection_face(first_pos, second_pos, BlockFacing::South, -2),
+        (BlockPos::new(0, 0, 3), second_pos)
+    );
+    // Contracting stops once the selection is a single block thick
+    assert_eq!(
+        move_selection_face(first_pos, second_pos, BlockFacing::Up, -10),
+        (first_pos, BlockPos::new(3, 0, 0))
+    );
+    assert_eq!(selection_volume(first_pos, second_pos), 4 * 3 * 6);
+    assert_eq!(
+        move_selection_face(first_pos, second_pos, BlockFacing::West, i32::MAX),
+        (BlockPos::new(-i32::MAX, 0, 5), second_pos)
+    );
+    assert_eq!(
+        move_selection_face(first_pos, second_pos, BlockFacing::East, i32::MAX),
+        (first_pos, BlockPos::new(i32::MAX, 2, 0))
+    );
+    let far = BlockPos::new(i32::MAX, i32::MAX, i32::MAX);
+    assert_eq!(
+        selection_volume(far, BlockPos::new(i32::MIN, 0, 0)),
+        u64::MAX
+    );
+}
+
+#[test]
+fn shape_blocks() {
+    let line = line_blocks(BlockPos::new(0, 0, 0), BlockPos::new(4, 2, -1));
+    assert_eq!(line.len(), 5);
+    assert!(line.contains(&BlockPos::new(2, 1, -1)));
+    assert!(line.contains(&BlockPos::new(4, 2, -1)));
+
+    assert!(in_radius(2, 0, 0, 2) && !in_radius(2, 2, 0, 2));
+}
@@ -0,0 +1,254 @@
+use crate::config::CONFIG;
+use crate::server::Message;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use log::{error, info, warn};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PACKET_RESPONSE_VALUE: i32 = 0;
+const PACKET_EXEC_COMMAND: i32 = 2;
+const PACKET_AUTH_RESPONSE: i32 = 2;
+const PACKET_AUTH: i32 = 3;
+
+/// The largest packet clients are allowed to send
+const MAX_PACKET_LENGTH: i32 = 4096;
+/// Responses longer than this are split into multiple packets
+const MAX_RESPONSE_BODY: usize = 4096;
+/// How long to wait for the output of a command, for example when a plot is lagging
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long clients have to log in before they are disconnected
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long logged in clients can stay connected without sending anything
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+/// The most clients that can be connected at the same time
+const MAX_CONNECTIONS: usize = 8;
+
+struct RconPacket {
+    id: i32,
+    ty: i32,
+    body: String,
+}
+
+impl RconPacket {
+    fn read(stream: &mut TcpStream) -> io::Result<RconPacket> {
+        let length = stream.read_i32::<LittleEndian>()?;
+        if !(10..=MAX_PACKET_LENGTH).contains(&length) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "invalid packet length",
+            ));
+        }
+        let id = stream.read_i32::<LittleEndian>()?;
+        let ty = stream.read_i32::<LittleEndian>()?;
+        let mut body = vec![0; length as usize - 8];
+        stream.read_exact(&mut body)?;
+        // The body is followed by two null bytes
+        body.truncate(body.len() - 2);
+        Ok(RconPacket {
+            id,
+            ty,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+
+    fn write(&self, stream: &mut TcpStream) -> io::Result<()> {
+        let mut buf = Vec::with_capacity(self.body.len() + 14);
+        buf.write_i32::<LittleEndian>(self.body.len() as i32 + 10)?;
+        buf.write_i32::<LittleEndian>(self.id)?;
+        buf.write_i32::<LittleEndian>(self.ty)?;
+        buf.extend_from_slice(self.body.as_bytes());
+        buf.extend_from_slice(&[0, 0]);
+        stream.write_all(&buf)
+    }
+}
+
+/// A client connected with the Source RCON protocol. Commands are run as server
+/// commands, the same ones that can be typed into the console.
+struct RconClient {
+    stream: TcpStream,
+    addr: SocketAddr,
+    authenticated: bool,
+    server_sender: Sender<Message>,
+}
+
+impl RconClient {
+    fn handle(&mut self) -> io::Result<()> {
+        loop {
+            let packet = RconPacket::read(&mut self.stream)?;
+            match packet.ty {
+                PACKET_AUTH => {
+                    self.authenticated =
+                        constant_time_eq(packet.body.as_bytes(), CONFIG.rcon_password.as_bytes());
+                    let id = if self.authenticated {
+                        info!("RCON client {} logged in", self.addr);
+                        self.stream.set_read_timeout(Some(IDLE_TIMEOUT))?;
+                        packet.id
+                    } else {
+                        warn!("RCON client {} used the wrong password", self.addr);
+                        -1
+                    };
+                    RconPacket {
+                        id,
+                        ty: PACKET_AUTH_RESPONSE,
+                        body: String::new(),
+                    }
+                    .write(&mut self.stream)?;
+                    // Clients get one try per connection, which makes guessing slower
+                    if !self.authenticated {
+                        return Ok(());
+                    }
+                }
+                PACKET_EXEC_COMMAND if self.authenticated => {
+                    let output = self.run_command(packet.body);
+                    self.send_response(packet.id, &output)?;
+                }
+                PACKET_EXEC_COMMAND => {
+                    RconPacket {
+                        id: -1,
+                        ty: PACKET_AUTH_RESPONSE,
+                        body: String::new(),
+                    }
+                    .write(&mut self.stream)?;
+                }
+                ty => {
+                    let output = format!("Unknown request {:x}", ty);
+                    self.send_response(packet.id, &output)?;
+                }
+            }
+        }
+    }
+
+    /// Sends the command to the server thread and collects its output
+    fn run_command(&self, command: String) -> String {
+        let (reply_tx, reply_rx) = mpsc::channel();
+        let source = format!("RCON client {}", self.addr);
+        let message = Message::ConsoleCommand(source, command, reply_tx);
+        if self.server_sender.send(message).is_err() {
+            return "The server is stopping.".to_owned();
+        }
+        let deadline = Instant::now() + COMMAND_TIMEOUT;
+        let mut lines = Vec::new();
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            match reply_rx.recv_timeout(timeout) {
+                Ok(line) => lines.push(line),
+                Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => {
+                    lines.push("Timed out waiting for the command to finish.".to_owned());
+                    break;
+                }
+            }
+        }
+        lines.join("\n")
+    }
+
+    fn send_response(&mut self, id: i32, output: &str) -> io::Result<()> {
+        let mut rest = output;
+        loop {
+            let mut len = rest.len().min(MAX_RESPONSE_BODY);
+            while !rest.is_char_boundary(len) {
+                len -= 1;
+            }
+            let (body, remaining) = rest.split_at(len);
+            RconPacket {
+                id,
+                ty: PACKET_RESPONSE_VALUE,
+                body: body.to_owned(),
+            }
+            .write(&mut self.stream)?;
+            if remaining.is_empty() {
+                return Ok(());
+            }
+            rest = remaining;
+        }
+    }
+}
+
+/// Compares the passwords in a time that doesn't depend on where they differ
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let len = a.len().max(b.len());
+    let mut diff = a.len() ^ b.len();
+    for i in 0..len {
+        let x = a.get(i).copied().unwrap_or(0);
+        let y = b.get(i).copied().unwrap_or(0);
+        diff |= (x ^ y) as usize;
+    }
+    diff == 0
+}
+
+fn listen(listener: TcpListener, server_sender: Sender<Message>) {
+    let connections = Arc::new(AtomicUsize::new(0));
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(err) => {
+                warn!("Error accepting RCON connection: {}", err);
+                continue;
+            }
+        };
+        let addr = match stream.peer_addr() {
+            Ok(addr) => addr,
+            Err(_) => continue,
+        };
+        if connections.load(Ordering::SeqCst) >= MAX_CONNECTIONS {
+            warn!("Too many RCON connections, disconnecting {}", addr);
+            continue;
+        }
+        if let Err(err) = stream.set_read_timeout(Some(AUTH_TIMEOUT)) {
+            warn!("Error setting RCON timeout for {}: {}", addr, err);
+            continue;
+        }
+        let mut client = RconClient {
+            stream,
+            addr,
+            authenticated: false,
+            server_sender: server_sender.clone(),
+        };
+        let connections = connections.clone();
+        connections.fetch_add(1, Ordering::SeqCst);
+        thread::spawn(move || {
+            // Errors here are almost always the client disconnecting or timing out
+            let _ = client.handle();
+            connections.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
+
+/// Starts listening for RCON clients on the configured address if RCON is enabled
+pub fn start(server_sender: Sender<Message>) {
+    if !CONFIG.rcon_enabled {
+        return;
+    }
+    if CONFIG.rcon_password.is_empty() {
+        warn!("RCON is enabled but rcon_password is empty. RCON will not be started.");
+        return;
+    }
+    let listener = match TcpListener::bind(&CONFIG.rcon_bind_address) {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!(
+                "Error binding RCON to {}: {}",
+                CONFIG.rcon_bind_address, err
+            );
+            return;
+        }
+    };
+    info!("RCON listening on {}", CONFIG.rcon_bind_address);
+    thread::Builder::new()
+        .name("RCON".to_owned())
+        .spawn(move || listen(listener, server_sender))
+        .unwrap();
+}
+
+#[test]
+fn rcon_password_compare() {
+    assert!(constant_time_eq(b"hunter2", b"hunter2"));
+    assert!(!constant_time_eq(b"hunter2", b"hunter3"));
+    assert!(!constant_time_eq(b"hunter2", b"hunter22"));
+    assert!(!constant_time_eq(b"", b"hunter2"));
+}
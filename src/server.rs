@@ -1,5 +1,6 @@
 use crate::chat::ChatComponent;
 use crate::config::CONFIG;
+use crate::metrics::METRICS;
 use crate::network::packets::clientbound::{
     C00DisconnectLogin, C00Response, C01Pong, C02LoginSuccess, C03SetCompression, C13WindowItems,
     C17PluginMessage, C24JoinGame, C24JoinGameBiomeEffects, C24JoinGameBiomeEffectsMoodSound,
@@ -13,15 +14,23 @@ use crate::network::packets::serverbound::{
 use crate::network::packets::{PacketEncoderExt, SlotData};
 use crate::network::{NetworkServer, NetworkState};
 use crate::player::{Gamemode, Player};
-use crate::plot::{self, commands::DECLARE_COMMANDS, database, Plot};
+use crate::plot::database::{self, Ban};
+use crate::plot::geometry::{PlotRegion, PLOT_GEOMETRY};
+use crate::plot::{self, commands::DECLARE_COMMANDS, Plot};
+use crate::{metrics, permissions, rcon};
 use backtrace::Backtrace;
 use bus::Bus;
+use chrono::{Local, TimeZone};
 use fern::colors::{Color, ColoredLevelConfig};
 use log::{error, info, warn};
 use serde_json::json;
 use std::collections::HashMap;
 use std::fs;
+use std::io::{self, BufRead};
+use std::net::IpAddr;
+use std::sync::atomic::Ordering;
 use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
 use std::time::{Duration, Instant};
 
 /// `Message` gets send from a plot thread to the server thread.
@@ -42,8 +51,27 @@ pub enum Message {
     PlayerUpdateGamemode(u128, Gamemode),
     /// This message is sent to the server thread when a plot unloads itself.
     PlotUnload(i32, i32),
+    /// This message is sent to the server thread after plots have been merged into the
+    /// region. The plots get reloaded as a single plot.
+    MergePlots(PlotRegion),
+    /// This message is sent to the server thread after a merged region has been split up.
+    /// The region gets reloaded as separate plots.
+    UnmergePlots(PlotRegion),
+    /// This message is sent to the server thread when a player claims a plot outside of
+    /// the plot they are in, for example with `/p auto`.
+    PlotClaimed(i32, i32),
     /// This message is sent to the server thread when a player runs /stop.
     Shutdown,
+    /// This message is sent to the server thread to kick a player, for example when they
+    /// get banned. It contains the uuid of the player and the reason.
+    KickPlayer(u128, String),
+    /// This message is sent to the server thread when an IP address is banned. Every
+    /// player connected from the address gets kicked with the reason.
+    KickIp(IpAddr, String),
+    /// This message is sent to the server thread when a command is typed into the console
+    /// or sent over RCON. It contains who sent the command, the command and a channel
+    /// for the output.
+    ConsoleCommand(String, String, Sender<String>),
 }
 
 /// `BroadcastMessage` gets broadcasted from the server thread to all the plot threads.
@@ -62,6 +90,9 @@ pub enum BroadcastMessage {
     PlayerLeft(u128),
     /// This message is broadcasted when a player changes their gamemode,
     PlayerUpdateGamemode(u128, Gamemode),
+    /// This message is broadcasted when the console runs save-all. Every plot saves
+    /// itself and its players.
+    Save,
     /// This message is broadcasted when the server is stopping, either through the stop
     /// command or through the ctrl+c handler.
     Shutdown,
@@ -73,6 +104,33 @@ pub enum BroadcastMessage {
 pub enum PrivMessage {
     PlayerEnterPlot(Player),
     PlayerTeleportOther(Player, String),
+    /// Sent to a plot that has to unload so it can be reloaded with a different layout.
+    Unload,
+    /// Sent to a newly merged plot to replace the roads between its plots with floor.
+    ClearMergedRoads,
+    /// Sent to a plot when one of its plots was claimed from another plot, so it can
+    /// reload its owner and wall.
+    PlotClaimed,
+    /// Kicks the player with the uuid from the plot. Contains the reason.
+    KickPlayer(u128, String),
+    /// Runs a server command in the plot, for example `rtps 100`. The output is sent
+    /// to the channel.
+    ConsoleCommand(String, Sender<String>),
+}
+
+/// Creates the message shown to banned players
+pub fn ban_message(header: &str, ban: &Ban) -> String {
+    let mut message = header.to_owned();
+    if let Some(reason) = &ban.reason {
+        message += &format!("\nReason: {}", reason);
+    }
+    let expires_at = ban
+        .expires_at
+        .and_then(|expires_at| Local.timestamp_opt(expires_at, 0).single());
+    if let Some(expires_at) = expires_at {
+        message += &format!("\nExpires: {}", expires_at.format("%Y-%m-%d %H:%M"));
+    }
+    message
 }
 
 /// This is the data that gets sent in the `PlayerJoinedInfo` broadcast message.
@@ -92,14 +150,25 @@ struct PlayerListEntry {
     uuid: u128,
     gamemode: Gamemode,
     skin: Option<String>,
+    ip: Option<IpAddr>,
 }
 
 struct PlotListEntry {
     plot_x: i32,
     plot_z: i32,
+    region: PlotRegion,
     priv_message_sender: mpsc::Sender<PrivMessage>,
 }
 
+/// Plots that are being reloaded after a merge or unmerge. Players that try to enter the
+/// region are held back until all the old plots have unloaded.
+struct PendingReload {
+    region: PlotRegion,
+    merge: bool,
+    unloading: Vec<(i32, i32)>,
+    players: Vec<Player>,
+}
+
 /// This represents a minecraft server
 pub struct MinecraftServer {
     network: NetworkServer,
@@ -108,6 +177,7 @@ pub struct MinecraftServer {
     plot_sender: Sender<Message>,
     online_players: HashMap<u128, PlayerListEntry>,
     running_plots: Vec<PlotListEntry>,
+    pending_reloads: Vec<PendingReload>,
 }
 
 impl MinecraftServer {
@@ -148,8 +218,13 @@ impl MinecraftServer {
         // Create world folders if they don't exist yet
         fs::create_dir_all("./world/players").unwrap();
         fs::create_dir_all("./world/plots").unwrap();
+        fs::create_dir_all("./world/clipboards").unwrap();
 
-        plot::database::init();
+        if let Err(err) = plot::database::init() {
+            error!("Error migrating the plot database: {}", err);
+            std::process::exit(1);
+        }
+        MinecraftServer::check_plot_geometry();
 
         let bind_addr = CONFIG.bind_address.clone();
 
@@ -163,6 +238,32 @@ impl MinecraftServer {
         })
         .expect("There was an error setting the ctrlc handler");
 
+        let console_sender = plot_tx.clone();
+        thread::Builder::new()
+            .name("Console".to_owned())
+            .spawn(move || {
+                let stdin = io::stdin();
+                for line in stdin.lock().lines() {
+                    let line = match line {
+                        Ok(line) => line,
+                        Err(_) => break,
+                    };
+                    let (reply_tx, reply_rx) = mpsc::channel();
+                    let message = Message::ConsoleCommand("Console".to_owned(), line, reply_tx);
+                    if console_sender.send(message).is_err() {
+                        break;
+                    }
+                    // Wait until the server and the plots are done with the command
+                    for output in reply_rx {
+                        info!("{}", output);
+                    }
+                }
+            })
+            .unwrap();
+
+        rcon::start(plot_tx.clone());
+        metrics::start();
+
         // Create server struct
         let mut server = MinecraftServer {
             network: NetworkServer::new(bind_addr),
@@ -171,25 +272,23 @@ impl MinecraftServer {
             plot_sender: plot_tx,
             online_players: HashMap::new(),
             running_plots: Vec::new(),
+            pending_reloads: Vec::new(),
         };
 
         // Load the spawn area plot on server start
         // This plot should be always active
-        let (spawn_tx, spawn_rx) = mpsc::channel();
-        Plot::load_and_run(
-            0,
-            0,
-            server.broadcaster.add_rx(),
-            server.plot_sender.clone(),
-            spawn_rx,
-            true,
-            None,
-        );
-        server.running_plots.push(PlotListEntry {
-            plot_x: 0,
-            plot_z: 0,
-            priv_message_sender: spawn_tx,
+        server.start_plot(0, 0, None);
+
+        // Load the plots that have asked to be kept loaded
+        let keep_loaded_plots = database::get_keep_loaded_plots().unwrap_or_else(|err| {
+            error!("Error getting the plots to keep loaded: {}", err);
+            Vec::new()
         });
+        for (plot_x, plot_z) in keep_loaded_plots {
+            if server.running_plot(plot_x, plot_z).is_none() {
+                server.start_plot(plot_x, plot_z, None);
+            }
+        }
 
         info!("Done! Start took {:?}", start_time.elapsed());
 
@@ -217,6 +316,219 @@ impl MinecraftServer {
         if let Some(index) = index {
             self.running_plots.remove(index);
         }
+        for reload in &mut self.pending_reloads {
+            reload.unloading.retain(|&plot| plot != (plot_x, plot_z));
+        }
+        self.finish_reloads();
+    }
+
+    /// Asks the plot the player is in to kick them
+    fn kick_player(&mut self, uuid: u128, reason: String) {
+        if let Some(player) = self.online_players.get(&uuid) {
+            if let Some(plot) = self.running_plot(player.plot_x, player.plot_z) {
+                let _ = plot
+                    .priv_message_sender
+                    .send(PrivMessage::KickPlayer(uuid, reason));
+            }
+        }
+    }
+
+    /// Returns the running plot that contains the plot at `plot_x` and `plot_z`
+    fn running_plot(&self, plot_x: i32, plot_z: i32) -> Option<&PlotListEntry> {
+        self.running_plots
+            .iter()
+            .find(|p| p.region.contains(plot_x, plot_z))
+    }
+
+    /// Unloads the running plots that overlap the region so they can be loaded again with
+    /// their new layout.
+    fn reload_plots(&mut self, region: PlotRegion, merge: bool) {
+        let mut unloading = Vec::new();
+        for plot in &self.running_plots {
+            if plot.region.intersects(&region) {
+                let _ = plot.priv_message_sender.send(PrivMessage::Unload);
+                unloading.push((plot.plot_x, plot.plot_z));
+            }
+        }
+        self.pending_reloads.push(PendingReload {
+            region,
+            merge,
+            unloading,
+            players: Vec::new(),
+        });
+        self.finish_reloads();
+    }
+
+    /// Loads the regions of pending reloads whose old plots have all unloaded and sends
+    /// the players that were waiting for them.
+    fn finish_reloads(&mut self) {
+        let (finished, pending) = self
+            .pending_reloads
+            .drain(..)
+            .partition(|r: &PendingReload| r.unloading.is_empty());
+        self.pending_reloads = pending;
+        for reload in finished {
+            if reload.merge {
+                self.start_plot(reload.region.min_x, reload.region.min_z, None);
+                let plot = self.running_plots.last().unwrap();
+                let _ = plot.priv_message_sender.send(PrivMessage::ClearMergedRoads);
+            } else if reload.region.contains(0, 0) {
+                // The spawn plot has to keep running
+                self.start_plot(0, 0, None);
+            }
+            for player in reload.players {
+                self.send_player_to_plot(player, false);
+            }
+        }
+    }
+
+    /// Returns the online player whose name is `username`, ignoring case
+    fn find_online_player(&self, username: &str) -> Option<&PlayerListEntry> {
+        self.online_players
+            .values()
+            .find(|p| p.username.eq_ignore_ascii_case(username))
+    }
+
+    /// Handles a command from the server console or an RCON client. `source` is logged as
+    /// the sender of the command and every line of output is sent to `reply`.
+    fn handle_console_command(&mut self, source: &str, line: &str, reply: Sender<String>) {
+        let mut args: Vec<&str> = line.split_whitespace().collect();
+        if args.is_empty() {
+            return;
+        }
+        let command = args.remove(0).trim_start_matches('/');
+        info!("{} issued server command: {}", source, line.trim());
+        let send = |message: String| {
+            let _ = reply.send(message);
+        };
+        match command {
+            "help" => {
+                send("Server commands:".to_owned());
+                send("  list - Lists the online players".to_owned());
+                send("  kick <player> [reason] - Kicks a player".to_owned());
+                send("  say <message> - Sends a message to every player".to_owned());
+                send("  plots - Lists the running plots".to_owned());
+                send(
+                    "  plot <x> <z> <command> - Runs rtps, radvance, save or info in a plot"
+                        .to_owned(),
+                );
+                send("  op <player>, deop <player> - Gives or takes operator status".to_owned());
+                send("  save-all - Saves every plot and player".to_owned());
+                send("  stop - Stops the server".to_owned());
+            }
+            "list" => {
+                let mut players: Vec<&PlayerListEntry> = self.online_players.values().collect();
+                players.sort_by(|a, b| a.username.cmp(&b.username));
+                send(format!(
+                    "There are {} of a max of {} players online",
+                    players.len(),
+                    CONFIG.max_players
+                ));
+                for player in players {
+                    send(format!(
+                        "  {} in plot {},{}",
+                        player.username, player.plot_x, player.plot_z
+                    ));
+                }
+            }
+            "kick" if !args.is_empty() => {
+                let uuid = match self.find_online_player(args[0]) {
+                    Some(player) => player.uuid,
+                    None => {
+                        send("Player not found!".to_owned());
+                        return;
+                    }
+                };
+                let reason = if args.len() > 1 {
+                    args[1..].join(" ")
+                } else {
+                    "Kicked by an operator".to_owned()
+                };
+                send(format!("Kicked {}: {}", args[0], reason));
+                self.kick_player(uuid, reason);
+            }
+            "say" if !args.is_empty() => {
+                let message = args.join(" ");
+                info!("[Server] {}", message);
+                self.broadcaster.broadcast(BroadcastMessage::Chat(
+                    0,
+                    ChatComponent::from_legacy_text(format!("&d[Server] {}", message)),
+                ));
+                send(format!("[Server] {}", message));
+            }
+            "plots" => {
+                send(format!("Running plots: {}", self.running_plots.len()));
+                for plot in &self.running_plots {
+                    let players = self
+                        .online_players
+                        .values()
+                        .filter(|p| plot.region.contains(p.plot_x, p.plot_z))
+                        .count();
+                    send(format!("  {} with {} players", plot.region, players));
+                }
+            }
+            "plot" if args.len() >= 3 => {
+                let (plot_x, plot_z) = match (args[0].parse(), args[1].parse()) {
+                    (Ok(plot_x), Ok(plot_z)) => (plot_x, plot_z),
+                    _ => {
+                        send("Unable to parse plot coordinates!".to_owned());
+                        return;
+                    }
+                };
+                match self.running_plot(plot_x, plot_z) {
+                    Some(plot) => {
+                        // The plot sends its output to `reply` itself
+                        let _ = plot.priv_message_sender.send(PrivMessage::ConsoleCommand(
+                            args[2..].join(" "),
+                            reply.clone(),
+                        ));
+                    }
+                    None => send(format!("Plot {},{} is not running!", plot_x, plot_z)),
+                }
+            }
+            "op" | "deop" if args.len() == 1 => {
+                let username = args[0];
+                let uuid = match self.find_online_player(username) {
+                    Some(player) => Ok(format!("{:032x}", player.uuid)),
+                    None => database::get_cached_uuid(username).map(|uuid| {
+                        uuid.unwrap_or_else(|| {
+                            format!("{:032x}", Player::generate_offline_uuid(username))
+                        })
+                    }),
+                };
+                let result = uuid.and_then(|uuid| {
+                    if command == "op" {
+                        database::add_op(&uuid, username)
+                    } else {
+                        database::remove_op(&uuid)
+                    }
+                });
+                permissions::invalidate_cache();
+                send(match (command, result) {
+                    ("op", Ok(true)) => format!("Made {} an operator", username),
+                    ("op", Ok(false)) => format!("{} is already an operator", username),
+                    (_, Ok(true)) => format!("{} is no longer an operator", username),
+                    (_, Ok(false)) => format!("{} isn't an operator", username),
+                    (_, Err(err)) => {
+                        error!("Error updating the operators: {}", err);
+                        "There was an error accessing the plot database.".to_owned()
+                    }
+                });
+            }
+            "save-all" => {
+                self.broadcaster.broadcast(BroadcastMessage::Save);
+                send("Saving all plots and players...".to_owned());
+            }
+            "stop" => {
+                send("Stopping the server...".to_owned());
+                drop(reply);
+                self.graceful_shutdown();
+            }
+            "kick" | "say" | "plot" | "op" | "deop" => {
+                send("Missing arguments! Type \"help\" for usage.".to_owned())
+            }
+            _ => send("Unknown command. Type \"help\" for a list of commands.".to_owned()),
+        }
     }
 
     fn graceful_shutdown(&mut self) {
@@ -234,9 +546,88 @@ impl MinecraftServer {
         std::process::exit(0);
     }
 
+    /// Makes sure the configured plot size matches the size the world was created with.
+    /// Changing the plot size of an existing world would corrupt every saved plot.
+    fn check_plot_geometry() {
+        let configured = (PLOT_GEOMETRY.width, PLOT_GEOMETRY.depth);
+        let saved = database::get_plot_size().unwrap_or_else(|err| {
+            error!("Error reading the plot size from the database: {}", err);
+            std::process::exit(1);
+        });
+        let saved = saved.or_else(|| {
+            // Worlds created before the plot size was configurable are always 256x256
+            let has_plots = fs::read_dir("./world/plots")
+                .map(|mut dir| dir.next().is_some())
+                .unwrap_or(false);
+            if has_plots {
+                Some((256, 256))
+            } else {
+                None
+            }
+        });
+        match saved {
+            Some(saved) if saved != configured => {
+                error!(
+                    "This world was created with {}x{} plots but the config specifies {}x{} plots.",
+                    saved.0, saved.1, configured.0, configured.1
+                );
+                error!("Change plot_width and plot_depth back or use a new world.");
+                std::process::exit(1);
+            }
+            Some(_) => {}
+            None => {
+                if let Err(err) = database::set_plot_size(configured.0, configured.1) {
+                    error!("Error saving the plot size to the database: {}", err);
+                }
+            }
+        }
+    }
+
+    /// Returns the region the plot belongs to. If the database can't be read the plot is
+    /// loaded on its own, which is safe because merged plots are saved separately.
+    fn plot_region(plot_x: i32, plot_z: i32) -> PlotRegion {
+        database::get_plot_region(plot_x, plot_z).unwrap_or_else(|err| {
+            error!(
+                "Error getting the region of plot {},{}: {}",
+                plot_x, plot_z, err
+            );
+            PlotRegion::single(plot_x, plot_z)
+        })
+    }
+
+    /// Loads the region containing the plot and starts its thread. The region containing
+    /// the spawn plot is always kept running.
+    fn start_plot(&mut self, plot_x: i32, plot_z: i32, initial_player: Option<Player>) {
+        let region = MinecraftServer::plot_region(plot_x, plot_z);
+        let always_running = region.contains(0, 0);
+        let (priv_tx, priv_rx) = mpsc::channel();
+        Plot::load_and_run(
+            region,
+            self.broadcaster.add_rx(),
+            self.plot_sender.clone(),
+            priv_rx,
+            always_running,
+            initial_player,
+        );
+        self.running_plots.push(PlotListEntry {
+            plot_x: region.min_x,
+            plot_z: region.min_z,
+            region,
+            priv_message_sender: priv_tx,
+        });
+    }
+
     fn send_player_to_plot(&mut self, player: Player, new_entry: bool) {
-        let plot_x = (player.x as i32) >> 8;
-        let plot_z = (player.z as i32) >> 8;
+        let (plot_x, plot_z) =
+            PLOT_GEOMETRY.plot_at(player.x.floor() as i32, player.z.floor() as i32);
+        // Players are listed under the first plot of the region they are in
+        let (plot_x, plot_z) = match self.running_plot(plot_x, plot_z) {
+            Some(plot) => (plot.plot_x, plot.plot_z),
+            None => {
+                let region = MinecraftServer::plot_region(plot_x, plot_z);
+                (region.min_x, region.min_z)
+            }
+        };
 
         if new_entry {
             let player_list_entry = PlayerListEntry {
@@ -246,32 +637,28 @@ impl MinecraftServer {
                 gamemode: player.gamemode,
                 uuid: player.uuid,
                 skin: None,
+                ip: player.client.ip,
             };
             self.online_players.insert(player.uuid, player_list_entry);
         } else {
             self.update_player_entry(player.uuid, plot_x, plot_z);
         }
 
+        if let Some(reload) = self
+            .pending_reloads
+            .iter_mut()
+            .find(|r| r.region.contains(plot_x, plot_z))
+        {
+            reload.players.push(player);
+            return;
+        }
+
         let plot_loaded = self
             .running_plots
             .iter()
             .any(|p| p.plot_x == plot_x && p.plot_z == plot_z);
         if !plot_loaded {
-            let (priv_tx, priv_rx) = mpsc::channel();
-            Plot::load_and_run(
-                plot_x,
-                plot_z,
-                self.broadcaster.add_rx(),
-                self.plot_sender.clone(),
-                priv_rx,
-                false,
-                Some(player),
-            );
-            self.running_plots.push(PlotListEntry {
-                plot_x,
-                plot_z,
-                priv_message_sender: priv_tx,
-            });
+            self.start_plot(plot_x, plot_z, Some(player));
         } else {
             let plot_list_entry = self
                 .running_plots
@@ -284,20 +671,75 @@ impl MinecraftServer {
         }
     }
 
+    /// Returns the reason the player isn't allowed to join, or `None` if they are.
+    /// Operators can join even if the server is full or they aren't whitelisted.
+    fn check_login(&self, uuid: u128, ip: Option<IpAddr>) -> database::Result<Option<String>> {
+        let uuid = format!("{:032x}", uuid);
+        if let Some(ban) = database::get_player_ban(&uuid)? {
+            return Ok(Some(ban_message("You are banned from this server.", &ban)));
+        }
+        if let Some(ip) = ip {
+            if let Some(ban) = database::get_ip_ban(&ip.to_string())? {
+                return Ok(Some(ban_message(
+                    "Your IP address is banned from this server.",
+                    &ban,
+                )));
+            }
+        }
+        if database::is_op(&uuid)? {
+            return Ok(None);
+        }
+        if CONFIG.whitelist && !database::is_whitelisted(&uuid)? {
+            return Ok(Some("You are not whitelisted on this server!".to_owned()));
+        }
+        if self.online_players.len() as i64 >= CONFIG.max_players {
+            return Ok(Some("The server is full!".to_owned()));
+        }
+        Ok(None)
+    }
+
     fn handle_player_login(&mut self, client_idx: usize, login_start: S00LoginStart) {
+        let username = login_start.name;
+        let client = &self.network.handshaking_clients[client_idx];
+        let uuid = client
+            .uuid
+            .unwrap_or_else(|| Player::generate_offline_uuid(&username));
+        // Players listed in `ops` are made operators so the first operator can be added
+        // without access to the console
+        if CONFIG.ops.contains(&username) {
+            match database::add_op(&format!("{:032x}", uuid), &username) {
+                Ok(true) => {
+                    info!(
+                        "Made {} an operator because they are listed in ops",
+                        username
+                    );
+                    permissions::invalidate_cache();
+                }
+                Ok(false) => {}
+                Err(err) => error!("Error making {} an operator: {}", username, err),
+            }
+        }
+        let rejection = self.check_login(uuid, client.ip).unwrap_or_else(|err| {
+            error!("Error checking if {} can join: {}", username, err);
+            Some("There was an error checking if you can join.".to_owned())
+        });
+        if let Some(reason) = rejection {
+            info!("{} was not allowed to join: {}", username, reason);
+            let client = &mut self.network.handshaking_clients[client_idx];
+            let disconnect = C00DisconnectLogin {
+                reason: json!({ "text": reason }).to_string(),
+            }
+            .encode();
+            client.send_packet(&disconnect);
+            client.close_connection();
+            return;
+        }
+
         let clients = &mut self.network.handshaking_clients;
-        clients[client_idx].username = Some(login_start.name);
+        clients[client_idx].username = Some(username.clone());
         let set_compression = C03SetCompression { threshold: 256 }.encode();
         clients[client_idx].send_packet(&set_compression);
         clients[client_idx].set_compressed(true);
-        let username = if let Some(name) = &clients[client_idx].username {
-            name.clone()
-        } else {
-            Default::default()
-        };
-        let uuid = clients[client_idx]
-            .uuid
-            .unwrap_or_else(|| Player::generate_offline_uuid(&username));
 
         let login_success = C02LoginSuccess {
             uuid,
@@ -511,7 +953,17 @@ impl MinecraftServer {
                     uuid: player.uuid,
                     skin: None,
                 };
-                database::ensure_user(format!("{:032x}", player.uuid), &player.username);
+                let uuid = format!("{:032x}", player.uuid);
+                let ip = player.client.ip.map(|ip| ip.to_string());
+                if let Err(err) = database::ensure_user(uuid.clone(), &player.username)
+                    .and_then(|_| database::touch_user_seen(uuid.clone()))
+                    .and_then(|_| match ip {
+                        Some(ip) => database::set_user_ip(&uuid, &ip),
+                        None => Ok(()),
+                    })
+                {
+                    error!("Error saving {} to the database: {}", player.username, err);
+                }
                 self.broadcaster
                     .broadcast(BroadcastMessage::PlayerJoinedInfo(player_join_info));
                 self.send_player_to_plot(player, true);
@@ -520,10 +972,35 @@ impl MinecraftServer {
                 if let Some((_, player)) = self.online_players.remove_entry(&uuid) {
                     info!("{} left the game", player.username);
                 }
+                if let Err(err) = database::touch_user_seen(format!("{:032x}", uuid)) {
+                    error!("Error updating the last seen time of a player: {}", err);
+                }
                 self.broadcaster
                     .broadcast(BroadcastMessage::PlayerLeft(uuid));
             }
             Message::PlotUnload(plot_x, plot_z) => self.handle_plot_unload(plot_x, plot_z),
+            Message::KickPlayer(uuid, reason) => self.kick_player(uuid, reason),
+            Message::KickIp(ip, reason) => {
+                let uuids: Vec<u128> = self
+                    .online_players
+                    .values()
+                    .filter(|player| player.ip == Some(ip))
+                    .map(|player| player.uuid)
+                    .collect();
+                for uuid in uuids {
+                    self.kick_player(uuid, reason.clone());
+                }
+            }
+            Message::ConsoleCommand(source, line, reply) => {
+                self.handle_console_command(&source, &line, reply)
+            }
+            Message::MergePlots(region) => self.reload_plots(region, true),
+            Message::UnmergePlots(region) => self.reload_plots(region, false),
+            Message::PlotClaimed(plot_x, plot_z) => {
+                if let Some(plot) = self.running_plot(plot_x, plot_z) {
+                    let _ = plot.priv_message_sender.send(PrivMessage::PlotClaimed);
+                }
+            }
             Message::ChatInfo(uuid, username, message) => {
                 info!("<{}> {}", username, message);
                 self.broadcaster.broadcast(BroadcastMessage::Chat(
@@ -587,7 +1064,11 @@ impl MinecraftServer {
     }
 
     fn update(&mut self) {
-        while let Ok(message) = self.receiver.try_recv() {
+        let messages: Vec<Message> = self.receiver.try_iter().collect();
+        METRICS
+            .server_queue_depth
+            .store(messages.len(), Ordering::Relaxed);
+        for message in messages {
             self.handle_message(message);
         }
         self.network.update();
@@ -597,6 +1078,16 @@ impl MinecraftServer {
                 packet.handle(self, client);
             }
         }
+        // Remove clients that were disconnected before they could join
+        self.network
+            .handshaking_clients
+            .retain(|client| client.alive);
+        METRICS
+            .handshaking_clients
+            .store(self.network.handshaking_clients.len(), Ordering::Relaxed);
+        METRICS
+            .online_players
+            .store(self.online_players.len(), Ordering::Relaxed);
     }
 }
 
@@ -624,6 +1115,9 @@ impl ServerBoundPacketHandler for MinecraftServer {
             let split: Vec<&str> = handshake.server_address.split('\u{0}').collect();
             if split.len() == 3 || split.len() == 4 {
                 client.uuid = u128::from_str_radix(split[2], 16).ok();
+                if let Ok(ip) = split[1].parse() {
+                    client.ip = Some(ip);
+                }
             } else {
                 let disconnect = C00DisconnectLogin {
                     reason: json!({
@@ -1,6 +1,8 @@
 pub mod storage;
+#[cfg(test)]
+pub mod test_world;
 
-use crate::blocks::{Block, BlockEntity, BlockPos};
+use crate::blocks::{Block, BlockEntity, BlockPos, WireAlgorithm};
 use serde::{Deserialize, Serialize};
 use storage::Chunk;
 
@@ -45,6 +47,14 @@ pub trait World {
 
     /// Returns true if there is a tick entry with `pos`
     fn pending_tick_at(&mut self, pos: BlockPos) -> bool;
+
+    /// Called each time a block is updated because something next to it changed
+    fn on_block_update(&mut self, _pos: BlockPos, _block: Block) {}
+
+    /// Returns the algorithm redstone wire in this world uses to update its neighbors
+    fn wire_algorithm(&self) -> WireAlgorithm {
+        WireAlgorithm::default()
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
@@ -5,7 +5,7 @@ use crate::network::packets::clientbound::{
 };
 use crate::network::packets::PacketEncoder;
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::mem;
 
 #[derive(Clone)]
@@ -191,6 +191,35 @@ impl PalettedBitBuffer {
     pub fn entries(&self) -> usize {
         self.data.entries
     }
+
+    pub fn save(&self) -> PalettedBitBufferData {
+        PalettedBitBufferData {
+            entries: self.data.entries,
+            bits_per_entry: self.data.bits_per_entry as u8,
+            longs: self.data.longs.clone(),
+            palette: self.palette.clone(),
+        }
+    }
+
+    /// Loads a saved buffer. Returns `None` if the data doesn't fit together.
+    pub fn load_data(data: PalettedBitBufferData) -> Option<PalettedBitBuffer> {
+        if !(4..=15).contains(&data.bits_per_entry) {
+            return None;
+        }
+        let entries_per_long = 64 / data.bits_per_entry as usize;
+        if data.longs.len() * entries_per_long < data.entries {
+            return None;
+        }
+        let buffer =
+            PalettedBitBuffer::load(data.entries, data.bits_per_entry, data.longs, data.palette);
+        if buffer.use_palette {
+            let palette_len = buffer.palette.len() as u32;
+            if (0..buffer.data.entries).any(|i| buffer.data.get_entry(i) >= palette_len) {
+                return None;
+            }
+        }
+        Some(buffer)
+    }
 }
 
 pub struct ChunkSection {
@@ -286,6 +315,19 @@ impl ChunkSection {
         }
     }
 
+    /// Removes every queued block change except the last one of each block
+    fn compact_multi_block(&mut self) {
+        let mut seen = HashSet::new();
+        let mut records: Vec<C3BMultiBlockChangeRecord> = self
+            .multi_block
+            .drain(..)
+            .rev()
+            .filter(|record| seen.insert((record.x, record.y, record.z)))
+            .collect();
+        records.reverse();
+        self.multi_block = records;
+    }
+
     fn drain_multi_block(
         &mut self,
         chunk_x: i32,
@@ -461,7 +503,9 @@ impl Chunk {
         }
     }
 
-    pub fn generate(layers: i32, x: i32, z: i32) -> Chunk {
+    /// Generates the bottom `layers` layers of a chunk using `block_at`, which receives
+    /// the world coordinates of each block and returns its block state id.
+    pub fn generate(layers: i32, x: i32, z: i32, block_at: impl Fn(i32, i32, i32) -> u32) -> Chunk {
         let mut chunk = Chunk {
             sections: BTreeMap::new(),
             x,
@@ -474,15 +518,9 @@ impl Chunk {
                 for rz in 0..16 {
                     let block_x = (x << 4) | rx;
                     let block_z = (z << 4) | rz;
-
-                    if block_x % 256 == 0
-                        || block_z % 256 == 0
-                        || (block_x + 1) % 256 == 0
-                        || (block_z + 1) % 256 == 0
-                    {
-                        chunk.set_block(rx as u32, ry as u32, rz as u32, 4495); // Stone Bricks
-                    } else {
-                        chunk.set_block(rx as u32, ry as u32, rz as u32, 246); // Sandstone
+                    let block = block_at(block_x, ry, block_z);
+                    if block != 0 {
+                        chunk.set_block(rx as u32, ry as u32, rz as u32, block);
                     }
                 }
             }
@@ -490,6 +528,14 @@ impl Chunk {
         chunk
     }
 
+    /// Drops the queued changes of blocks that changed again later, so only the final
+    /// state of each block gets sent to players
+    pub fn compact_multi_block(&mut self) {
+        for section in self.sections.values_mut() {
+            section.compact_multi_block();
+        }
+    }
+
     pub fn drain_multi_block(&mut self) -> Vec<C3BMultiBlockChange> {
         let mut packets = Vec::new();
         for (y, section) in &mut self.sections {
@@ -511,8 +557,49 @@ struct ChunkSectionData {
     entries: usize,
 }
 
+/// A `PalettedBitBuffer` as it is saved to disk
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PalettedBitBufferData {
+    entries: usize,
+    bits_per_entry: u8,
+    longs: Vec<u64>,
+    palette: Vec<u32>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ChunkData {
     sections: BTreeMap<u8, ChunkSectionData>,
     block_entities: HashMap<BlockPos, BlockEntity>,
 }
+
+#[test]
+fn multi_block_compaction() {
+    let mut chunk = Chunk::empty(0, 0);
+    chunk.set_block(1, 2, 3, 5);
+    chunk.set_block(1, 2, 3, 6);
+    chunk.set_block(4, 2, 3, 7);
+    chunk.set_block(1, 2, 3, 8);
+    chunk.compact_multi_block();
+    let packets = chunk.drain_multi_block();
+    assert_eq!(packets.len(), 1);
+    let changes: Vec<(u8, u32)> = packets[0]
+        .records
+        .iter()
+        .map(|record| (record.x, record.block_id))
+        .collect();
+    assert_eq!(changes, vec![(4, 7), (1, 8)]);
+}
+
+#[test]
+fn paletted_buffer_data() {
+    let mut buffer = PalettedBitBuffer::with_entries(100);
+    for i in 0..100 {
+        buffer.set_entry(i, i as u32 % 20 * 7);
+    }
+    let loaded = PalettedBitBuffer::load_data(buffer.save()).unwrap();
+    assert!((0..100).all(|i| loaded.get_entry(i) == i as u32 % 20 * 7));
+
+    let mut data = buffer.save();
+    data.longs.pop();
+    assert!(PalettedBitBuffer::load_data(data).is_none());
+}
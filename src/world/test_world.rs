@@ -0,0 +1,94 @@
+use super::storage::Chunk;
+use super::{TickEntry, TickPriority, World};
+use crate::blocks::{Block, BlockEntity, BlockPos, WireAlgorithm};
+use std::collections::HashMap;
+
+/// A world that only exists in memory, used to test circuits without loading a plot.
+/// Blocks that were never set are air.
+#[derive(Default)]
+pub struct TestWorld {
+    blocks: HashMap<BlockPos, u32>,
+    block_entities: HashMap<BlockPos, BlockEntity>,
+    to_be_ticked: Vec<TickEntry>,
+    pub wire_algorithm: WireAlgorithm,
+}
+
+impl TestWorld {
+    pub fn new(wire_algorithm: WireAlgorithm) -> TestWorld {
+        TestWorld {
+            wire_algorithm,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the positions of the pending ticks in the order they will run
+    pub fn pending_ticks(&self) -> Vec<BlockPos> {
+        self.to_be_ticked.iter().map(|entry| entry.pos).collect()
+    }
+}
+
+impl World for TestWorld {
+    fn get_block(&self, pos: BlockPos) -> Block {
+        Block::from_id(self.get_block_raw(pos))
+    }
+
+    fn get_block_raw(&self, pos: BlockPos) -> u32 {
+        self.blocks.get(&pos).copied().unwrap_or(0)
+    }
+
+    fn set_block(&mut self, pos: BlockPos, block: Block) -> bool {
+        self.set_block_raw(pos, block.get_id())
+    }
+
+    fn set_block_raw(&mut self, pos: BlockPos, block: u32) -> bool {
+        self.blocks.insert(pos, block) != Some(block)
+    }
+
+    fn delete_block_entity(&mut self, pos: BlockPos) {
+        self.block_entities.remove(&pos);
+    }
+
+    fn get_block_entity(&self, pos: BlockPos) -> Option<&BlockEntity> {
+        self.block_entities.get(&pos)
+    }
+
+    fn set_block_entity(&mut self, pos: BlockPos, block_entity: BlockEntity) {
+        self.block_entities.insert(pos, block_entity);
+    }
+
+    fn get_chunk(&self, _x: i32, _z: i32) -> Option<&Chunk> {
+        None
+    }
+
+    fn get_chunk_mut(&mut self, _x: i32, _z: i32) -> Option<&mut Chunk> {
+        None
+    }
+
+    fn tick(&mut self) {
+        for pending in &mut self.to_be_ticked {
+            pending.ticks_left = pending.ticks_left.saturating_sub(1);
+        }
+        while self.to_be_ticked.first().map(|e| e.ticks_left).unwrap_or(1) == 0 {
+            let entry = self.to_be_ticked.remove(0);
+            self.get_block(entry.pos).tick(self, entry.pos);
+        }
+    }
+
+    fn schedule_tick(&mut self, pos: BlockPos, delay: u32, priority: TickPriority) {
+        self.to_be_ticked.push(TickEntry {
+            pos,
+            ticks_left: delay,
+            tick_priority: priority,
+        });
+        self.to_be_ticked
+            .sort_by_key(|e| (e.ticks_left, e.tick_priority.clone()));
+    }
+
+    fn pending_tick_at(&mut self, pos: BlockPos) -> bool {
+        self.to_be_ticked.iter().any(|e| e.pos == pos)
+    }
+
+    fn wire_algorithm(&self) -> WireAlgorithm {
+        self.wire_algorithm
+    }
+}